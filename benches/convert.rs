@@ -0,0 +1,140 @@
+//! 转换大图片的耗时，运行 `cargo bench --bench convert`
+//!
+//! 黑白和 256 色模式按像素查表，这里同时给出库中的查表实现与原先逐个像素做浮点计算的
+//! 实现的对照耗时。
+
+use image::{DynamicImage, Rgba, RgbaImage};
+use pixel_says::bench::luminance;
+use pixel_says::{render_image_to_text_with_options, Color, Luminance, PixelMode, RenderOptions};
+use std::hint::black_box;
+use std::io::sink;
+use std::time::{Duration, Instant};
+
+const ROUNDS: u32 = 10;
+
+/// 运行 `ROUNDS` 次，返回平均耗时
+fn measure<F: FnMut()>(mut f: F) -> Duration {
+    f();
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        f();
+    }
+    start.elapsed() / ROUNDS
+}
+
+/// 原先的亮度计算：`Fast` 直接按权重做浮点乘法，`Linear` 查线性光强后用 `powf` 编码回 sRGB
+fn float_luminance(r: u8, g: u8, b: u8, model: Luminance) -> u8 {
+    let linear = |value: u8| {
+        let v = value as f32 / 255.0;
+        if v <= 0.04045 {
+            v / 12.92
+        } else {
+            ((v + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    match model {
+        Luminance::Fast => (0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32) as u8,
+        Luminance::Linear => {
+            let y = (0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b)).clamp(0.0, 1.0);
+            let encoded = if y <= 0.0031308 {
+                y * 12.92
+            } else {
+                1.055 * y.powf(1.0 / 2.4) - 0.055
+            };
+            (encoded * 255.0).round() as u8
+        }
+    }
+}
+
+/// 原先的 256 色映射：逐个分量计算立方体的一级和灰阶，再比较两者的距离
+fn float_ansi256(color: Color) -> u8 {
+    let channels = [color.r, color.g, color.b];
+    let cube_index = channels.map(|c| match c {
+        0..=47 => 0,
+        48..=114 => 1,
+        _ => (c - 35) / 40,
+    });
+    let cube = Color::from_ansi256(16 + 36 * cube_index[0] + 6 * cube_index[1] + cube_index[2]);
+    let average = channels.iter().map(|&c| c as u32).sum::<u32>() / 3;
+    let gray_index = (average.saturating_sub(3) / 10).min(23) as u8;
+    let gray = Color::from_ansi256(232 + gray_index);
+    let distance = |other: Color| {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+        d(color.r, other.r) + d(color.g, other.g) + d(color.b, other.b)
+    };
+    if distance(gray) < distance(cube) {
+        232 + gray_index
+    } else {
+        16 + 36 * cube_index[0] + 6 * cube_index[1] + cube_index[2]
+    }
+}
+
+fn main() {
+    let frame = RgbaImage::from_fn(1920, 1080, |x, y| {
+        Rgba([(x % 256) as u8, (y % 256) as u8, ((x + y) % 256) as u8, 255])
+    });
+
+    for (name, mode, luminance) in [
+        (
+            "monochrome (linear)",
+            PixelMode::Monochrome,
+            Luminance::Linear,
+        ),
+        ("monochrome (fast)", PixelMode::Monochrome, Luminance::Fast),
+        ("ansi256", PixelMode::Ansi256, Luminance::Linear),
+    ] {
+        let options = RenderOptions {
+            mode,
+            luminance,
+            max_cells: None,
+            ..RenderOptions::default()
+        };
+        let img = DynamicImage::ImageRgba8(frame.clone());
+        let elapsed = measure(|| {
+            render_image_to_text_with_options(img.clone(), &options, sink()).unwrap();
+        });
+        println!("{:<24} {:>10.2?}", name, elapsed);
+    }
+
+    // 只计算每个像素的亮度和 256 色编号
+    let pixels = frame.as_raw();
+    for (name, model) in [("linear", Luminance::Linear), ("fast", Luminance::Fast)] {
+        let float = measure(|| {
+            for pixel in pixels.chunks_exact(4) {
+                black_box(float_luminance(pixel[0], pixel[1], pixel[2], model));
+            }
+        });
+        let lookup = measure(|| {
+            for pixel in pixels.chunks_exact(4) {
+                black_box(luminance(pixel[0], pixel[1], pixel[2], model));
+            }
+        });
+        println!(
+            "{:<24} {:>10.2?}",
+            format!("luminance {} (float)", name),
+            float
+        );
+        println!(
+            "{:<24} {:>10.2?}",
+            format!("luminance {} (lookup)", name),
+            lookup
+        );
+    }
+
+    let colors: Vec<Color> = pixels
+        .chunks_exact(4)
+        .map(|pixel| Color::rgb(pixel[0], pixel[1], pixel[2]))
+        .collect();
+    let float = measure(|| {
+        for &color in &colors {
+            black_box(float_ansi256(color));
+        }
+    });
+    let lookup = measure(|| {
+        for &color in &colors {
+            black_box(color.to_ansi256());
+        }
+    });
+    println!("{:<24} {:>10.2?}", "to_ansi256 (float)", float);
+    println!("{:<24} {:>10.2?}", "to_ansi256 (lookup)", lookup);
+}
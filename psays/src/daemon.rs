@@ -0,0 +1,209 @@
+//! Render server over a Unix domain socket.
+//!
+//! Every message in both directions is a 4-byte big-endian length followed by
+//! that many bytes of JSON. A request looks like
+//! `{"message": "hi", "image": "/path/to/sprite.png", "width": 30, "mode": "shade"}`
+//! where everything except `message` is optional; `"mascot": "cat"` may be
+//! given instead of `image`. `"options"` may carry a complete serialized
+//! `RenderOptions` that replaces the daemon's own defaults; `psays --socket`
+//! sends its options this way. The reply is either
+//! `{"output": "..."}` or `{"error": "..."}`. Decoded images are kept in memory
+//! and reloaded only when the file's modification time changes.
+
+use image::DynamicImage;
+use pixel_says::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs,
+    io::{self, Read, Write},
+    os::unix::{
+        fs::{FileTypeExt, MetadataExt, PermissionsExt},
+        net::{UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+    time::SystemTime,
+};
+
+/// Requests larger than this are rejected
+const MAX_FRAME: u32 = 1 << 20;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Request {
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mascot: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub think: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub border: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub options: Option<RenderOptions>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Response {
+    Output(String),
+    Error(String),
+}
+
+type Cache = Arc<Mutex<HashMap<PathBuf, (SystemTime, Arc<DynamicImage>)>>>;
+
+/// Default socket location, private to the current user where possible
+pub fn default_socket_path() -> PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => PathBuf::from(dir).join("psays.sock"),
+        None => {
+            let user = std::env::var("USER").unwrap_or_else(|_| "default".to_string());
+            std::env::temp_dir().join(format!("psays-{}.sock", user))
+        }
+    }
+}
+
+/// Serve render requests until the process is killed
+///
+/// `defaults` supplies every option a request doesn't override.
+pub fn serve(socket: &Path, defaults: RenderOptions) -> io::Result<()> {
+    // A stale socket from a previous run would make bind fail, but never
+    // remove anything that isn't a socket
+    if let Ok(metadata) = fs::symlink_metadata(socket) {
+        if !metadata.file_type().is_socket() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} exists and is not a socket", socket.display()),
+            ));
+        }
+        if UnixStream::connect(socket).is_err() {
+            fs::remove_file(socket)?;
+        }
+    }
+    let listener = UnixListener::bind(socket)?;
+    // The socket is created with the umask's permissions; under a shared
+    // temporary directory that would let other users send requests
+    fs::set_permissions(socket, fs::Permissions::from_mode(0o600))?;
+    let cache = Cache::default();
+    let defaults = Arc::new(defaults);
+
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let cache = Arc::clone(&cache);
+        let defaults = Arc::clone(&defaults);
+        thread::spawn(move || {
+            // A client hanging up early is not the server's problem
+            let _ = handle(stream, &cache, &defaults);
+        });
+    }
+    Ok(())
+}
+
+/// Send one request to a running daemon and return the rendered output
+pub fn request(socket: &Path, request: &Request) -> Result<String, Box<dyn Error>> {
+    // Anyone can create the default socket under a shared temporary
+    // directory, so only talk to a daemon run by the same user
+    let owner = fs::metadata(socket)
+        .map_err(|e| format!("Failed to connect to {}: {}", socket.display(), e))?
+        .uid();
+    // SAFETY: getuid has no preconditions and always succeeds
+    if owner != unsafe { libc::getuid() } {
+        return Err(format!("{} is owned by another user", socket.display()).into());
+    }
+    let mut stream = UnixStream::connect(socket)
+        .map_err(|e| format!("Failed to connect to {}: {}", socket.display(), e))?;
+    write_frame(&mut stream, &serde_json::to_vec(request)?)?;
+    match serde_json::from_slice(&read_frame(&mut stream)?)? {
+        Response::Output(output) => Ok(output),
+        Response::Error(error) => Err(error.into()),
+    }
+}
+
+fn handle(mut stream: UnixStream, cache: &Cache, defaults: &RenderOptions) -> io::Result<()> {
+    loop {
+        let frame = match read_frame(&mut stream) {
+            Ok(frame) => frame,
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        let response = match render(&frame, cache, defaults) {
+            Ok(output) => Response::Output(output),
+            Err(e) => Response::Error(e.to_string()),
+        };
+        write_frame(&mut stream, &serde_json::to_vec(&response)?)?;
+    }
+}
+
+fn render(frame: &[u8], cache: &Cache, defaults: &RenderOptions) -> Result<String, Box<dyn Error>> {
+    let request: Request = serde_json::from_slice(frame)?;
+    let mut options = request.options.unwrap_or_else(|| defaults.clone());
+    if let Some(width) = request.width {
+        options.max_width = width;
+    }
+    if let Some(mode) = &request.mode {
+        options.mode = mode.parse()?;
+    }
+    if request.think {
+        options.bubble = BubbleKind::Think;
+    }
+    if let Some(border) = &request.border {
+        options.bubble_style = border.parse()?;
+    }
+
+    let mut output = Vec::new();
+    if let Some(mascot) = &request.mascot {
+        say_with_mascot(mascot.parse()?, &request.message, &options, &mut output)?;
+        return Ok(String::from_utf8(output)?);
+    }
+    match &request.image {
+        Some(path) => {
+            let img = cached_image(path, cache, options.limits)?;
+            say_from_image_ref(&img, &request.message, &options, &mut output)?
+        }
+        None => say_with_options(&request.message, &options, &mut output)?,
+    };
+    Ok(String::from_utf8(output)?)
+}
+
+fn cached_image(path: &Path, cache: &Cache, limits: DecodeLimits) -> io::Result<Arc<DynamicImage>> {
+    let modified = fs::metadata(path)?.modified()?;
+    if let Some((time, img)) = cache.lock().unwrap().get(path) {
+        if *time == modified {
+            return Ok(Arc::clone(img));
+        }
+    }
+    let img = Arc::new(load_image(path, limits)?);
+    cache
+        .lock()
+        .unwrap()
+        .insert(path.to_path_buf(), (modified, Arc::clone(&img)));
+    Ok(img)
+}
+
+fn read_frame<R: Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let mut len = [0u8; 4];
+    reader.read_exact(&mut len)?;
+    let len = u32::from_be_bytes(len);
+    if len > MAX_FRAME {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Request too large",
+        ));
+    }
+    let mut frame = vec![0; len as usize];
+    reader.read_exact(&mut frame)?;
+    Ok(frame)
+}
+
+fn write_frame<W: Write>(writer: &mut W, frame: &[u8]) -> io::Result<()> {
+    writer.write_all(&(frame.len() as u32).to_be_bytes())?;
+    writer.write_all(frame)?;
+    writer.flush()
+}
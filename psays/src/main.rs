@@ -1,9 +1,15 @@
-use clap::{command, value_parser, Arg, ArgAction};
+use clap::{command, parser::ValueSource, value_parser, Arg, ArgAction};
+use image::DynamicImage;
 use pixel_says::*;
+#[cfg(unix)]
+mod daemon;
+#[cfg(unix)]
+mod resize;
+
 use std::{
     error::Error,
     fs,
-    io::{stderr, stdin, stdout, BufWriter, Read, Write},
+    io::{stderr, stdin, stdout, BufWriter, IsTerminal, Read, Write},
     path::PathBuf,
     process::exit,
     str,
@@ -13,6 +19,37 @@ use std::{
 const INPUT: &str = "Failed to read input to the program";
 const STDOUT: &str = "Failed to write stdout";
 const STDERR: &str = "Failed to write stderr";
+const MESSAGE: &str = "A message is required when the image is read from stdin";
+const SHEET: &str = "--sheet and --sheet-cell need an image given with --image";
+const NO_BUBBLE: &str = "--no-bubble needs an image given with --image or --mascot";
+const ANIMATE: &str = "--animate needs an image given with --image";
+const CAST: &str = "--cast records a single message";
+const PNG: &str = "--format png writes a binary image; give a file with -o";
+#[cfg(not(feature = "figlet"))]
+const BANNER: &str = "--banner needs psays built with the figlet feature; use --font instead";
+#[cfg(unix)]
+const DAEMON_BYTES: &str =
+    "Images read from stdin or downloaded from a URL can't be sent to the daemon";
+#[cfg(unix)]
+const DAEMON_SHEET: &str = "Sprite sheet frames can't be sent to the daemon";
+#[cfg(unix)]
+const DAEMON_OPTIONS: &str =
+    "--template, --var, --highlight, --banner and --font can't be sent to the daemon";
+#[cfg(unix)]
+const DAEMON_FORMAT: &str =
+    "The daemon only returns text, so --format png and ans can't be used with --socket";
+
+/// Bubble border presets accepted by --border
+const BORDERS: [&str; 4] = ["ascii", "box", "rounded", "double"];
+
+/// Where the pixel image comes from
+enum Image {
+    Path(PathBuf),
+    Bytes(Vec<u8>),
+    /// A frame cut out of a sprite sheet
+    Decoded(DynamicImage),
+    Mascot(Mascot),
+}
 
 fn main() {
     if let Err(ref e) = run() {
@@ -39,60 +76,1146 @@ fn run() -> Result<(), Box<dyn Error>> {
             Arg::new("WIDTH")
                 .long("width")
                 .short('w')
-                .help("Set the width of the text box")
-                .default_value("40")
-                .value_parser(value_parser!(usize)),
+                .help(
+                    "Set the width of the text box, or `auto` to fit the terminal \
+                     [env: PIXEL_SAYS_WIDTH] [default: 40]",
+                )
+                .value_name("N|auto")
+                .value_parser(parse_width),
+        )
+        .arg(
+            Arg::new("FIT")
+                .long("fit")
+                .help("Shrink the image and text box to fit the terminal's width or whole screen")
+                .value_parser(["width", "screen"]),
         )
         .arg(
             Arg::new("IMAGE")
                 .long("image")
                 .short('i')
-                .help("Path to the pixel image file")
+                .help(
+                    "Path to the pixel image file (or an http(s) URL when built with the http \
+                     feature), or - to read it from stdin [env: PIXEL_SAYS_IMAGE]",
+                )
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("MODE")
+                .long("mode")
+                .short('m')
+                .help(
+                    "Pixel mode: auto, truecolor, ansi256, ansi16, grayscale, monochrome, invert, \
+                     shade, ascii, quadrant or braille [env: PIXEL_SAYS_MODE] [default: auto]",
+                )
+                .value_parser(value_parser!(PixelMode)),
+        )
+        .arg(
+            Arg::new("COLUMNS")
+                .long("columns")
+                .short('c')
+                .help("Lay out multiple renders side by side in up to N columns")
+                .default_value("1")
+                .value_parser(value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("ANCHOR")
+                .long("anchor")
+                .help(
+                    "Point the bubble tail at pixel X,Y of the image (e.g. the mouth), \
+                     or at the image's center",
+                )
+                .value_name("X,Y|center")
+                .value_parser(parse_anchor),
+        )
+        .arg(
+            Arg::new("CENTER_BUBBLE")
+                .long("center-bubble")
+                .help("Center the bubble over a wider image")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("ALT_TEXT")
+                .long("alt-text")
+                .help("Describe the image and message for screen readers")
+                .value_parser(["append", "only"]),
+        )
+        .arg(
+            Arg::new("TEMPLATE")
+                .long("template")
+                .help(
+                    "Expand {hostname}, {user}, {date}, {uptime} and --var placeholders \
+                     in the message",
+                )
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("VAR")
+                .long("var")
+                .help("Set a template placeholder, implies --template")
+                .value_name("KEY=VALUE")
+                .action(ArgAction::Append)
+                .value_parser(parse_var),
+        )
+        .arg(
+            Arg::new("THINK")
+                .long("think")
+                .help("Draw a thought bubble instead of a speech bubble")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("ANSI")
+                .long("ansi")
+                .help(
+                    "Strip color codes from the message, keep them (e.g. from lolcat), \
+                     or reject them",
+                )
+                .default_value("strip")
+                .value_parser(["strip", "keep", "reject"]),
+        )
+        .arg(
+            Arg::new("WRAP")
+                .long("wrap")
+                .help(
+                    "Wrap each line, reflow paragraphs, or keep indentation and spacing \
+                     as written (for poems)",
+                )
+                .default_value("lines")
+                .value_parser(["lines", "paragraphs", "preserve"]),
+        )
+        .arg(
+            Arg::new("LAYOUT")
+                .long("layout")
+                .help("Put the bubble next to the image, or inside the image's frame")
+                .default_value("bubble")
+                .value_parser(["bubble", "enclosed"]),
+        )
+        .arg(
+            Arg::new("POSITION")
+                .long("position")
+                .help("Where the bubble goes relative to the image")
+                .default_value("above")
+                .value_parser(["above", "below", "left", "right"]),
+        )
+        .arg(
+            Arg::new("BORDER")
+                .long("border")
+                .help("Bubble border style")
+                .default_value("ascii")
+                .value_parser(BORDERS),
+        )
+        .arg(
+            Arg::new("PADDING")
+                .long("padding")
+                .help("Space between the text and the bubble border")
+                .value_name("H[,V]")
+                .value_parser(parse_padding),
+        )
+        .arg(
+            Arg::new("MARGIN")
+                .long("margin")
+                .help("Indent the whole output by N columns")
+                .default_value("0")
+                .value_parser(value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("MASCOT")
+                .long("mascot")
+                .help("Use a built-in mascot instead of an image file")
+                .conflicts_with("IMAGE")
+                .value_parser(Mascot::ALL.map(Mascot::name)),
+        )
+        .arg(
+            Arg::new("RANDOM")
+                .long("random")
+                .help("Pick a random image from --random=DIR, or a random built-in mascot")
+                .value_name("DIR")
+                .num_args(0..=1)
+                .require_equals(true)
+                .conflicts_with_all(["IMAGE", "MASCOT"])
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("SHEET")
+                .long("sheet")
+                .help("Treat --image as a sprite sheet with this many columns and rows")
+                .value_name("COLSxROWS")
+                .conflicts_with("SHEET_CELL")
+                .value_parser(parse_size),
+        )
+        .arg(
+            Arg::new("SHEET_CELL")
+                .long("sheet-cell")
+                .help("Treat --image as a sprite sheet of cells this many pixels in size")
+                .value_name("WIDTHxHEIGHT")
+                .value_parser(parse_size),
+        )
+        .arg(
+            Arg::new("CROP")
+                .long("crop")
+                .help("Only draw this region of the image, in pixels")
+                .value_name("X,Y,W,H")
+                .value_parser(parse_rect),
+        )
+        .arg(
+            Arg::new("THRESHOLD")
+                .long("threshold")
+                .help(
+                    "Brightness (0-255) above which monochrome pixels count as lit, \
+                     N% to keep roughly N percent of the pixels dark, \
+                     or `auto` to pick one from the image",
+                )
+                .value_name("N|N%|auto")
+                .value_parser(parse_threshold),
+        )
+        .arg(
+            Arg::new("SHADE_LEVELS")
+                .long("shade-levels")
+                .help(
+                    "Brightness steps between the shade mode characters: `even`, \
+                     `histogram` to spread the image evenly, or four values 0-255",
+                )
+                .value_name("even|histogram|A,B,C,D")
+                .value_parser(parse_shade_levels),
+        )
+        .arg(
+            Arg::new("LUMINANCE")
+                .long("luminance")
+                .help(
+                    "How to compute pixel brightness: `linear` is gamma-correct, \
+                     `fast` matches older versions",
+                )
+                .default_value("linear")
+                .value_parser(["linear", "fast"]),
+        )
+        .arg(
+            Arg::new("ALPHA_THRESHOLD")
+                .long("alpha-threshold")
+                .help("Treat pixels with less opacity than this (0-255) as transparent")
+                .default_value("128")
+                .value_parser(value_parser!(u8)),
+        )
+        .arg(
+            Arg::new("ALPHA_BLEND")
+                .long("alpha-blend")
+                .help(
+                    "Blend semi-transparent pixels onto this color instead of drawing them opaque",
+                )
+                .value_name("COLOR")
+                .value_parser(parse_color),
+        )
+        .arg(
+            Arg::new("MAX_SIZE")
+                .long("max-size")
+                .help("Shrink the image to at most this many pixels, or `none` to keep its size")
+                .value_name("WIDTHxHEIGHT|none")
+                .value_parser(parse_max_size),
+        )
+        .arg(
+            Arg::new("ASPECT")
+                .long("aspect")
+                .help("Correct for tall terminal cells: `auto` by mode, `off`, or a height factor")
+                .value_name("auto|off|FACTOR")
+                .value_parser(parse_aspect),
+        )
+        .arg(
+            Arg::new("SPRITE")
+                .long("sprite")
+                .help("Which sprite sheet frame to show, counting from 0 in reading order")
+                .default_value("0")
+                .value_parser(value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("NO_BUBBLE")
+                .long("no-bubble")
+                .help("Only draw the image, without a message")
+                .conflicts_with_all(["ANIMATE", "FILES", "TEXT"])
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("ANIMATE")
+                .long("animate")
+                .help("Play an animated GIF, APNG or WebP --image in place")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("LOOPS")
+                .long("loops")
+                .help("How many times --animate plays the animation [default: forever]")
+                .value_parser(value_parser!(u32)),
+        )
+        .arg(
+            Arg::new("CAST")
+                .long("cast")
+                .help("Write --animate as an asciinema .cast recording instead of playing it")
+                .requires("ANIMATE")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("BACKEND")
+                .long("backend")
+                .help("Draw the image as text, a Sixel bitmap or an iTerm2 inline image")
+                .default_value("text")
+                .value_parser(["text", "sixel", "iterm2"]),
+        )
+        .arg(
+            Arg::new("FORMAT")
+                .long("format")
+                .help(
+                    "Write ANSI text for a terminal, an HTML <pre> block, an SVG or a PNG image, \
+                     the grid of characters and colors as JSON for other programs to draw, or \
+                     classic ANSI art (16 colors, CP437, CRLF and a SAUCE record) for .ans \
+                     viewers [default: ans for -o FILE.ans, else ansi]",
+                )
+                .default_value("ansi")
+                .hide_default_value(true)
+                .value_parser(["ansi", "html", "svg", "png", "json", "ans"]),
+        )
+        .arg(
+            Arg::new("OUTPUT")
+                .short('o')
+                .long("output")
+                .help(
+                    "Write the output to FILE instead of stdout; a .ans FILE uses the classic \
+                     ANSI art conventions of --format ans",
+                )
+                .value_name("FILE")
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("COLOR")
+                .long("color")
+                .help(
+                    "When to use colors and other escape sequences; auto turns them off when \
+                     NO_COLOR is set or stdout is not a terminal, unless CLICOLOR_FORCE is set, \
+                     and always keeps them even when NO_COLOR is set",
+                )
+                .default_value("auto")
+                .value_parser(["auto", "always", "never"]),
+        )
+        .arg(
+            Arg::new("BRIGHTNESS")
+                .long("brightness")
+                .help("Brighten (up to 1) or darken (down to -1) the image before drawing")
+                .value_name("AMOUNT")
+                .allow_negative_numbers(true)
+                .default_value("0")
+                .value_parser(value_parser!(f32)),
+        )
+        .arg(
+            Arg::new("CONTRAST")
+                .long("contrast")
+                .help("Multiply the image contrast, e.g. 1.5 to read better on dark backgrounds")
+                .value_name("FACTOR")
+                .default_value("1")
+                .value_parser(value_parser!(f32)),
+        )
+        .arg(
+            Arg::new("SATURATION")
+                .long("saturation")
+                .help("Multiply the image saturation; 0 turns it gray")
+                .value_name("FACTOR")
+                .default_value("1")
+                .value_parser(value_parser!(f32)),
+        )
+        .arg(
+            Arg::new("HUE_ROTATE")
+                .long("hue-rotate")
+                .help("Rotate the hue of the image by this many degrees")
+                .value_name("DEGREES")
+                .allow_negative_numbers(true)
+                .default_value("0")
+                .value_parser(value_parser!(f32)),
+        )
+        .arg(
+            Arg::new("TINT")
+                .long("tint")
+                .help("Recolor the image in shades of this color, e.g. red for errors")
+                .value_name("COLOR")
+                .value_parser(parse_color),
+        )
+        .arg(
+            Arg::new("POSTERIZE")
+                .long("posterize")
+                .help("Reduce each color channel to N levels for a chunkier retro look")
+                .value_name("N")
+                .value_parser(value_parser!(u8).range(2..)),
+        )
+        .arg(
+            Arg::new("PALETTE")
+                .long("palette")
+                .help(
+                    "Only use the colors of a built-in palette (gameboy, nes, pico8), \
+                     a GIMP .gpl file or a file of hex colors",
+                )
+                .value_name("NAME|FILE")
+                .value_parser(parse_palette),
+        )
+        .arg(
+            Arg::new("DITHER")
+                .long("dither")
+                .help("Dither monochrome, invert, braille, ansi256 and ansi16 output")
+                .value_parser(["floyd-steinberg", "bayer2", "bayer4", "bayer8"]),
+        )
+        .arg(
+            Arg::new("BG_CELLS")
+                .long("bg-cells")
+                .help("Paint pixels with background colors instead of █ glyphs")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("HIGHLIGHT")
+                .long("highlight")
+                .help("Highlight text matching REGEX in the bubble, e.g. 'ERROR=#ff0000'")
+                .value_name("REGEX=COLOR")
+                .action(ArgAction::Append)
+                .value_parser(parse_highlight),
+        )
+        .arg(
+            Arg::new("TEXT_COLOR")
+                .long("text-color")
+                .help("Color the message text, e.g. '#ffaa00'")
+                .value_name("COLOR")
+                .value_parser(parse_color),
+        )
+        .arg(
+            Arg::new("RAINBOW")
+                .long("rainbow")
+                .help("Color the message with a rainbow gradient, like lolcat")
+                .conflicts_with("TEXT_COLOR")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("RAINBOW_FREQ")
+                .long("rainbow-freq")
+                .help("Degrees of hue change per column in the rainbow gradient")
+                .value_name("DEGREES")
+                .requires("RAINBOW")
+                .default_value("10")
+                .value_parser(value_parser!(f32)),
+        )
+        .arg(
+            Arg::new("RAINBOW_HUE")
+                .long("rainbow-hue")
+                .help("Hue of the first character in the rainbow gradient (0 is red)")
+                .value_name("DEGREES")
+                .requires("RAINBOW")
+                .default_value("0")
+                .value_parser(value_parser!(f32)),
+        )
+        .arg(
+            Arg::new("SPEAKER")
+                .long("speaker")
+                .help("Show a name tag in the bubble's top border")
+                .value_name("NAME"),
+        )
+        .arg(
+            Arg::new("BANNER")
+                .long("banner")
+                .help("Draw the message as large banner text with the built-in FIGlet font")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("FONT")
+                .long("font")
+                .help("Draw the message as banner text with a FIGlet font file, implies --banner")
+                .value_name("FILE.flf")
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("BOLD")
+                .long("bold")
+                .help("Draw the message text in bold")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("ITALIC")
+                .long("italic")
+                .help("Draw the message text in italics")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("CRLF")
+                .long("crlf")
+                .help("End lines with \\r\\n, for files read by Windows tools")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("TEE")
+                .long("tee")
+                .help("Also write the output to FILE")
+                .value_name("FILE")
+                .value_parser(value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("DAEMON")
+                .long("daemon")
+                .help("Serve render requests on a Unix socket, keeping decoded images in memory")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("SOCKET")
+                .long("socket")
+                .help(
+                    "Socket for --daemon to listen on; without --daemon, \
+                     send the render request to the daemon listening there",
+                )
                 .value_parser(value_parser!(PathBuf)),
         )
         .arg(Arg::new("TEXT").action(ArgAction::Append))
         .get_matches();
 
-    let width = *args.get_one::<usize>("WIDTH").unwrap();
+    // Environment variables provide the defaults for flags that aren't given
+    let env = EnvDefaults::from_env()?;
+    // `Some(None)` stands for `--width auto`
+    let width = args
+        .get_one::<Option<usize>>("WIDTH")
+        .copied()
+        .or(env.width.map(Some));
+    let mode = EnvDefaults {
+        mode: args
+            .get_one::<PixelMode>("MODE")
+            .cloned()
+            .or(env.mode.clone()),
+        ..env.clone()
+    }
+    .effective_mode()
+    .unwrap_or(PixelMode::Auto);
+    let vars = args.get_many::<(String, String)>("VAR");
+    let template = (args.get_flag("TEMPLATE") || vars.is_some()).then(|| {
+        let mut template = Template::system();
+        for (key, value) in vars.into_iter().flatten() {
+            template.set(key, value);
+        }
+        template
+    });
+    let banner = match args.get_one::<PathBuf>("FONT") {
+        Some(path) => Some(FigletFont::parse(&fs::read_to_string(path)?)?),
+        None if args.get_flag("BANNER") => Some(builtin_font()?),
+        None => None,
+    };
+    let columns = *args.get_one::<usize>("COLUMNS").unwrap();
+    let color = args.get_one::<String>("COLOR").map(String::as_str);
+    // An explicit --color always wins over NO_COLOR, the way CLICOLOR_FORCE wins
+    // over a pipe, so terminal detection must not see it
+    if color == Some("always") {
+        std::env::remove_var("NO_COLOR");
+    }
+    let format = match args.get_one::<String>("FORMAT").map(String::as_str) {
+        Some("ansi")
+            if args.value_source("FORMAT") == Some(ValueSource::DefaultValue)
+                && args.get_one::<PathBuf>("OUTPUT").is_some_and(|path| {
+                    path.extension()
+                        .is_some_and(|extension| extension.eq_ignore_ascii_case("ans"))
+                }) =>
+        {
+            Some("ans")
+        }
+        format => format,
+    };
+    let mut options = RenderOptions {
+        mode,
+        max_width: width.flatten().unwrap_or(40),
+        tail: args
+            .get_one::<TailAnchor>("ANCHOR")
+            .copied()
+            .unwrap_or_default(),
+        template,
+        backend: match (
+            format,
+            args.get_one::<String>("BACKEND").map(String::as_str),
+        ) {
+            (Some("html"), _) => OutputBackend::Html,
+            (Some("svg"), _) => OutputBackend::Svg,
+            (Some("png"), _) => OutputBackend::Png,
+            (Some("json"), _) => OutputBackend::Json,
+            (Some("ans"), _) => OutputBackend::Ans,
+            (_, Some("sixel")) => OutputBackend::Sixel,
+            (_, Some("iterm2")) => OutputBackend::Iterm2,
+            _ => OutputBackend::Text,
+        },
+        dither: match args.get_one::<String>("DITHER").map(String::as_str) {
+            Some("floyd-steinberg") => Dither::FloydSteinberg,
+            Some("bayer2") => Dither::Ordered(BayerSize::Two),
+            Some("bayer4") => Dither::Ordered(BayerSize::Four),
+            Some("bayer8") => Dither::Ordered(BayerSize::Eight),
+            _ => Dither::Off,
+        },
+        palette: args.get_one::<ColorPalette>("PALETTE").cloned(),
+        brightness: *args.get_one::<f32>("BRIGHTNESS").unwrap(),
+        contrast: *args.get_one::<f32>("CONTRAST").unwrap(),
+        saturation: *args.get_one::<f32>("SATURATION").unwrap(),
+        hue_rotate: *args.get_one::<f32>("HUE_ROTATE").unwrap(),
+        tint: args.get_one::<Color>("TINT").copied(),
+        posterize: args.get_one::<u8>("POSTERIZE").copied(),
+        background_cells: args.get_flag("BG_CELLS"),
+        center_bubble: args.get_flag("CENTER_BUBBLE"),
+        ansi: match args.get_one::<String>("ANSI").map(String::as_str) {
+            Some("keep") => AnsiPolicy::PassThrough,
+            Some("reject") => AnsiPolicy::Reject,
+            _ => AnsiPolicy::Strip,
+        },
+        wrap: match args.get_one::<String>("WRAP").map(String::as_str) {
+            Some("paragraphs") => WrapMode::Paragraphs,
+            Some("preserve") => WrapMode::PreserveNewlines,
+            _ => WrapMode::Lines,
+        },
+        layout: match args.get_one::<String>("LAYOUT").map(String::as_str) {
+            Some("enclosed") => Layout::Enclosed,
+            _ => Layout::Bubble,
+        },
+        position: match args.get_one::<String>("POSITION").map(String::as_str) {
+            Some("below") => BubblePosition::Below,
+            Some("left") => BubblePosition::Left,
+            Some("right") => BubblePosition::Right,
+            _ => BubblePosition::Above,
+        },
+        bubble_style: args
+            .get_one::<String>("BORDER")
+            .map(|style| style.parse())
+            .transpose()?
+            .unwrap_or_default(),
+        text_style: TextStyle {
+            color: args.get_one::<Color>("TEXT_COLOR").copied(),
+            bold: args.get_flag("BOLD"),
+            italic: args.get_flag("ITALIC"),
+        },
+        rainbow: args.get_flag("RAINBOW").then(|| Rainbow {
+            frequency: *args.get_one::<f32>("RAINBOW_FREQ").unwrap(),
+            start_hue: *args.get_one::<f32>("RAINBOW_HUE").unwrap(),
+        }),
+        banner,
+        speaker: args.get_one::<String>("SPEAKER").cloned(),
+        padding: args
+            .get_one::<BubblePadding>("PADDING")
+            .copied()
+            .unwrap_or_default(),
+        margin: *args.get_one::<usize>("MARGIN").unwrap(),
+        max_cells: args
+            .get_one::<Option<(u32, u32)>>("MAX_SIZE")
+            .copied()
+            .unwrap_or(Some(DEFAULT_MAX_CELLS)),
+        crop: args.get_one::<Rect>("CROP").copied(),
+        threshold: args
+            .get_one::<Threshold>("THRESHOLD")
+            .copied()
+            .unwrap_or_default(),
+        shade_levels: args
+            .get_one::<ShadeLevels>("SHADE_LEVELS")
+            .copied()
+            .unwrap_or_default(),
+        luminance: match args.get_one::<String>("LUMINANCE").map(String::as_str) {
+            Some("fast") => Luminance::Fast,
+            _ => Luminance::Linear,
+        },
+        alpha_threshold: *args.get_one::<u8>("ALPHA_THRESHOLD").unwrap(),
+        alpha_blend: args.get_one::<Color>("ALPHA_BLEND").copied(),
+        aspect_correction: args
+            .get_one::<AspectCorrection>("ASPECT")
+            .copied()
+            .unwrap_or_default(),
+        bubble: if args.get_flag("THINK") {
+            BubbleKind::Think
+        } else {
+            BubbleKind::Say
+        },
+        highlights: args
+            .get_many::<Highlight>("HIGHLIGHT")
+            .map(|highlights| highlights.cloned().collect())
+            .unwrap_or_default(),
+        line_ending: if args.get_flag("CRLF") {
+            LineEnding::CrLf
+        } else {
+            LineEnding::Lf
+        },
+        alt_text: match args.get_one::<String>("ALT_TEXT").map(String::as_str) {
+            Some("append") => AltText::Append,
+            Some("only") => AltText::Only,
+            _ => AltText::Off,
+        },
+        profile: match color {
+            Some("never") => OutputProfile::Plain,
+            _ => OutputProfile::Unicode,
+        },
+        ..RenderOptions::default()
+    };
+    let fit = match args.get_one::<String>("FIT").map(String::as_str) {
+        Some("width") => Some(FitStrategy::Width),
+        Some("screen") => Some(FitStrategy::Screen),
+        _ => (width == Some(None)).then_some(FitStrategy::Text),
+    };
+    if let Some(fit) = fit {
+        fit.fit_terminal(&mut options);
+        // Animations fit themselves again when the terminal is resized
+        options.fit = Some(fit);
+        // An explicit width still wins over the terminal's
+        if let Some(Some(width)) = width {
+            options.max_width = width;
+        }
+    }
 
-    let mut stdin = stdin();
-    let stdout = stdout();
+    #[cfg(unix)]
+    let socket = args.get_one::<PathBuf>("SOCKET");
+    #[cfg(unix)]
+    if args.get_flag("DAEMON") {
+        let socket = socket.cloned().unwrap_or_else(daemon::default_socket_path);
+        return Ok(daemon::serve(&socket, options)?);
+    }
+    #[cfg(unix)]
+    if socket.is_some() {
+        let given = |id| args.value_source(id) == Some(ValueSource::CommandLine);
+        if ["TEMPLATE", "VAR", "HIGHLIGHT", "BANNER", "FONT"]
+            .into_iter()
+            .any(given)
+        {
+            return Err(DAEMON_OPTIONS.into());
+        }
+        if matches!(options.backend, OutputBackend::Png | OutputBackend::Ans) {
+            return Err(DAEMON_FORMAT.into());
+        }
+    }
 
-    let mut writer = BufWriter::new(stdout.lock());
+    // Exports and recordings are files, not terminal output, and keep their colors
+    let exported = matches!(
+        options.backend,
+        OutputBackend::Html
+            | OutputBackend::Svg
+            | OutputBackend::Png
+            | OutputBackend::Json
+            | OutputBackend::Ans
+    ) || args.get_flag("CAST");
+    match color {
+        Some("auto") if !exported => options.profile = detect_output_profile(),
+        // Forced colors can't query a pipe for its capabilities
+        Some("always") if options.mode == PixelMode::Auto && !std::io::stdout().is_terminal() => {
+            options.mode = PixelMode::Ansi256
+        }
+        _ => {}
+    }
 
-    // Get the image path if provided
-    let image_path = args.get_one::<PathBuf>("IMAGE");
+    let stdout = stdout();
+    let mut stdout = BufWriter::new(stdout.lock());
+    let create = |path: &PathBuf| {
+        fs::File::create(path)
+            .map(BufWriter::new)
+            .map_err(|e| format!("Failed to create {}: {}", path.display(), e))
+    };
+    let mut output = args.get_one::<PathBuf>("OUTPUT").map(create).transpose()?;
+    if options.backend == OutputBackend::Png && output.is_none() && std::io::stdout().is_terminal()
+    {
+        return Err(PNG.into());
+    }
+    let mut tee = args.get_one::<PathBuf>("TEE").map(create).transpose()?;
+    let mut writer = MultiWriter::new();
+    match &mut output {
+        Some(output) => writer.push(output),
+        None => writer.push(&mut stdout),
+    };
+    if let Some(tee) = &mut tee {
+        writer.push(tee);
+    }
 
-    if let Some(files) = args.get_many::<PathBuf>("FILES") {
-        // Read in files and say them with the pixel image
-        for f in files {
-            let content = fs::read_to_string(f).map_err(|_| INPUT)?;
-            if let Some(img_path) = image_path {
-                say(&content, width, img_path, &mut writer).map_err(|e| format!("Failed to display with image: {}", e))?;
-            } else {
-                say_simple(&content, width, &mut writer).map_err(|_| STDOUT)?;
-            }
+    // Get the image path if provided
+    let mut image = args
+        .get_one::<PathBuf>("IMAGE")
+        .or(env.image.as_ref())
+        .cloned()
+        .map(Image::Path);
+    if let Some(mascot) = args.get_one::<String>("MASCOT") {
+        image = Some(Image::Mascot(mascot.parse()?));
+    }
+    if args.contains_id("RANDOM") {
+        image = Some(match args.get_one::<PathBuf>("RANDOM") {
+            Some(dir) => Image::Path(
+                pick_random_image(dir).map_err(|e| format!("Failed to pick an image: {}", e))?,
+            ),
+            None => Image::Mascot(Mascot::random()),
+        });
+    }
+    let image_from_stdin = matches!(&image, Some(Image::Path(path)) if path.as_os_str() == "-");
+    if image_from_stdin {
+        let mut bytes = Vec::new();
+        stdin().read_to_end(&mut bytes).map_err(|_| INPUT)?;
+        image = Some(Image::Bytes(bytes));
+    }
+    // Download once up front so sprite sheets, animations and --no-bubble,
+    // which only read files or bytes, work with URLs too
+    #[cfg(feature = "http")]
+    if let Some(Image::Path(path)) = &image {
+        if let Some(url) = path.to_str().filter(|path| is_url(path)) {
+            let bytes = download_image(url, options.limits)
+                .map_err(|e| format!("Failed to download {}: {}", url, e))?;
+            image = Some(Image::Bytes(bytes));
         }
-        Ok(())
-    } else if let Some(other_args) = args.get_many::<String>("TEXT") {
-        let text = other_args
+    }
+    let files = args.get_many::<PathBuf>("FILES");
+    let text = args.get_many::<String>("TEXT").map(|other_args| {
+        other_args
             .map(String::as_str)
             .collect::<Vec<&str>>()
-            .join(" ");
-        if let Some(img_path) = image_path {
-            say(&text, width, img_path, &mut writer).map_err(|e| format!("Failed to display with image: {}", e))?;
+            .join(" ")
+    });
+
+    // Without TEXT or FILES the message comes from stdin, which may carry the
+    // image instead; with them stdin is left alone unless --image - asks for it.
+    // --no-bubble has no message, so it never reads one
+    let no_bubble = args.get_flag("NO_BUBBLE");
+    let mut input = None;
+    if !image_from_stdin && !no_bubble && files.is_none() && text.is_none() {
+        let mut bytes = Vec::new();
+        stdin().read_to_end(&mut bytes).map_err(|_| INPUT)?;
+        if is_image_data(&bytes) {
+            image = Some(Image::Bytes(bytes));
         } else {
-            say_simple(&text, width, &mut writer).map_err(|_| STDOUT)?;
+            input = Some(String::from_utf8(bytes).map_err(|_| INPUT)?);
         }
-        Ok(())
+    }
+
+    let messages = if let Some(files) = files {
+        // Read in files and say them with the pixel image
+        files
+            .map(|f| fs::read_to_string(f).map_err(|_| INPUT))
+            .collect::<Result<Vec<_>, _>>()?
+    } else if let Some(text) = text.or(input) {
+        vec![text]
+    } else if no_bubble {
+        Vec::new()
     } else {
-        let mut input = String::new();
-        stdin.read_to_string(&mut input).map_err(|_| INPUT)?;
-        if let Some(img_path) = image_path {
-            say(&input, width, img_path, &mut writer).map_err(|e| format!("Failed to display with image: {}", e))?;
-        } else {
-            say_simple(&input, width, &mut writer).map_err(|_| STDOUT)?;
+        return Err(MESSAGE.into());
+    };
+
+    // Cut the chosen frame out of a sprite sheet
+    let layout = match (
+        args.get_one::<(u32, u32)>("SHEET"),
+        args.get_one::<(u32, u32)>("SHEET_CELL"),
+    ) {
+        (Some(&(columns, rows)), _) => Some(SheetLayout::Grid { columns, rows }),
+        (_, Some(&(width, height))) => Some(SheetLayout::CellSize { width, height }),
+        (None, None) => None,
+    };
+    if let Some(layout) = layout {
+        let sheet = match &image {
+            Some(Image::Path(path)) => SpriteSheet::open(path, layout, options.limits),
+            Some(Image::Bytes(bytes)) => SpriteSheet::from_bytes(bytes, layout, options.limits),
+            Some(Image::Decoded(_)) | None => return Err(SHEET.into()),
+            Some(Image::Mascot(mascot)) => SpriteSheet::new(mascot.image(), layout),
         }
-        Ok(())
+        .map_err(|e| format!("Failed to load sprite sheet: {}", e))?;
+        let index = *args
+            .get_one::<usize>("SPRITE")
+            .expect("SPRITE has a default");
+        let frame = sheet
+            .frame(index)
+            .ok_or_else(|| format!("The sprite sheet only has {} frames", sheet.len()))?;
+        image = Some(Image::Decoded(frame));
     }
+
+    if no_bubble {
+        let img = match image {
+            Some(Image::Path(path)) => load_image(path, options.limits),
+            Some(Image::Bytes(bytes)) => load_image_from_bytes(&bytes, options.limits),
+            Some(Image::Decoded(img)) => Ok(img),
+            Some(Image::Mascot(mascot)) => Ok(mascot.image()),
+            None => return Err(NO_BUBBLE.into()),
+        }
+        .map_err(|e| format!("Failed to load image: {}", e))?;
+        render_image_to_text_with_options(img, &options, &mut writer).map_err(|_| STDOUT)?;
+        writer.flush().map_err(|_| STDOUT)?;
+        return Ok(());
+    }
+
+    if args.get_flag("ANIMATE") {
+        let frames = match &image {
+            Some(Image::Path(path)) => FrameSequence::open(path, options.limits),
+            Some(Image::Bytes(bytes)) => FrameSequence::from_bytes(bytes, options.limits),
+            Some(Image::Decoded(img)) => Ok(FrameSequence::from(img.clone())),
+            Some(Image::Mascot(mascot)) => Ok(FrameSequence::from(mascot.image())),
+            None => return Err(ANIMATE.into()),
+        }
+        .map_err(|e| format!("Failed to play animation: {}", e))?;
+        if args.get_flag("CAST") {
+            let [message] = &messages[..] else {
+                return Err(CAST.into());
+            };
+            write_asciicast(&frames, message, &options, &mut writer)
+                .map_err(|e| format!("Failed to write the recording: {}", e))?;
+            return Ok(());
+        }
+        let loops = args.get_one::<u32>("LOOPS").copied();
+        #[cfg(unix)]
+        resize::install();
+        for message in &messages {
+            #[cfg(unix)]
+            let played = say_from_frames_resizing(
+                &frames,
+                message,
+                &options,
+                loops,
+                &mut writer,
+                resize::poll,
+            );
+            #[cfg(not(unix))]
+            let played = say_from_frames(&frames, message, &options, loops, &mut writer);
+            played.map_err(|e| format!("Failed to play animation: {}", e))?;
+        }
+        return Ok(());
+    }
+
+    // PNG output is binary, so keep the bytes as they are
+    let render_one = |message: &str| -> Result<Vec<u8>, Box<dyn Error>> {
+        #[cfg(unix)]
+        if let Some(socket) = socket {
+            return remote(socket, message, &options, image.as_ref()).map(String::into_bytes);
+        }
+        let mut output = Vec::new();
+        render(message, &options, image.as_ref(), &mut output)?;
+        Ok(output)
+    };
+
+    if columns > 1 && messages.len() > 1 {
+        let renders = messages
+            .iter()
+            .map(|message| {
+                render_one(message).map(|output| String::from_utf8_lossy(&output).into_owned())
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let max_width = terminal_size().map(|(columns, _)| columns);
+        writer
+            .write_all(arrange_columns(&renders, columns, max_width).as_bytes())
+            .map_err(|_| STDOUT)?;
+    } else {
+        for message in &messages {
+            writer
+                .write_all(&render_one(message)?)
+                .map_err(|_| STDOUT)?;
+        }
+    }
+    writer.flush().map_err(|_| STDOUT)?;
+    Ok(())
+}
+
+/// Render one message, with the pixel image if one was given
+fn render<W>(
+    message: &str,
+    options: &RenderOptions,
+    image: Option<&Image>,
+    writer: W,
+) -> Result<(), Box<dyn Error>>
+where
+    W: Write,
+{
+    let image_error = |e| format!("Failed to display with image: {}", e);
+    match image {
+        Some(Image::Path(img_path)) => {
+            say_from_image_with_options(img_path, message, options, writer).map_err(image_error)?
+        }
+        Some(Image::Bytes(bytes)) => {
+            say_from_bytes_with_options(bytes, message, options, writer).map_err(image_error)?
+        }
+        Some(Image::Decoded(img)) => {
+            say_from_dynamic_image_with_options(img.clone(), message, options, writer)
+                .map_err(image_error)?
+        }
+        Some(Image::Mascot(mascot)) => {
+            say_with_mascot(*mascot, message, options, writer).map_err(image_error)?
+        }
+        None => say_with_options(message, options, writer).map_err(|_| STDOUT)?,
+    };
+    Ok(())
+}
+
+/// Ask a running daemon to render one message
+#[cfg(unix)]
+fn remote(
+    socket: &std::path::Path,
+    message: &str,
+    options: &RenderOptions,
+    image: Option<&Image>,
+) -> Result<String, Box<dyn Error>> {
+    let (image, mascot) = match image {
+        // The daemon resolves paths against its own working directory
+        Some(Image::Path(path)) => (Some(fs::canonicalize(path)?), None),
+        Some(Image::Bytes(_)) => return Err(DAEMON_BYTES.into()),
+        Some(Image::Decoded(_)) => return Err(DAEMON_SHEET.into()),
+        Some(Image::Mascot(mascot)) => (None, Some(mascot.to_string())),
+        None => (None, None),
+    };
+    let request = daemon::Request {
+        message: message.to_string(),
+        image,
+        mascot,
+        think: false,
+        border: None,
+        width: None,
+        mode: None,
+        options: Some(RenderOptions {
+            // Detect the client's terminal, not the daemon's
+            mode: options.mode.resolve(),
+            ..options.clone()
+        }),
+    };
+    daemon::request(socket, &request)
+}
+
+/// Parse an `X,Y` pixel coordinate, or `center` for the image's center
+fn parse_anchor(value: &str) -> Result<TailAnchor, String> {
+    if value.trim().eq_ignore_ascii_case("center") {
+        return Ok(TailAnchor::ImageCenter);
+    }
+    let err = || {
+        format!(
+            "expected X,Y pixel coordinates or `center`, got `{}`",
+            value
+        )
+    };
+    let (x, y) = value.split_once(',').ok_or_else(err)?;
+    let x = x.trim().parse().map_err(|_| err())?;
+    let y = y.trim().parse().map_err(|_| err())?;
+    Ok(TailAnchor::Point(x, y))
+}
+
+/// The FIGlet font behind --banner
+#[cfg(feature = "figlet")]
+fn builtin_font() -> Result<FigletFont, Box<dyn Error>> {
+    Ok(FigletFont::banner())
+}
+
+#[cfg(not(feature = "figlet"))]
+fn builtin_font() -> Result<FigletFont, Box<dyn Error>> {
+    Err(BANNER.into())
+}
+
+/// Parse a `WIDTHxHEIGHT` pair such as `4x2`
+fn parse_size(value: &str) -> Result<(u32, u32), String> {
+    let err = || format!("expected WIDTHxHEIGHT, got `{}`", value);
+    let (width, height) = value.split_once(['x', 'X']).ok_or_else(err)?;
+    let width = width.trim().parse().map_err(|_| err())?;
+    let height = height.trim().parse().map_err(|_| err())?;
+    Ok((width, height))
+}
+
+/// Parse a text box width, or `auto` (`None`) to follow the terminal
+fn parse_width(value: &str) -> Result<Option<usize>, String> {
+    if value.trim().eq_ignore_ascii_case("auto") {
+        return Ok(None);
+    }
+    value
+        .trim()
+        .parse()
+        .map(Some)
+        .map_err(|_| format!("expected a number or `auto`, got `{}`", value))
+}
+
+/// Parse an `X,Y,W,H` pixel region
+fn parse_rect(value: &str) -> Result<Rect, String> {
+    let err = || format!("expected X,Y,W,H, got `{}`", value);
+    let fields = value
+        .split(',')
+        .map(|field| field.trim().parse().map_err(|_| err()))
+        .collect::<Result<Vec<u32>, String>>()?;
+    match fields[..] {
+        [x, y, width, height] if width > 0 && height > 0 => Ok(Rect::new(x, y, width, height)),
+        _ => Err(err()),
+    }
+}
+
+/// Parse a `WIDTHxHEIGHT` image size limit, or `none` for no limit
+fn parse_max_size(value: &str) -> Result<Option<(u32, u32)>, String> {
+    if value.trim().eq_ignore_ascii_case("none") {
+        return Ok(None);
+    }
+    parse_size(value).map(Some)
+}
+
+/// Parse a `0`-`255` brightness threshold, an `N%` percentile or `auto`
+fn parse_threshold(value: &str) -> Result<Threshold, String> {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("auto") {
+        return Ok(Threshold::Auto);
+    }
+    let err = || format!("expected 0-255, 0%-100% or `auto`, got `{}`", value);
+    match value.strip_suffix('%') {
+        Some(percent) => match percent.trim().parse() {
+            Ok(percent @ 0..=100) => Ok(Threshold::Percentile(percent)),
+            _ => Err(err()),
+        },
+        None => value.parse().map(Threshold::Absolute).map_err(|_| err()),
+    }
+}
+
+/// Load a built-in palette by name, or a palette file
+fn parse_palette(value: &str) -> Result<ColorPalette, String> {
+    if let Some(palette) = ColorPalette::builtin(value) {
+        return Ok(palette);
+    }
+    fs::read_to_string(value)
+        .and_then(|data| ColorPalette::parse(&data))
+        .map_err(|e| e.to_string())
+}
+
+/// Parse `even`, `histogram` or four comma separated `0`-`255` levels
+fn parse_shade_levels(value: &str) -> Result<ShadeLevels, String> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "even" => Ok(ShadeLevels::Even),
+        "histogram" => Ok(ShadeLevels::Histogram),
+        levels => {
+            let levels: Vec<u8> = levels
+                .split(',')
+                .map(|level| level.trim().parse())
+                .collect::<Result<_, _>>()
+                .map_err(|_| format!("expected `even`, `histogram` or A,B,C,D, got `{}`", value))?;
+            levels
+                .try_into()
+                .map(ShadeLevels::Custom)
+                .map_err(|_| format!("expected four levels, got `{}`", value))
+        }
+    }
+}
+
+/// Parse `auto`, `off` or a positive image height factor
+fn parse_aspect(value: &str) -> Result<AspectCorrection, String> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "auto" => Ok(AspectCorrection::Auto),
+        "off" => Ok(AspectCorrection::Off),
+        factor => match factor.parse::<f32>() {
+            Ok(factor) if factor.is_finite() && factor > 0.0 => {
+                Ok(AspectCorrection::Factor(factor))
+            }
+            _ => Err(format!(
+                "expected `auto`, `off` or a positive number, got `{}`",
+                value
+            )),
+        },
+    }
+}
+
+/// Parse `H` or `H,V` bubble padding
+fn parse_padding(value: &str) -> Result<BubblePadding, String> {
+    let err = || format!("expected H or H,V, got `{}`", value);
+    let (horizontal, vertical) = value.split_once(',').unwrap_or((value, "0"));
+    Ok(BubblePadding {
+        horizontal: horizontal.trim().parse().map_err(|_| err())?,
+        vertical: vertical.trim().parse().map_err(|_| err())?,
+    })
+}
+
+/// Parse a `KEY=VALUE` template placeholder
+fn parse_var(value: &str) -> Result<(String, String), String> {
+    let (key, value) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected KEY=VALUE, got `{}`", value))?;
+    Ok((key.trim().to_string(), value.to_string()))
+}
+
+/// Parse a `#rrggbb` or `#rgb` color
+fn parse_color(value: &str) -> Result<Color, String> {
+    value.parse().map_err(|e: ParseColorError| e.to_string())
+}
+
+/// Parse a `REGEX=COLOR` highlight rule; the last `=` separates the color
+fn parse_highlight(value: &str) -> Result<Highlight, String> {
+    let (pattern, color) = value
+        .rsplit_once('=')
+        .ok_or_else(|| format!("expected REGEX=COLOR, got `{}`", value))?;
+    let color = parse_color(color)?;
+    Highlight::new(pattern, color).map_err(|e| e.to_string())
 }
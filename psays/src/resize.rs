@@ -0,0 +1,33 @@
+//! Terminal resize notifications for animations.
+//!
+//! The library never installs signal handlers itself; psays owns the process,
+//! so it listens for `SIGWINCH` and only reads the terminal size after one.
+
+use pixel_says::terminal_size;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static RESIZED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn on_sigwinch(_: libc::c_int) {
+    RESIZED.store(true, Ordering::Relaxed);
+}
+
+/// Start listening for `SIGWINCH`
+pub fn install() {
+    // SAFETY: the handler only stores to an atomic, which is async-signal-safe
+    unsafe {
+        libc::signal(
+            libc::SIGWINCH,
+            on_sigwinch as *const () as libc::sighandler_t,
+        );
+    }
+}
+
+/// The new terminal size if the window was resized since the last call
+pub fn poll() -> Option<(usize, usize)> {
+    if RESIZED.swap(false, Ordering::Relaxed) {
+        terminal_size()
+    } else {
+        None
+    }
+}
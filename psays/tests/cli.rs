@@ -0,0 +1,51 @@
+use std::io::Write;
+use std::process::{Child, Command, Output, Stdio};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+// How long psays may take before it is considered stuck on stdin
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Run psays with a stdin that has data in it but is never closed
+fn run_with_open_stdin(args: &[&str]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_psays"))
+        .args(args)
+        .env_remove("PIXEL_SAYS_IMAGE")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("psays should start");
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(b"\xff\xfe not a message\n").unwrap();
+    stdin.flush().unwrap();
+
+    wait_or_kill(&mut child);
+    drop(stdin);
+    child.wait_with_output().unwrap()
+}
+
+fn wait_or_kill(child: &mut Child) {
+    let start = Instant::now();
+    while child.try_wait().unwrap().is_none() {
+        if start.elapsed() > TIMEOUT {
+            child.kill().unwrap();
+            panic!("psays kept waiting for stdin to close");
+        }
+        sleep(Duration::from_millis(20));
+    }
+}
+
+#[test]
+fn text_does_not_read_stdin() {
+    let output = run_with_open_stdin(&["--color", "never", "hello"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("hello"));
+}
+
+#[test]
+fn no_bubble_does_not_read_stdin() {
+    let output = run_with_open_stdin(&["--color", "never", "--no-bubble", "--mascot", "ferris"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(!output.stdout.is_empty());
+}
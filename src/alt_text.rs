@@ -0,0 +1,84 @@
+use crate::filters::is_visible;
+use crate::text::sanitize_message;
+use crate::{AnsiPolicy, Color};
+use image::{DynamicImage, GenericImageView};
+use std::collections::HashMap;
+
+/// 描述中列出的主要颜色数
+const DOMINANT_COLORS: usize = 3;
+
+/// 生成一行纯文本描述，如
+/// `Image: ferris.png, 16x12 pixels, dominant colors #f74c00, #000000. Message: Hello!`
+pub(crate) fn describe(name: Option<&str>, img: &DynamicImage, message: &str) -> String {
+    describe_message(&describe_image(name, img), message)
+}
+
+/// 描述中与消息无关的部分，可以在多条消息之间复用
+pub(crate) fn describe_image(name: Option<&str>, img: &DynamicImage) -> String {
+    let (width, height) = img.dimensions();
+    let mut description = String::from("Image: ");
+    if let Some(name) = name {
+        description.push_str(name);
+        description.push_str(", ");
+    }
+    description.push_str(&format!("{}x{} pixels", width, height));
+
+    let colors = dominant_colors(img);
+    if !colors.is_empty() {
+        let colors: Vec<String> = colors.iter().map(Color::to_string).collect();
+        description.push_str(", dominant colors ");
+        description.push_str(&colors.join(", "));
+    }
+    description
+}
+
+/// 在图片的描述之后加上消息
+pub(crate) fn describe_message(image: &str, message: &str) -> String {
+    // 消息合并为一行，去掉所有转义序列
+    let message = sanitize_message(message, AnsiPolicy::Strip).unwrap_or_default();
+    let message = message.split_whitespace().collect::<Vec<_>>().join(" ");
+    format!("{}. Message: {}\n", image, message)
+}
+
+/// 出现次数最多的几种可见颜色，在缩略图上统计以免处理大图时过慢
+fn dominant_colors(img: &DynamicImage) -> Vec<Color> {
+    let thumbnail = if img.width() > 64 || img.height() > 64 {
+        img.thumbnail(64, 64).into_rgba8()
+    } else {
+        img.to_rgba8()
+    };
+    let mut counts: HashMap<[u8; 3], usize> = HashMap::new();
+    for pixel in thumbnail.pixels().filter(|pixel| is_visible(pixel)) {
+        *counts.entry([pixel[0], pixel[1], pixel[2]]).or_default() += 1;
+    }
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    // 次数相同时按颜色排序，保证输出稳定
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    counts
+        .into_iter()
+        .take(DOMINANT_COLORS)
+        .map(|([r, g, b], _)| Color::rgb(r, g, b))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn test_describe_image() {
+        let mut img = RgbaImage::from_pixel(4, 2, Rgba([255, 0, 0, 255]));
+        img.put_pixel(0, 0, Rgba([0, 0, 255, 255]));
+        img.put_pixel(1, 0, Rgba([0, 0, 0, 0]));
+
+        assert_eq!(
+            describe(
+                Some("sprite.png"),
+                &DynamicImage::ImageRgba8(img),
+                "\x1b[1mHello\x1b[0m\n  world"
+            ),
+            "Image: sprite.png, 4x2 pixels, dominant colors #ff0000, #0000ff. Message: Hello world\n"
+        );
+    }
+}
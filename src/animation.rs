@@ -0,0 +1,606 @@
+use crate::bubble::{bubble_width, write_bubble};
+use crate::convert::{art_height, art_width, crop_image, render_art};
+use crate::output::LineWriter;
+use crate::report::CountingWriter;
+use crate::text::{longest_line, text_width, wrap_message};
+use crate::{
+    image_error, load_image_from_bytes, place_art, visible_colors, write_tail, DecodeLimits,
+    ImageTooLarge, LineEnding, OutputBackend, RenderOptions, RenderReport, ResizeWatcher, BUFSIZE,
+};
+use image::codecs::gif::GifDecoder;
+use image::codecs::png::PngDecoder;
+use image::codecs::webp::WebPDecoder;
+use image::{AnimationDecoder, DynamicImage, ImageDecoder, ImageFormat};
+use smallvec::SmallVec;
+use std::borrow::Cow;
+use std::io::{Cursor, Result, Write};
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+/// 没有指定（或为 0）的帧延迟按 100 毫秒处理，与浏览器的行为一致
+const DEFAULT_DELAY: Duration = Duration::from_millis(100);
+
+/// 动画的一帧
+#[derive(Debug, Clone)]
+pub struct AnimationFrame {
+    /// 与画布大小相同的完整图片
+    pub image: DynamicImage,
+    /// 显示这一帧的时间
+    pub delay: Duration,
+}
+
+/// 解码后的动画帧序列，GIF、APNG 和动态 WebP 都会转换为这种形式
+///
+/// 静态图片被视为只有一帧的动画。
+#[derive(Debug, Clone, Default)]
+pub struct FrameSequence {
+    frames: Vec<AnimationFrame>,
+}
+
+impl FrameSequence {
+    /// 由已有的帧创建
+    pub fn new(frames: Vec<AnimationFrame>) -> Self {
+        FrameSequence { frames }
+    }
+
+    /// 在解码限制下读取动画文件
+    pub fn open<P>(path: P, limits: DecodeLimits) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        FrameSequence::from_bytes(&std::fs::read(path)?, limits)
+    }
+
+    /// 在解码限制下解码内存中的动画，格式根据数据内容判断
+    pub fn from_bytes(bytes: &[u8], limits: DecodeLimits) -> Result<Self> {
+        match image::guess_format(bytes).map_err(image_error)? {
+            ImageFormat::Gif => {
+                let decoder = GifDecoder::new(Cursor::new(bytes)).map_err(image_error)?;
+                decode_frames(decoder, limits, |decoder| Ok(decoder.into_frames()))
+            }
+            ImageFormat::Png => {
+                let decoder = PngDecoder::new(Cursor::new(bytes)).map_err(image_error)?;
+                if decoder.is_apng().map_err(image_error)? {
+                    decode_frames(decoder, limits, |decoder| Ok(decoder.apng()?.into_frames()))
+                } else {
+                    still(bytes, limits)
+                }
+            }
+            ImageFormat::WebP => {
+                let decoder = WebPDecoder::new(Cursor::new(bytes)).map_err(image_error)?;
+                if decoder.has_animation() {
+                    decode_frames(decoder, limits, |decoder| Ok(decoder.into_frames()))
+                } else {
+                    still(bytes, limits)
+                }
+            }
+            _ => still(bytes, limits),
+        }
+    }
+
+    /// 所有帧
+    pub fn frames(&self) -> &[AnimationFrame] {
+        &self.frames
+    }
+
+    /// 帧的数量
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// 是否没有任何帧
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+}
+
+impl From<DynamicImage> for FrameSequence {
+    fn from(image: DynamicImage) -> Self {
+        FrameSequence::new(vec![AnimationFrame {
+            image,
+            delay: DEFAULT_DELAY,
+        }])
+    }
+}
+
+/// 播放动图（GIF、APNG 或动态 WebP）：消息框只绘制一次，图片的每一帧通过光标上移在原地重绘
+///
+/// `loops` 为播放的次数，`None` 表示无限循环。动画总是使用字符输出和
+/// `Bubble` 布局；ASCII 输出不允许转义序列，只显示第一帧。
+/// 终端大小变化后重新绘制消息框，`options.fit` 不为 `None` 时还会按新的大小重新转换。
+/// 返回的统计包括所有帧的颜色数，图片和消息框的尺寸按最后一次转换计算。
+pub fn say_from_animated_image<P, W>(
+    image_path: P,
+    message: &str,
+    options: &RenderOptions,
+    loops: Option<u32>,
+    writer: W,
+) -> Result<RenderReport>
+where
+    P: AsRef<Path>,
+    W: Write,
+{
+    let frames = FrameSequence::open(image_path, options.limits)?;
+    say_from_frames(&frames, message, options, loops, writer)
+}
+
+/// 播放已解码的帧序列，参见 [`say_from_animated_image`]
+pub fn say_from_frames<W>(
+    frames: &FrameSequence,
+    message: &str,
+    options: &RenderOptions,
+    loops: Option<u32>,
+    writer: W,
+) -> Result<RenderReport>
+where
+    W: Write,
+{
+    play(frames.frames(), message, options, loops, writer)
+}
+
+/// 与 [`say_from_frames`] 相同，但由调用者检查终端大小的变化
+///
+/// 绘制每一帧之前调用 `resized`，终端大小变化后它返回新的列数和行数，否则返回 `None`。
+/// 程序自己处理 `SIGWINCH` 时可以只在收到信号后读取 [`crate::terminal_size`]，
+/// 而不必像 [`ResizeWatcher`] 那样每一帧都读取。
+pub fn say_from_frames_resizing<W, F>(
+    frames: &FrameSequence,
+    message: &str,
+    options: &RenderOptions,
+    loops: Option<u32>,
+    writer: W,
+    resized: F,
+) -> Result<RenderReport>
+where
+    W: Write,
+    F: FnMut() -> Option<(usize, usize)>,
+{
+    play_resizing(frames.frames(), message, options, loops, writer, resized)
+}
+
+/// 把动画写成 asciinema v2 格式的录像（`.cast`），每一帧的时间与播放时相同
+///
+/// 录像只播放一遍，最后一帧也保留它的显示时间，循环交给播放器决定。终端的大小按
+/// 第一帧的输出计算，与 [`say_from_frames`] 一样，ASCII 输出只包含第一帧。
+///
+/// # Example
+///
+/// ```rust
+/// use image::{DynamicImage, RgbaImage};
+/// use pixel_says::{write_asciicast, FrameSequence, RenderOptions};
+///
+/// let frames = FrameSequence::from(DynamicImage::ImageRgba8(RgbaImage::new(4, 4)));
+/// let mut cast = Vec::new();
+/// write_asciicast(&frames, "Hello!", &RenderOptions::default(), &mut cast).unwrap();
+/// ```
+pub fn write_asciicast<W>(
+    frames: &FrameSequence,
+    message: &str,
+    options: &RenderOptions,
+    writer: W,
+) -> Result<RenderReport>
+where
+    W: Write,
+{
+    let mut writer = CountingWriter::new(writer);
+    let options = &RenderOptions {
+        backend: OutputBackend::Text,
+        // 录像中的换行要回到行首
+        line_ending: LineEnding::CrLf,
+        ..options.clone()
+    };
+    let animation = prepare(frames.frames(), message, options)?;
+    let mut arts = animation.arts.as_slice();
+    if !options.profile.allows_escapes() {
+        arts = &arts[..arts.len().min(1)];
+    }
+    let output = |text: &[u8]| -> Result<String> {
+        let mut output = Vec::new();
+        LineWriter::new(&mut output, options).write_all(text)?;
+        Ok(String::from_utf8_lossy(&output).into_owned())
+    };
+
+    let mut first = animation.bubble.clone();
+    if let Some((art, _)) = arts.first() {
+        first.extend_from_slice(art);
+    }
+    let first = output(&first)?;
+    let width = first
+        .lines()
+        .map(|line| text_width(line.trim_end_matches('\r'), options.ambiguous_width))
+        .max()
+        .unwrap_or(0);
+    // 多留一行，最后的换行不会让第一行滚出屏幕
+    let height = first.lines().count() + 1;
+    writeln!(
+        writer,
+        "{{\"version\": 2, \"width\": {}, \"height\": {}}}",
+        width.max(1),
+        height
+    )?;
+
+    let mut time = Duration::ZERO;
+    let mut event = |time: Duration, data: &str| {
+        writeln!(
+            writer,
+            "[{:.3}, \"o\", {}]",
+            time.as_secs_f64(),
+            json_string(data)
+        )
+    };
+    event(time, &first)?;
+    let mut previous_lines = 0;
+    for (i, (art, delay)) in arts.iter().enumerate() {
+        if i > 0 {
+            // 回到图片的第一行
+            let data = format!("\x1b[{}A{}", previous_lines, output(art)?);
+            event(time, &data)?;
+        }
+        previous_lines = art.iter().filter(|&&b| b == b'\n').count();
+        time += *delay;
+    }
+    if !arts.is_empty() {
+        // 保留最后一帧的显示时间
+        event(time, "")?;
+    }
+    writer.flush()?;
+    Ok(RenderReport {
+        lines: writer.lines,
+        bytes_written: writer.bytes,
+        ..animation.report
+    })
+}
+
+/// JSON 字符串字面量
+pub(crate) fn json_string(text: &str) -> String {
+    let mut json = String::with_capacity(text.len() + 2);
+    json.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+fn still(bytes: &[u8], limits: DecodeLimits) -> Result<FrameSequence> {
+    Ok(FrameSequence::from(load_image_from_bytes(bytes, limits)?))
+}
+
+/// 检查尺寸后解码所有帧
+fn decode_frames<'a, D, F>(
+    mut decoder: D,
+    limits: DecodeLimits,
+    into_frames: F,
+) -> Result<FrameSequence>
+where
+    D: ImageDecoder,
+    F: FnOnce(D) -> image::ImageResult<image::Frames<'a>>,
+{
+    let (width, height) = decoder.dimensions();
+    if !limits.allows(width, height) {
+        return Err(ImageTooLarge {
+            dimensions: Some((width, height)),
+            limits,
+        }
+        .into());
+    }
+    decoder.set_limits(limits.into()).map_err(image_error)?;
+
+    let frames = into_frames(decoder)
+        .map_err(image_error)?
+        .collect_frames()
+        .map_err(image_error)?;
+    Ok(FrameSequence::new(
+        frames
+            .into_iter()
+            .map(|frame| {
+                let (numer, denom) = frame.delay().numer_denom_ms();
+                let delay = Duration::from_millis((numer / denom.max(1)) as u64);
+                AnimationFrame {
+                    delay: if delay.is_zero() {
+                        DEFAULT_DELAY
+                    } else {
+                        delay
+                    },
+                    image: DynamicImage::ImageRgba8(frame.into_buffer()),
+                }
+            })
+            .collect(),
+    ))
+}
+
+fn play<W>(
+    frames: &[AnimationFrame],
+    message: &str,
+    options: &RenderOptions,
+    loops: Option<u32>,
+    writer: W,
+) -> Result<RenderReport>
+where
+    W: Write,
+{
+    let mut watcher = ResizeWatcher::new();
+    play_resizing(frames, message, options, loops, writer, || watcher.poll())
+}
+
+/// 与 [`play`] 相同，`resized` 返回终端变化后的大小
+fn play_resizing<W, F>(
+    frames: &[AnimationFrame],
+    message: &str,
+    options: &RenderOptions,
+    loops: Option<u32>,
+    writer: W,
+    mut resized: F,
+) -> Result<RenderReport>
+where
+    W: Write,
+    F: FnMut() -> Option<(usize, usize)>,
+{
+    let mut options = RenderOptions {
+        backend: OutputBackend::Text,
+        ..options.clone()
+    };
+    let mut animation = prepare(frames, message, &options)?;
+    let mut counter = CountingWriter::new(writer);
+    let mut writer = LineWriter::new(&mut counter, &options);
+    writer.write_all(&animation.bubble)?;
+
+    if !options.profile.allows_escapes() || animation.arts.len() <= 1 {
+        if let Some((art, _)) = animation.arts.first() {
+            writer.write_all(art)?;
+        }
+        writer.flush()?;
+        return Ok(RenderReport {
+            lines: counter.lines,
+            bytes_written: counter.bytes,
+            ..animation.report
+        });
+    }
+
+    let mut previous_lines = 0;
+    let mut round = 0;
+    while loops.is_none_or(|loops| round < loops.max(1)) {
+        for i in 0..animation.arts.len() {
+            if let Some(size) = resized() {
+                // 窗口大小变化后终端可能重新折行，按新的大小重新转换，
+                // 回到消息框的第一行清除残留的内容后重新绘制
+                let bubble_lines = line_count(&animation.bubble);
+                if let Some(fit) = options.fit {
+                    fit.apply(&mut options, size);
+                    animation = prepare(frames, message, &options)?;
+                }
+                if previous_lines + bubble_lines > 0 {
+                    write!(writer, "\x1b[{}A", previous_lines + bubble_lines)?;
+                }
+                write!(writer, "\x1b[J")?;
+                writer.write_all(&animation.bubble)?;
+            } else if previous_lines > 0 {
+                // 回到图片的第一行
+                write!(writer, "\x1b[{}A", previous_lines)?;
+            }
+            let (art, delay) = &animation.arts[i];
+            writer.write_all(art)?;
+            writer.flush()?;
+            previous_lines = line_count(art);
+
+            let last = loops.is_some_and(|loops| round + 1 >= loops.max(1))
+                && i + 1 == animation.arts.len();
+            if !last {
+                thread::sleep(*delay);
+            }
+        }
+        round += 1;
+    }
+    Ok(RenderReport {
+        lines: counter.lines,
+        bytes_written: counter.bytes,
+        ..animation.report
+    })
+}
+
+/// 文本中的行数
+fn line_count(text: &[u8]) -> usize {
+    text.iter().filter(|&&b| b == b'\n').count()
+}
+
+/// 转换好的动画
+struct Animation {
+    /// 只绘制一次的消息框和连接线
+    bubble: Vec<u8>,
+    /// 每一帧的图片和显示时间
+    arts: Vec<(Vec<u8>, Duration)>,
+    /// 图片和消息框的尺寸以及所有帧的颜色数，输出的统计由调用者填写
+    report: RenderReport,
+}
+
+/// 绘制消息框并转换每一帧
+fn prepare(frames: &[AnimationFrame], message: &str, options: &RenderOptions) -> Result<Animation> {
+    let mut write_buffer = SmallVec::<[u8; BUFSIZE]>::new();
+    let wrapped = wrap_message(message, options)?;
+    let lines: Vec<&str> = wrapped.lines().collect();
+    let actual_width = longest_line(&lines, options.ambiguous_width);
+    write_bubble(&mut write_buffer, &lines, actual_width, options);
+    write_buffer.push(b'\n');
+
+    // 先把每一帧转换好，播放时只需要输出；所有帧的尺寸相同，连接线的位置也相同
+    let mut indent = 0;
+    let mut arts = Vec::with_capacity(frames.len());
+    let bubble_columns = bubble_width(actual_width, options);
+    let border = if options.frame.is_some() { 2 } else { 0 };
+    let mut report = RenderReport {
+        bubble_width: bubble_columns,
+        ..RenderReport::default()
+    };
+    let mut colors = std::collections::HashSet::new();
+    for frame in frames {
+        let (img, frame_indent) = place_art(
+            crop_image(Cow::Borrowed(&frame.image), options),
+            bubble_columns,
+            options,
+        );
+        indent = frame_indent;
+        report.art_width = art_width(img.width(), options) + border;
+        report.art_height = art_height(img.height(), options) + border;
+        colors.extend(visible_colors(&img));
+        let mut art = Vec::new();
+        render_art(&img, options, &mut art)?;
+        arts.push((art, frame.delay));
+    }
+    write_tail(&mut write_buffer, indent, options.bubble, false);
+    report.colors_used = colors.len();
+    Ok(Animation {
+        bubble: write_buffer.to_vec(),
+        arts,
+        report,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FitStrategy;
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn test_animation_redraws_in_place() {
+        let frame = |color| {
+            let img = RgbaImage::from_pixel(2, 1, Rgba(color));
+            AnimationFrame {
+                image: DynamicImage::ImageRgba8(img),
+                delay: Duration::ZERO,
+            }
+        };
+        let frames = [frame([255, 0, 0, 255]), frame([0, 0, 255, 255])];
+        let mut output = Vec::new();
+        let report = play(
+            &frames,
+            "hi",
+            &RenderOptions::default(),
+            Some(1),
+            &mut output,
+        )
+        .unwrap();
+        assert_eq!((report.art_width, report.art_height), (4, 1));
+        assert_eq!(report.bytes_written, output.len());
+        // 两帧的颜色都计入
+        assert_eq!(report.colors_used, 2);
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(report.lines, output.lines().count());
+
+        // 消息框只出现一次，第二帧之前光标上移一行
+        assert_eq!(output.matches("< hi >").count(), 1);
+        let (first, second) = output.split_once("\x1b[1A").unwrap();
+        assert!(first.ends_with("\x1b[38;2;255;0;0m████\x1b[0m\n"));
+        assert!(second.ends_with("\x1b[38;2;0;0;255m████\x1b[0m\n"));
+    }
+
+    #[test]
+    fn test_animation_refits_on_resize() {
+        let frame = |color| AnimationFrame {
+            image: DynamicImage::ImageRgba8(RgbaImage::from_pixel(8, 1, Rgba(color))),
+            delay: Duration::ZERO,
+        };
+        let frames = [frame([255, 0, 0, 255]), frame([0, 0, 255, 255])];
+        let options = RenderOptions {
+            fit: Some(FitStrategy::Width),
+            ..RenderOptions::default()
+        };
+        // 第二帧之前终端变为 12 列
+        let mut sizes = [None, Some((12, 24))].into_iter();
+        let mut output = Vec::new();
+        play_resizing(
+            &frames,
+            "hello world",
+            &options,
+            Some(1),
+            &mut output,
+            || sizes.next().flatten(),
+        )
+        .unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        // 回到消息框的第一行（消息框 3 行、连接线 2 行、图片 1 行），按新的宽度重新绘制
+        let (first, second) = output.split_once("\x1b[6A\x1b[J").unwrap();
+        assert!(first.contains("< hello world >"));
+        assert!(first.ends_with(&format!("\x1b[38;2;255;0;0m{}\x1b[0m\n", "█".repeat(16))));
+        assert!(second.contains("/ hello \\\n\\ world /"));
+        assert!(second.ends_with(&format!("\x1b[38;2;0;0;255m{}\x1b[0m\n", "█".repeat(12))));
+    }
+
+    #[test]
+    fn test_write_asciicast() {
+        let frame = |color, delay| AnimationFrame {
+            image: DynamicImage::ImageRgba8(RgbaImage::from_pixel(2, 1, Rgba(color))),
+            delay: Duration::from_millis(delay),
+        };
+        let frames = FrameSequence::new(vec![
+            frame([255, 0, 0, 255], 50),
+            frame([0, 0, 255, 255], 100),
+        ]);
+        let mut cast = Vec::new();
+        write_asciicast(&frames, "hi", &RenderOptions::default(), &mut cast).unwrap();
+        let cast = String::from_utf8(cast).unwrap();
+        let lines: Vec<serde_json::Value> = cast
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        // 消息框 3 行，连接线 2 行，图片 1 行，再多留一行
+        assert_eq!(lines[0]["version"], 2);
+        assert_eq!(lines[0]["width"], 6);
+        assert_eq!(lines[0]["height"], 7);
+        let times: Vec<f64> = lines[1..]
+            .iter()
+            .map(|event| event[0].as_f64().unwrap())
+            .collect();
+        assert_eq!(times, [0.0, 0.05, 0.15]);
+        assert!(lines[1][2].as_str().unwrap().contains("< hi >\r\n"));
+        assert!(lines[2][2]
+            .as_str()
+            .unwrap()
+            .starts_with("\x1b[1A\x1b[38;2;0;0;255m"));
+        assert_eq!(lines[3][2], "");
+    }
+
+    #[test]
+    fn test_frame_sequence_from_bytes() {
+        use image::codecs::gif::GifEncoder;
+        use image::Delay;
+
+        let mut gif = Vec::new();
+        {
+            let mut encoder = GifEncoder::new(&mut gif);
+            for color in [[255, 0, 0, 255], [0, 0, 255, 255]] {
+                let img = RgbaImage::from_pixel(2, 1, Rgba(color));
+                let delay = Delay::from_numer_denom_ms(50, 1);
+                encoder
+                    .encode_frame(image::Frame::from_parts(img, 0, 0, delay))
+                    .unwrap();
+            }
+        }
+        let frames = FrameSequence::from_bytes(&gif, DecodeLimits::default()).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames.frames()[0].delay, Duration::from_millis(50));
+        assert_eq!(
+            frames.frames()[1].image.to_rgba8().get_pixel(0, 0),
+            &Rgba([0, 0, 255, 255])
+        );
+
+        // 静态图片只有一帧
+        let mut png = Vec::new();
+        let img = DynamicImage::ImageRgba8(RgbaImage::new(2, 1));
+        img.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+        let frames = FrameSequence::from_bytes(&png, DecodeLimits::default()).unwrap();
+        assert_eq!(frames.len(), 1);
+    }
+}
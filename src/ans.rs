@@ -0,0 +1,147 @@
+use crate::template::civil_from_days;
+use crate::{Cell, CellGrid, Color};
+use std::time::{SystemTime, UNIX_EPOCH};
+use unicode_width::UnicodeWidthChar;
+
+/// CP437 中 0x80 到 0xFF 对应的字符
+const CP437_HIGH: [char; 128] = [
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å', //
+    'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ', //
+    'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»', //
+    '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕', '╣', '║', '╗', '╝', '╜', '╛', '┐', //
+    '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦', '╠', '═', '╬', '╧', //
+    '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐', '▀', //
+    'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩', //
+    '≡', '±', '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{a0}',
+];
+
+/// 文件内容与 SAUCE 记录之间的文件结束符（Ctrl-Z）
+const EOF: u8 = 0x1a;
+
+/// 把一个字符编码为 CP437，没有对应字符时每列输出一个 `?`，保持对齐
+fn push_cp437(output: &mut Vec<u8>, ch: char) {
+    if ch.is_ascii() {
+        output.push(ch as u8);
+    } else if let Some(index) = CP437_HIGH.iter().position(|&c| c == ch) {
+        output.push(0x80 + index as u8);
+    } else {
+        let columns = ch.width().unwrap_or(1).max(1);
+        output.extend(std::iter::repeat_n(b'?', columns));
+    }
+}
+
+/// 最接近的 16 色编号，`bright` 为 false 时只在前 8 种颜色中选择
+pub(crate) fn nearest_basic(color: Color, bright: bool) -> u8 {
+    let count = if bright { 16 } else { 8 };
+    (0..count)
+        .min_by_key(|&index| color.distance(Color::from_ansi256(index)))
+        .unwrap_or(0)
+}
+
+/// 设置颜色的 SGR 序列：先重置，前景色为 30–37（亮色另加粗体），背景色为 40–47
+fn push_sgr(output: &mut Vec<u8>, (fg, bg): (Option<u8>, Option<u8>)) {
+    output.extend_from_slice(b"\x1b[0");
+    if let Some(fg) = fg {
+        if fg >= 8 {
+            output.extend_from_slice(b";1");
+        }
+        output.extend_from_slice(format!(";{}", 30 + fg % 8).as_bytes());
+    }
+    if let Some(bg) = bg {
+        output.extend_from_slice(format!(";{}", 40 + bg).as_bytes());
+    }
+    output.push(b'm');
+}
+
+/// 按照经典 ANSI 画（`.ans`）的惯例编码字符格：字符转换为 CP437，颜色量化为 16 色
+/// （背景只有 8 色），换行为 CRLF，末尾是文件结束符和描述宽度、行数的 SAUCE 记录
+pub(crate) fn encode_ans(grid: &CellGrid) -> Vec<u8> {
+    let mut output = Vec::with_capacity(grid.width() * grid.height() + 129);
+    for row in grid.rows() {
+        // 去掉行尾没有颜色的空格
+        let end = row
+            .iter()
+            .rposition(|cell| (cell.ch, cell.fg, cell.bg) != (' ', None, None))
+            .map_or(0, |last| last + 1);
+        let mut current = (None, None);
+        for cell in &row[..end] {
+            let Cell { ch, fg, bg, .. } = *cell;
+            if ch == '\0' {
+                continue;
+            }
+            let colors = (
+                fg.map(|fg| nearest_basic(fg, true)),
+                bg.map(|bg| nearest_basic(bg, false)),
+            );
+            if colors != current {
+                push_sgr(&mut output, colors);
+                current = colors;
+            }
+            push_cp437(&mut output, ch);
+        }
+        if current != (None, None) {
+            output.extend_from_slice(b"\x1b[0m");
+        }
+        output.extend_from_slice(b"\r\n");
+    }
+    let record = sauce(output.len(), grid.width(), grid.height(), &today());
+    output.push(EOF);
+    output.extend_from_slice(&record);
+    output
+}
+
+/// 128 字节的 SAUCE 记录，类型为 ANSi 字符画，`date` 是 `CCYYMMDD`
+fn sauce(file_size: usize, width: usize, lines: usize, date: &str) -> [u8; 128] {
+    let mut record = [0; 128];
+    record[..7].copy_from_slice(b"SAUCE00");
+    // 标题、作者和组织留空
+    record[7..82].fill(b' ');
+    record[82..90].copy_from_slice(&date.as_bytes()[..8]);
+    let clamp = |value: usize| value.min(u16::MAX as usize) as u16;
+    record[90..94].copy_from_slice(&(file_size.min(u32::MAX as usize) as u32).to_le_bytes());
+    record[94] = 1;
+    record[95] = 1;
+    record[96..98].copy_from_slice(&clamp(width).to_le_bytes());
+    record[98..100].copy_from_slice(&clamp(lines).to_le_bytes());
+    record
+}
+
+/// 今天的 UTC 日期，格式为 `CCYYMMDD`
+fn today() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let (year, month, day) = civil_from_days((seconds / 86400) as i64);
+    format!("{:04}{:02}{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_ans() {
+        let grid = CellGrid::from_ansi(
+            "\x1b[38;5;196m█\x1b[48;2;0;0;200m▀\x1b[0m┌⠁\n\x1b[38;2;0;0;90m中\x1b[0mé\n",
+        );
+        let output = encode_ans(&grid);
+        let (content, record) = output.split_at(output.len() - 129);
+        // 真彩色和 256 色量化为 16 色，亮色使用粗体
+        assert_eq!(
+            content,
+            b"\x1b[0;1;31m\xdb\x1b[0;1;31;44m\xdf\x1b[0m\xda?\r\n\
+              \x1b[0;30m??\x1b[0m\x82\r\n"
+                .as_slice()
+        );
+        assert_eq!(record[0], EOF);
+        let record = &record[1..];
+        assert_eq!(&record[..7], b"SAUCE00");
+        assert_eq!(
+            u32::from_le_bytes(record[90..94].try_into().unwrap()),
+            content.len() as u32
+        );
+        assert_eq!(&record[94..100], &[1, 1, 4, 0, 2, 0]);
+
+        assert_eq!(&sauce(0, 0, 0, "20261016")[82..90], b"20261016");
+    }
+}
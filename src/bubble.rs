@@ -0,0 +1,447 @@
+use crate::text::{ansi_regex, grapheme_width, strip_ansi, text_width, to_ascii};
+use crate::{
+    AmbiguousWidth, BubbleKind, BubblePosition, Color, OutputProfile, Rainbow, RenderOptions,
+    TextStyle, BUFSIZE,
+};
+use smallvec::SmallVec;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// 消息框边框使用的字符，每个字符应占一列
+///
+/// 预设之外的样式可以从预设开始覆盖部分字符，例如
+/// `BubbleStyle { top: ['.', '~', '.'], ..BubbleStyle::ROUNDED }`。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct BubbleStyle {
+    /// 顶部边框：左角、横线、右角
+    pub top: [char; 3],
+    /// 底部边框：左角、横线、右角
+    pub bottom: [char; 3],
+    /// 左侧边框：只有一行时、第一行、中间的行、最后一行
+    pub left: [char; 4],
+    /// 右侧边框，顺序与 `left` 相同
+    pub right: [char; 4],
+}
+
+impl BubbleStyle {
+    /// 经典的 cowsay 消息框：`_`、`-`、`<`、`>`、`/`、`\`
+    pub const ASCII: BubbleStyle = BubbleStyle {
+        top: [' ', '_', ' '],
+        bottom: [' ', '-', ' '],
+        left: ['<', '/', '|', '\\'],
+        right: ['>', '\\', '|', '/'],
+    };
+    /// `┌─┐│└┘`
+    pub const UNICODE_BOX: BubbleStyle = BubbleStyle {
+        top: ['┌', '─', '┐'],
+        bottom: ['└', '─', '┘'],
+        left: ['│'; 4],
+        right: ['│'; 4],
+    };
+    /// `╭─╮│╰╯`
+    pub const ROUNDED: BubbleStyle = BubbleStyle {
+        top: ['╭', '─', '╮'],
+        bottom: ['╰', '─', '╯'],
+        left: ['│'; 4],
+        right: ['│'; 4],
+    };
+    /// `╔═╗║╚╝`
+    pub const DOUBLE: BubbleStyle = BubbleStyle {
+        top: ['╔', '═', '╗'],
+        bottom: ['╚', '═', '╝'],
+        left: ['║'; 4],
+        right: ['║'; 4],
+    };
+}
+
+impl Default for BubbleStyle {
+    fn default() -> Self {
+        BubbleStyle::ASCII
+    }
+}
+
+/// 无法识别的消息框样式名称
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseBubbleStyleError(String);
+
+impl std::fmt::Display for ParseBubbleStyleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "未知的消息框样式: {}", self.0)
+    }
+}
+
+impl std::error::Error for ParseBubbleStyleError {}
+
+impl std::str::FromStr for BubbleStyle {
+    type Err = ParseBubbleStyleError;
+
+    /// 预设的名称：`ascii`、`box`（或 `unicode-box`）、`rounded`、`double`
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "ascii" => Ok(BubbleStyle::ASCII),
+            "box" | "unicode-box" => Ok(BubbleStyle::UNICODE_BOX),
+            "rounded" => Ok(BubbleStyle::ROUNDED),
+            "double" => Ok(BubbleStyle::DOUBLE),
+            _ => Err(ParseBubbleStyleError(s.to_string())),
+        }
+    }
+}
+
+/// 实际使用的边框：思考时两侧是圆括号，ASCII 输出时只能使用 ASCII 字符
+fn effective_style(options: &RenderOptions) -> BubbleStyle {
+    let mut style = if options.profile == OutputProfile::Ascii {
+        BubbleStyle::ASCII
+    } else {
+        options.bubble_style
+    };
+    if options.bubble == BubbleKind::Think {
+        style.left = ['('; 4];
+        style.right = [')'; 4];
+    }
+    style
+}
+
+/// 内容宽度为 `width` 的消息框包括边框在内占用的列数
+pub(crate) fn bubble_width(width: usize, options: &RenderOptions) -> usize {
+    inner_width(width, options) + 2
+}
+
+/// 两个角之间的宽度，消息框会加宽以放下说话者的名字
+fn inner_width(width: usize, options: &RenderOptions) -> usize {
+    let tag_width =
+        speaker_tag(options).map_or(0, |tag| text_width(&tag, options.ambiguous_width) + 4);
+    (width + 2 * options.padding.horizontal).max(tag_width)
+}
+
+/// 顶部边框中说话者的名字，去掉其中的转义序列和控制字符
+fn speaker_tag(options: &RenderOptions) -> Option<String> {
+    let name = options.speaker.as_deref()?;
+    let mut name = strip_ansi(name).replace(char::is_control, "");
+    if options.profile == OutputProfile::Ascii {
+        name = to_ascii(&name);
+    }
+    let name = name.trim();
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// 绘制水平边框，右角是空格时省略，避免行尾空白；`width` 不包括两个角
+///
+/// 有 `tag` 时把它嵌在左角之后，如 `┌─ Ferris ───┐`，宽度按 `ambiguous` 计算。
+fn write_border(
+    write_buffer: &mut SmallVec<[u8; BUFSIZE]>,
+    chars: [char; 3],
+    width: usize,
+    tag: Option<&str>,
+    ambiguous: AmbiguousWidth,
+) {
+    let mut utf8 = [0; 4];
+    let [left, horizontal, right] = chars.map(|c| c.encode_utf8(&mut utf8).as_bytes().to_vec());
+    write_buffer.extend_from_slice(&left);
+    let mut used = 0;
+    if let Some(tag) = tag {
+        write_buffer.extend_from_slice(&horizontal);
+        write_buffer.push(b' ');
+        write_buffer.extend_from_slice(tag.as_bytes());
+        write_buffer.push(b' ');
+        used = text_width(tag, ambiguous) + 3;
+    }
+    for _ in used..width {
+        write_buffer.extend_from_slice(&horizontal);
+    }
+    if chars[2] != ' ' {
+        write_buffer.extend_from_slice(&right);
+    }
+}
+
+/// 绘制消息框，底部边框之后不换行
+pub(crate) fn write_bubble(
+    write_buffer: &mut SmallVec<[u8; BUFSIZE]>,
+    lines: &[&str],
+    width: usize,
+    options: &RenderOptions,
+) {
+    let style = effective_style(options);
+    let padding = options.padding;
+    let inner_width = inner_width(width, options);
+    let width = inner_width - 2 * padding.horizontal;
+    // 上下的内边距是空行
+    let blank = std::iter::repeat_n("", padding.vertical);
+    let lines: Vec<&str> = blank
+        .clone()
+        .chain(lines.iter().copied())
+        .chain(blank)
+        .collect();
+    let line_count = lines.len();
+    let mut utf8 = [0; 4];
+    let (text_style, rainbow) = match options.rainbow {
+        _ if !options.profile.allows_escapes() => (String::new(), None),
+        // 彩虹渐变代替文字样式中的颜色
+        Some(rainbow) => {
+            let style = TextStyle {
+                color: None,
+                ..options.text_style
+            };
+            (style.escape(), Some(rainbow))
+        }
+        None => (options.text_style.escape(), None),
+    };
+
+    // 绘制消息框顶部
+    write_border(
+        write_buffer,
+        style.top,
+        inner_width,
+        speaker_tag(options).as_deref(),
+        options.ambiguous_width,
+    );
+    write_buffer.push(b'\n');
+
+    // 绘制消息内容
+    for (i, line) in lines.iter().enumerate() {
+        let side = if line_count == 1 {
+            0
+        } else if i == 0 {
+            1
+        } else if i == line_count - 1 {
+            3
+        } else {
+            2
+        };
+
+        write_buffer.extend_from_slice(style.left[side].encode_utf8(&mut utf8).as_bytes());
+        write_buffer.extend(std::iter::repeat_n(b' ', padding.horizontal));
+        // 宽度按不带样式的文字计算
+        let line_len = text_width(line, options.ambiguous_width);
+        let text = match rainbow {
+            Some(rainbow) => rainbow_line(line, i, rainbow, options),
+            None => line.to_string(),
+        };
+        let styled = !text_style.is_empty() && !line.is_empty();
+        if styled {
+            write_buffer.extend_from_slice(styled_line(&text, &text_style).as_bytes());
+        } else {
+            write_buffer.extend_from_slice(text.as_bytes());
+        }
+        // 保留的转义序列和文字样式不能影响到边框
+        if styled || text.contains('\x1b') {
+            write_buffer.extend_from_slice(b"\x1b[0m");
+        }
+        for _ in line_len..width {
+            write_buffer.push(b' ');
+        }
+        write_buffer.extend(std::iter::repeat_n(b' ', padding.horizontal));
+        write_buffer.extend_from_slice(style.right[side].encode_utf8(&mut utf8).as_bytes());
+        write_buffer.push(b'\n');
+    }
+
+    // 绘制消息框底部
+    write_border(
+        write_buffer,
+        style.bottom,
+        inner_width,
+        None,
+        options.ambiguous_width,
+    );
+}
+
+/// 给一行文字加上样式，行中的样式重置（如突出显示的结尾）之后重新设置
+fn styled_line(line: &str, style: &str) -> String {
+    let mut styled = style.to_string();
+    let mut last = 0;
+    for m in ansi_regex().find_iter(line) {
+        styled.push_str(&line[last..m.end()]);
+        if matches!(m.as_str(), "\x1b[0m" | "\x1b[m") {
+            styled.push_str(style);
+        }
+        last = m.end();
+    }
+    styled.push_str(&line[last..]);
+    styled
+}
+
+/// 给第 `row` 行的每个字符加上彩虹渐变的颜色，空白不着色；
+/// 已经带有样式的文本（如突出显示的部分）保持原样
+fn rainbow_line(line: &str, row: usize, rainbow: Rainbow, options: &RenderOptions) -> String {
+    let mut result = String::with_capacity(line.len() * 8);
+    let mut column = 0;
+    let mut styled = false;
+    let mut last = 0;
+    let escapes = ansi_regex()
+        .find_iter(line)
+        .map(|m| (m.start(), m.end()))
+        .chain([(line.len(), line.len())]);
+    for (start, end) in escapes {
+        for grapheme in line[last..start].graphemes(true) {
+            if !styled && !grapheme.trim().is_empty() {
+                let hue = rainbow.start_hue + rainbow.frequency * (column + row) as f32;
+                result.push_str(&Color::from_hue(hue).fg_escape());
+            }
+            result.push_str(grapheme);
+            column += grapheme_width(grapheme, options.ambiguous_width);
+        }
+        let sequence = &line[start..end];
+        if !sequence.is_empty() {
+            styled = !matches!(sequence, "\x1b[0m" | "\x1b[m");
+        }
+        result.push_str(sequence);
+        last = end;
+    }
+    result
+}
+
+/// 连接线的两行，从消息框一侧开始
+pub(crate) fn tail_marks(kind: BubbleKind) -> [&'static [u8]; 2] {
+    match kind {
+        BubbleKind::Say => [b"\\", b"\\"],
+        BubbleKind::Think => [b"O", b"o"],
+    }
+}
+
+/// 消息框在图片左侧或右侧时的水平连接线，从左到右
+pub(crate) fn horizontal_tail(kind: BubbleKind, position: BubblePosition) -> &'static str {
+    match (kind, position) {
+        (BubbleKind::Say, BubblePosition::Left) => "-->",
+        (BubbleKind::Say, _) => "<--",
+        (BubbleKind::Think, BubblePosition::Left) => "O o",
+        (BubbleKind::Think, _) => "o O",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BubblePadding;
+
+    #[test]
+    fn test_bubble_styles() {
+        let render = |style: BubbleStyle| {
+            let options = RenderOptions {
+                bubble_style: style,
+                ..RenderOptions::default()
+            };
+            let mut buffer = SmallVec::new();
+            write_bubble(&mut buffer, &["hi", "yo"], 2, &options);
+            String::from_utf8(buffer.to_vec()).unwrap()
+        };
+
+        assert_eq!(render(BubbleStyle::ASCII), " ____\n/ hi \\\n\\ yo /\n ----");
+        assert_eq!(
+            render(BubbleStyle::ROUNDED),
+            "╭────╮\n│ hi │\n│ yo │\n╰────╯"
+        );
+        let custom = BubbleStyle {
+            top: ['.', '~', '.'],
+            ..BubbleStyle::DOUBLE
+        };
+        assert_eq!(render(custom), ".~~~~.\n║ hi ║\n║ yo ║\n╚════╝");
+        assert_eq!("box".parse(), Ok(BubbleStyle::UNICODE_BOX));
+    }
+
+    #[test]
+    fn test_text_style() {
+        let options = RenderOptions {
+            text_style: TextStyle {
+                color: Some(Color::rgb(255, 0, 0)),
+                bold: true,
+                italic: false,
+            },
+            ..RenderOptions::default()
+        };
+        let mut buffer = SmallVec::new();
+        write_bubble(&mut buffer, &["a \x1b[1mb\x1b[0m c", "d"], 5, &options);
+        let output = String::from_utf8(buffer.to_vec()).unwrap();
+        let style = "\x1b[1m\x1b[38;2;255;0;0m";
+
+        assert_eq!(
+            output,
+            format!(
+                " _______\n/ {s}a \x1b[1mb\x1b[0m{s} c\x1b[0m \\\n\\ {s}d\x1b[0m     /\n -------",
+                s = style
+            )
+        );
+    }
+
+    #[test]
+    fn test_rainbow_text() {
+        let rainbow = Rainbow {
+            frequency: 120.0,
+            start_hue: 0.0,
+        };
+        let options = RenderOptions::default();
+        let [red, green, blue] = [0.0, 120.0, 240.0].map(|hue| Color::from_hue(hue).fg_escape());
+        assert_eq!(blue, "\x1b[38;2;0;0;255m");
+
+        assert_eq!(
+            rainbow_line("a b", 0, rainbow, &options),
+            format!("{}a {}b", red, blue)
+        );
+        // 第二行的颜色向后错开一列，突出显示的文本保持原样
+        assert_eq!(
+            rainbow_line("a\x1b[1mb\x1b[0mc", 1, rainbow, &options),
+            format!("{}a\x1b[1mb\x1b[0m{}c", green, red)
+        );
+    }
+
+    #[test]
+    fn test_speaker_tag() {
+        let render = |speaker: &str, style: BubbleStyle| {
+            let options = RenderOptions {
+                speaker: Some(speaker.to_string()),
+                bubble_style: style,
+                ..RenderOptions::default()
+            };
+            let mut buffer = SmallVec::new();
+            write_bubble(&mut buffer, &["hello world"], 11, &options);
+            (
+                String::from_utf8(buffer.to_vec()).unwrap(),
+                bubble_width(11, &options),
+            )
+        };
+
+        let (output, width) = render("Ferris", BubbleStyle::UNICODE_BOX);
+        assert_eq!(output, "┌─ Ferris ────┐\n│ hello world │\n└─────────────┘");
+        assert_eq!(width, 15);
+        // 名字比消息长时加宽消息框
+        let (output, width) = render("\x1b[1mFerris the crab", BubbleStyle::ASCII);
+        assert_eq!(
+            output,
+            " _ Ferris the crab _\n< hello world       >\n -------------------"
+        );
+        assert_eq!(width, 21);
+
+        // 歧义宽度的字符按全角计算时，边框仍然与消息对齐（ASCII 样式省略了右角）
+        let options = RenderOptions {
+            speaker: Some("①①①①①①①①".to_string()),
+            bubble_style: BubbleStyle::ASCII,
+            ambiguous_width: AmbiguousWidth::Wide,
+            ..RenderOptions::default()
+        };
+        let mut buffer = SmallVec::new();
+        write_bubble(&mut buffer, &["hello world"], 11, &options);
+        let output = String::from_utf8(buffer.to_vec()).unwrap();
+        let widths: Vec<usize> = output
+            .lines()
+            .map(|line| text_width(line, AmbiguousWidth::Wide))
+            .collect();
+        assert_eq!(widths, [21, 22, 21], "{}", output);
+        assert_eq!(bubble_width(11, &options), 22);
+    }
+
+    #[test]
+    fn test_bubble_padding() {
+        let options = RenderOptions {
+            padding: BubblePadding {
+                horizontal: 2,
+                vertical: 1,
+            },
+            ..RenderOptions::default()
+        };
+        let mut buffer = SmallVec::new();
+        write_bubble(&mut buffer, &["hi"], 2, &options);
+        let output = String::from_utf8(buffer.to_vec()).unwrap();
+
+        assert_eq!(output, " ______\n/      \\\n|  hi  |\n\\      /\n ------");
+        assert_eq!(bubble_width(2, &options), 8);
+    }
+}
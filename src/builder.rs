@@ -0,0 +1,202 @@
+use crate::lines::named_lines;
+use crate::{
+    collect_string, say_named, BubbleKind, BubblePosition, BubbleStyle, Color, Dither, Layout,
+    PixelMode, RenderOptions, RenderReport, RenderedLines, TailAnchor, TextStyle,
+};
+use image::DynamicImage;
+use std::io::{Result, Write};
+
+/// 逐项设置选项的渲染入口
+///
+/// 没有设置的选项使用 [`RenderOptions::default`]。以后增加的选项只会增加新的方法，
+/// 不会改变已有的调用方式。
+///
+/// # Example
+///
+/// ```rust
+/// use image::{DynamicImage, RgbaImage};
+/// use pixel_says::{PixelMode, PixelSays};
+///
+/// let sprite = DynamicImage::ImageRgba8(RgbaImage::new(4, 4));
+/// let mut output = Vec::new();
+/// PixelSays::new(sprite)
+///     .message("Hello!")
+///     .mode(PixelMode::Ansi256)
+///     .width(24)
+///     .render(&mut output)
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct PixelSays {
+    image: DynamicImage,
+    name: Option<String>,
+    message: String,
+    options: RenderOptions,
+}
+
+impl PixelSays {
+    /// 以默认选项绘制图片，消息为空
+    pub fn new(image: DynamicImage) -> Self {
+        PixelSays {
+            image,
+            name: None,
+            message: String::new(),
+            options: RenderOptions::default(),
+        }
+    }
+
+    /// 消息框中的文字
+    pub fn message(mut self, message: impl Into<String>) -> Self {
+        self.message = message.into();
+        self
+    }
+
+    /// 文字描述中使用的图片名称
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// 替换全部选项，之后调用的方法在此基础上修改
+    pub fn options(mut self, options: RenderOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// 像素转换模式
+    pub fn mode(mut self, mode: PixelMode) -> Self {
+        self.options.mode = mode;
+        self
+    }
+
+    /// 消息文本的最大宽度
+    pub fn width(mut self, max_width: usize) -> Self {
+        self.options.max_width = max_width;
+        self
+    }
+
+    /// 把图片的色相旋转 `degrees` 度
+    pub fn hue_rotate(mut self, degrees: f32) -> Self {
+        self.options.hue_rotate = degrees;
+        self
+    }
+
+    /// 按亮度把图片重新着色为 `color`
+    pub fn tint(mut self, color: Color) -> Self {
+        self.options.tint = Some(color);
+        self
+    }
+
+    /// 把每个通道量化为 `levels` 级
+    pub fn posterize(mut self, levels: u8) -> Self {
+        self.options.posterize = Some(levels);
+        self
+    }
+
+    /// 颜色有限的模式使用的抖动算法
+    pub fn dither(mut self, dither: Dither) -> Self {
+        self.options.dither = dither;
+        self
+    }
+
+    /// 消息与图片的排列方式
+    pub fn layout(mut self, layout: Layout) -> Self {
+        self.options.layout = layout;
+        self
+    }
+
+    /// 消息框相对于图片的位置
+    pub fn position(mut self, position: BubblePosition) -> Self {
+        self.options.position = position;
+        self
+    }
+
+    /// 连接线的位置
+    pub fn tail(mut self, tail: TailAnchor) -> Self {
+        self.options.tail = tail;
+        self
+    }
+
+    /// 说话还是思考
+    pub fn bubble(mut self, bubble: BubbleKind) -> Self {
+        self.options.bubble = bubble;
+        self
+    }
+
+    /// 消息框边框的字符
+    pub fn bubble_style(mut self, style: BubbleStyle) -> Self {
+        self.options.bubble_style = style;
+        self
+    }
+
+    /// 消息文字的样式
+    pub fn text_style(mut self, style: TextStyle) -> Self {
+        self.options.text_style = style;
+        self
+    }
+
+    /// 当前的选项
+    pub fn render_options(&self) -> &RenderOptions {
+        &self.options
+    }
+
+    /// 渲染并写入 `writer`
+    pub fn render<W>(self, writer: W) -> Result<RenderReport>
+    where
+        W: Write,
+    {
+        say_named(
+            self.image,
+            self.name.as_deref(),
+            &self.message,
+            &self.options,
+            writer,
+        )
+    }
+
+    /// 渲染并返回输出的字符串
+    pub fn render_to_string(self) -> Result<String> {
+        collect_string(|writer| self.render(writer))
+    }
+
+    /// 逐行渲染，见 [`crate::RenderedLines`]
+    pub fn lines(self) -> Result<RenderedLines> {
+        named_lines(
+            self.image,
+            self.name.as_deref(),
+            &self.message,
+            &self.options,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::say_from_dynamic_image_with_options;
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn test_builder_matches_options() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(4, 2, Rgba([255, 0, 0, 255])));
+        let options = RenderOptions {
+            mode: PixelMode::Ansi256,
+            max_width: 8,
+            bubble: BubbleKind::Think,
+            ..RenderOptions::default()
+        };
+        let mut expected = Vec::new();
+        say_from_dynamic_image_with_options(img.clone(), "hello there", &options, &mut expected)
+            .unwrap();
+
+        let mut output = Vec::new();
+        let builder = PixelSays::new(img)
+            .message("hello there")
+            .mode(PixelMode::Ansi256)
+            .width(8)
+            .bubble(BubbleKind::Think);
+        assert_eq!(builder.render_options().max_width, 8);
+        builder.render(&mut output).unwrap();
+        assert_eq!(output, expected);
+    }
+}
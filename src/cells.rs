@@ -0,0 +1,775 @@
+use crate::animation::json_string;
+use crate::ans::encode_ans;
+use crate::convert::{
+    art_height, art_width, is_export, pixel_column, pixel_rows_per_line, quadrant_mask,
+};
+#[cfg(feature = "gui")]
+use crate::overlay::glyph;
+#[cfg(feature = "png")]
+use crate::png::encode_png;
+use crate::report::CountingWriter;
+use crate::text::ansi_regex;
+use crate::CompiledSprite;
+use crate::{Color, OutputBackend, RenderOptions, RenderReport};
+use image::{DynamicImage, GenericImageView};
+#[cfg(any(feature = "gui", feature = "png"))]
+use image::{Rgba, RgbaImage};
+use std::borrow::Cow;
+use std::io::{Result, Write};
+use unicode_width::UnicodeWidthChar;
+
+/// 终端中的一个字符格
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    /// 显示的字符；全角字符之后的一格为 `'\0'`，表示被前一个字符占用
+    pub ch: char,
+    /// 前景色，`None` 表示终端的默认颜色
+    pub fg: Option<Color>,
+    /// 背景色，`None` 表示终端的默认颜色
+    pub bg: Option<Color>,
+    /// 图片中对应像素的最大不透明度，按 [`RenderOptions::alpha_threshold`] 解析后只有 0 和 255；
+    /// 消息框、连接线、边框等不属于图片的字符格为 `None`，因此可以区分透明的像素和消息框中的空格
+    pub alpha: Option<u8>,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Cell {
+            ch: ' ',
+            fg: None,
+            bg: None,
+            alpha: None,
+        }
+    }
+}
+
+/// 按行排列的字符格，与终端中显示的内容一一对应，便于在 GUI 中绘制
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CellGrid {
+    width: usize,
+    height: usize,
+    cells: Vec<Cell>,
+}
+
+impl CellGrid {
+    /// 解析带有 ANSI 颜色的文本，支持 24 位颜色（`38;2`、`48;2`）、256 色（`38;5`、`48;5`）
+    /// 与重置，其他转义序列会被忽略
+    pub fn from_ansi(text: &str) -> Self {
+        let mut rows: Vec<Vec<Cell>> = Vec::new();
+        for line in text.lines() {
+            let mut row = Vec::new();
+            let (mut fg, mut bg) = (None, None);
+            let mut last = 0;
+            let matches = ansi_regex()
+                .find_iter(line)
+                .map(|m| (m.start(), m.end()))
+                .chain([(line.len(), line.len())]);
+            for (start, end) in matches {
+                for ch in line[last..start].chars() {
+                    row.push(Cell {
+                        ch,
+                        fg,
+                        bg,
+                        alpha: None,
+                    });
+                    if ch.width() == Some(2) {
+                        row.push(Cell {
+                            ch: '\0',
+                            fg,
+                            bg,
+                            alpha: None,
+                        });
+                    }
+                }
+                apply_sgr(&line[start..end], &mut fg, &mut bg);
+                last = end;
+            }
+            rows.push(row);
+        }
+
+        let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+        let height = rows.len();
+        let mut cells = Vec::with_capacity(width * height);
+        for mut row in rows {
+            row.resize(width, Cell::default());
+            cells.extend(row);
+        }
+        CellGrid {
+            width,
+            height,
+            cells,
+        }
+    }
+
+    /// 列数
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// 行数
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// 第 `y` 行第 `x` 列的字符格
+    pub fn get(&self, x: usize, y: usize) -> Option<&Cell> {
+        (x < self.width && y < self.height).then(|| &self.cells[y * self.width + x])
+    }
+
+    /// 按照排版相同的不透明度对照文本（见 [`alpha_map`]）设置图片字符格的不透明度
+    pub(crate) fn apply_alpha(&mut self, map: &CellGrid) {
+        let width = self.width;
+        for (index, cell) in self.cells.iter_mut().enumerate() {
+            let Some(mapped) = map.get(index % width, index / width) else {
+                continue;
+            };
+            let code = mapped.ch as u32;
+            if mapped.ch != cell.ch && (ALPHA_BASE..=ALPHA_BASE + 255).contains(&code) {
+                cell.alpha = Some((code - ALPHA_BASE) as u8);
+            }
+        }
+    }
+
+    /// 逐行访问
+    pub fn rows(&self) -> impl Iterator<Item = &[Cell]> {
+        self.cells.chunks(self.width.max(1))
+    }
+
+    /// 把字符格合成为一张图片，每格 `cell_width`x`cell_height` 像素
+    ///
+    /// 方块字符按形状填充，明暗字符按浓淡混合前景色和背景色，其他字符使用内置的
+    /// 3x5 像素字体绘制。没有颜色的前景按白色绘制，没有颜色的背景保持透明。
+    #[cfg(feature = "gui")]
+    pub fn to_image(&self, cell_width: u32, cell_height: u32) -> RgbaImage {
+        let (cell_width, cell_height) = (cell_width.max(1), cell_height.max(1));
+        let mut img = RgbaImage::new(
+            self.width as u32 * cell_width,
+            self.height as u32 * cell_height,
+        );
+        for (y, row) in self.rows().enumerate() {
+            for (x, cell) in row.iter().enumerate() {
+                let (left, top) = (x as u32 * cell_width, y as u32 * cell_height);
+                draw_cell(&mut img, cell, left, top, cell_width, cell_height);
+            }
+        }
+        img
+    }
+
+    /// 转换为 HTML 的 `<pre>` 片段，颜色相同的相邻字符格合并为一个 `<span>`
+    ///
+    /// 每行末尾没有颜色的空格会被省去。
+    pub fn to_html(&self) -> String {
+        let mut html = String::from("<pre style=\"line-height:1\">\n");
+        for row in self.rows() {
+            let end = row
+                .iter()
+                .rposition(|cell| *cell != Cell::default())
+                .map_or(0, |last| last + 1);
+            let mut current = (None, None);
+            for cell in &row[..end] {
+                if cell.ch == '\0' {
+                    continue;
+                }
+                if (cell.fg, cell.bg) != current {
+                    if current != (None, None) {
+                        html.push_str("</span>");
+                    }
+                    current = (cell.fg, cell.bg);
+                    if current != (None, None) {
+                        html.push_str(&span_tag(cell.fg, cell.bg));
+                    }
+                }
+                push_escaped(&mut html, cell.ch);
+            }
+            if current != (None, None) {
+                html.push_str("</span>");
+            }
+            html.push('\n');
+        }
+        html.push_str("</pre>\n");
+        html
+    }
+
+    /// 转换为 SVG 文档，每格 8x16：背景色和方块字符绘制为矩形，其他字符绘制为文字
+    ///
+    /// 颜色和形状相同的相邻字符格合并为一个元素。没有颜色的前景按黑色绘制，
+    /// 没有颜色的背景保持透明。
+    pub fn to_svg(&self) -> String {
+        let (cell_width, cell_height) = SVG_CELL;
+        let (width, height) = (self.width * cell_width, self.height * cell_height);
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+             viewBox=\"0 0 {width} {height}\" font-family=\"monospace\" font-size=\"14\">\n"
+        );
+        for (y, row) in self.rows().enumerate() {
+            let top = y * cell_height;
+            for (x, run) in runs(row, |a, b| a.bg == b.bg) {
+                if let Some(bg) = run[0].bg {
+                    let left = x * cell_width;
+                    push_rect(
+                        &mut svg,
+                        (left, top, run.len() * cell_width, cell_height),
+                        Some(bg),
+                        "",
+                    );
+                }
+            }
+            for (x, run) in runs(row, |a, b| a.ch == b.ch && a.fg == b.fg) {
+                push_blocks(&mut svg, run, x * cell_width, top);
+            }
+            for (x, run) in runs(row, |a, b| is_block(a.ch) == is_block(b.ch) && a.fg == b.fg) {
+                // 去掉两端的空格
+                let Some(first) = run.iter().position(|cell| cell.ch != ' ') else {
+                    continue;
+                };
+                let last = run.iter().rposition(|cell| cell.ch != ' ').unwrap_or(first);
+                if is_block(run[0].ch) {
+                    continue;
+                }
+                svg.push_str(&format!(
+                    "<text x=\"{}\" y=\"{}\" textLength=\"{}\" lengthAdjust=\"spacingAndGlyphs\" \
+                     xml:space=\"preserve\"{}>",
+                    (x + first) * cell_width,
+                    top + SVG_BASELINE,
+                    (last + 1 - first) * cell_width,
+                    fill_attr(run[0].fg),
+                ));
+                for cell in &run[first..=last] {
+                    if cell.ch != '\0' {
+                        push_escaped(&mut svg, cell.ch);
+                    }
+                }
+                svg.push_str("</text>\n");
+            }
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// 转换为 JSON，便于网页前端或机器人自行绘制：`rows` 中每格有 `glyph`（字符）、
+    /// `fg` 和 `bg`（`[r, g, b, a]`，`null` 表示终端的默认颜色）以及 `alpha`
+    /// （图片像素的不透明度，不属于图片时为 `null`）
+    ///
+    /// 颜色的 `a` 是图片像素的不透明度，其他字符格为 255。全角字符之后被占用的一格的
+    /// `glyph` 是空字符串，因此每行都有 `width` 格。
+    pub fn to_json(&self) -> String {
+        let color = |color: Option<Color>, alpha: Option<u8>| match color {
+            Some(Color { r, g, b }) => format!("[{},{},{},{}]", r, g, b, alpha.unwrap_or(255)),
+            None => "null".to_string(),
+        };
+        let rows: Vec<String> = self
+            .rows()
+            .map(|row| {
+                let cells: Vec<String> = row
+                    .iter()
+                    .map(|cell| {
+                        let glyph = match cell.ch {
+                            '\0' => String::new(),
+                            ch => ch.to_string(),
+                        };
+                        format!(
+                            "{{\"glyph\":{},\"fg\":{},\"bg\":{},\"alpha\":{}}}",
+                            json_string(&glyph),
+                            color(cell.fg, cell.alpha),
+                            color(cell.bg, cell.alpha),
+                            cell.alpha
+                                .map_or("null".to_string(), |alpha| alpha.to_string())
+                        )
+                    })
+                    .collect();
+                format!("[{}]", cells.join(","))
+            })
+            .collect();
+        format!(
+            "{{\"width\":{},\"height\":{},\"rows\":[\n{}\n]}}\n",
+            self.width,
+            self.height,
+            rows.join(",\n")
+        )
+    }
+}
+
+/// 不透明度对照文本中表示图片字符格的字符为 `ALPHA_BASE` 加上不透明度，
+/// 这一段加拿大原住民音节文字在任何终端中都只占一列
+const ALPHA_BASE: u32 = 0x1400;
+
+/// 把转换好的图片文本中每个图片字符格换成表示其中像素最大不透明度的字符，边框不变
+///
+/// 对照文本与图片文本的排版完全相同，和消息框一起排版后就能按位置找到输出中
+/// 每个图片字符格的不透明度。`inset` 是图片之前边框占用的列数和行数。
+pub(crate) fn alpha_map(
+    art: &[u8],
+    img: &DynamicImage,
+    inset: usize,
+    options: &RenderOptions,
+) -> Vec<u8> {
+    let rows_per_line = pixel_rows_per_line(options);
+    let columns = art_width(img.width(), options);
+    let mut alpha = vec![vec![None; columns]; art_height(img.height(), options)];
+    for (x, y, pixel) in img.pixels() {
+        let start = pixel_column(x as usize, options);
+        let end = pixel_column(x as usize + 1, options).max(start + 1);
+        let line = &mut alpha[(y / rows_per_line) as usize];
+        for value in line[start.min(columns)..end.min(columns)].iter_mut() {
+            *value = Some(value.unwrap_or(0).max(pixel[3]));
+        }
+    }
+
+    let grid = CellGrid::from_ansi(&String::from_utf8_lossy(art));
+    let mut map = String::new();
+    for (y, row) in grid.rows().enumerate() {
+        for (x, cell) in row.iter().enumerate() {
+            let value = y
+                .checked_sub(inset)
+                .zip(x.checked_sub(inset))
+                .and_then(|(line, column)| *alpha.get(line)?.get(column)?);
+            match value.and_then(|value| char::from_u32(ALPHA_BASE + value as u32)) {
+                Some(ch) => map.push(ch),
+                None if cell.ch != '\0' => map.push(cell.ch),
+                None => {}
+            }
+        }
+        map.push('\n');
+    }
+    map.into_bytes()
+}
+
+/// SVG 中一个字符格的宽度和高度
+const SVG_CELL: (usize, usize) = (8, 16);
+
+/// SVG 中文字基线到字符格顶部的距离
+const SVG_BASELINE: usize = 12;
+
+/// 把一行分为 `same` 成立的连续字符格，返回每一段的起始列和字符格
+fn runs<F>(row: &[Cell], same: F) -> impl Iterator<Item = (usize, &[Cell])>
+where
+    F: FnMut(&Cell, &Cell) -> bool,
+{
+    row.chunk_by(same).scan(0, |x, run| {
+        let start = *x;
+        *x += run.len();
+        Some((start, run))
+    })
+}
+
+/// 是否为按形状绘制的方块或明暗字符
+fn is_block(ch: char) -> bool {
+    ch != ' ' && (matches!(ch, '░' | '▒' | '▓') || quadrant_mask(ch).is_some())
+}
+
+/// 把一段相同的方块字符绘制为矩形，左右相连的部分合并为一个矩形
+fn push_blocks(svg: &mut String, run: &[Cell], left: usize, top: usize) {
+    let (cell_width, cell_height) = SVG_CELL;
+    let (half_width, half_height) = (cell_width / 2, cell_height / 2);
+    let (width, fill) = (run.len() * cell_width, run[0].fg);
+    let opacity = match run[0].ch {
+        '░' => " fill-opacity=\"0.25\"",
+        '▒' => " fill-opacity=\"0.5\"",
+        '▓' => " fill-opacity=\"0.75\"",
+        _ => "",
+    };
+    if !opacity.is_empty() {
+        push_rect(svg, (left, top, width, cell_height), fill, opacity);
+        return;
+    }
+    let Some(mask) = quadrant_mask(run[0].ch).filter(|&mask| mask != 0) else {
+        return;
+    };
+    if mask == 0b1111 {
+        push_rect(svg, (left, top, width, cell_height), fill, "");
+        return;
+    }
+    for half in 0..2 {
+        let y = top + half * half_height;
+        let bits = mask >> (half * 2) & 0b11;
+        if bits == 0b11 {
+            push_rect(svg, (left, y, width, half_height), fill, "");
+            continue;
+        }
+        for side in 0..2 {
+            if bits & 1 << side != 0 {
+                for i in 0..run.len() {
+                    let x = left + i * cell_width + side * half_width;
+                    push_rect(svg, (x, y, half_width, half_height), fill, "");
+                }
+            }
+        }
+    }
+}
+
+/// 添加一个矩形，`extra` 为额外的属性
+fn push_rect(
+    svg: &mut String,
+    (x, y, width, height): (usize, usize, usize, usize),
+    fill: Option<Color>,
+    extra: &str,
+) {
+    svg.push_str(&format!(
+        "<rect x=\"{x}\" y=\"{y}\" width=\"{width}\" height=\"{height}\"{}{extra}/>\n",
+        fill_attr(fill)
+    ));
+}
+
+/// 填充颜色属性，没有颜色时使用默认的黑色
+fn fill_attr(color: Option<Color>) -> String {
+    color.map_or_else(String::new, |color| format!(" fill=\"{}\"", color))
+}
+
+/// 添加一个字符，转义 HTML 和 XML 中的特殊字符
+fn push_escaped(output: &mut String, ch: char) {
+    match ch {
+        '&' => output.push_str("&amp;"),
+        '<' => output.push_str("&lt;"),
+        '>' => output.push_str("&gt;"),
+        ch => output.push(ch),
+    }
+}
+
+/// 带有颜色样式的 `<span>` 开始标签
+fn span_tag(fg: Option<Color>, bg: Option<Color>) -> String {
+    let mut style = Vec::new();
+    if let Some(fg) = fg {
+        style.push(format!("color:{}", fg));
+    }
+    if let Some(bg) = bg {
+        style.push(format!("background-color:{}", bg));
+    }
+    format!("<span style=\"{}\">", style.join(";"))
+}
+
+/// 没有启用 `png` 功能时无法输出 PNG
+#[cfg(not(feature = "png"))]
+fn encode_png(_grid: &CellGrid) -> Result<Vec<u8>> {
+    Err(crate::PixelSaysError::FeatureDisabled("png").into())
+}
+
+/// 按照 `options.backend` 把渲染好的字符格导出为 HTML、SVG、PNG、JSON 或 `.ans` 文件，
+/// 统计中的字节数和行数按写入的内容计算，PNG 的行数则是图片中字符格的行数
+pub(crate) fn write_export<W>(
+    grid: &CellGrid,
+    options: &RenderOptions,
+    report: RenderReport,
+    writer: W,
+) -> Result<RenderReport>
+where
+    W: Write,
+{
+    debug_assert!(is_export(options));
+    let output = match options.backend {
+        OutputBackend::Svg => grid.to_svg().into_bytes(),
+        OutputBackend::Png => encode_png(grid)?,
+        OutputBackend::Json => grid.to_json().into_bytes(),
+        OutputBackend::Ans => encode_ans(grid),
+        _ => grid.to_html().into_bytes(),
+    };
+    let mut counter = CountingWriter::new(writer);
+    counter.write_all(&output)?;
+    Ok(RenderReport {
+        lines: match options.backend {
+            OutputBackend::Png => grid.height(),
+            _ => counter.lines,
+        },
+        bytes_written: counter.bytes,
+        ..report
+    })
+}
+
+/// 渲染为字符格，而不是输出 ANSI 文本
+///
+/// 得到的正是导出（如 `Json`）之前的字符格，图片字符格带有像素的不透明度；
+/// 使用 [`crate::OutputProfile::Ascii`] 时没有不透明度。
+pub fn render_cells(img: DynamicImage, message: &str, options: &RenderOptions) -> Result<CellGrid> {
+    // 按导出的方式转换图片，才会记录不透明度
+    let options = RenderOptions {
+        backend: if is_export(options) {
+            options.backend
+        } else {
+            OutputBackend::Json
+        },
+        ..options.clone()
+    };
+    CompiledSprite::compile(Cow::Owned(img), None, &options)?.say_cells(message)
+}
+
+/// 根据 SGR 序列（`ESC [ ... m`）更新当前颜色
+fn apply_sgr(escape: &str, fg: &mut Option<Color>, bg: &mut Option<Color>) {
+    let Some(params) = escape
+        .strip_prefix("\x1b[")
+        .and_then(|rest| rest.strip_suffix('m'))
+    else {
+        return;
+    };
+    let params: Vec<u32> = params.split(';').map(|p| p.parse().unwrap_or(0)).collect();
+    let mut i = 0;
+    while i < params.len() {
+        match params[i] {
+            0 => (*fg, *bg) = (None, None),
+            39 => *fg = None,
+            49 => *bg = None,
+            code @ 30..=37 => *fg = Some(Color::from_ansi256((code - 30) as u8)),
+            code @ 90..=97 => *fg = Some(Color::from_ansi256((code - 90 + 8) as u8)),
+            code @ 40..=47 => *bg = Some(Color::from_ansi256((code - 40) as u8)),
+            code @ 100..=107 => *bg = Some(Color::from_ansi256((code - 100 + 8) as u8)),
+            code @ (38 | 48) if params.get(i + 1) == Some(&2) && i + 4 < params.len() => {
+                let channel = |offset: usize| params[i + offset].min(255) as u8;
+                let color = Some(Color::rgb(channel(2), channel(3), channel(4)));
+                if code == 38 {
+                    *fg = color;
+                } else {
+                    *bg = color;
+                }
+                i += 4;
+            }
+            code @ (38 | 48) if params.get(i + 1) == Some(&5) && i + 2 < params.len() => {
+                let color = Some(Color::from_ansi256(params[i + 2].min(255) as u8));
+                if code == 38 {
+                    *fg = color;
+                } else {
+                    *bg = color;
+                }
+                i += 2;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+}
+
+/// 没有颜色的前景按白色绘制
+#[cfg(any(feature = "gui", feature = "png"))]
+pub(crate) const DEFAULT_FG: Color = Color::rgb(255, 255, 255);
+
+/// 绘制字符格的背景和方块、明暗字符，其他字符返回给调用者用字体绘制
+#[cfg(any(feature = "gui", feature = "png"))]
+pub(crate) fn draw_shapes(
+    img: &mut RgbaImage,
+    cell: &Cell,
+    (left, top): (u32, u32),
+    (width, height): (u32, u32),
+) -> Option<char> {
+    let fg = cell.fg.unwrap_or(DEFAULT_FG);
+    let bg = cell.bg.map(|c| Rgba([c.r, c.g, c.b, 255]));
+    let solid = Rgba([fg.r, fg.g, fg.b, 255]);
+    let shade = |alpha: f32| match bg {
+        Some(Rgba([r, g, b, _])) => {
+            let mix = |f: u8, b: u8| (f as f32 * alpha + b as f32 * (1.0 - alpha)).round() as u8;
+            Rgba([mix(fg.r, r), mix(fg.g, g), mix(fg.b, b), 255])
+        }
+        None => Rgba([fg.r, fg.g, fg.b, (alpha * 255.0).round() as u8]),
+    };
+
+    let mut fill = |x0: u32, y0: u32, w: u32, h: u32, pixel: Rgba<u8>| {
+        fill_rect(img, (left + x0, top + y0), (w, h), pixel);
+    };
+    if let Some(bg) = bg {
+        fill(0, 0, width, height, bg);
+    }
+    match cell.ch {
+        ' ' | '\0' => {}
+        '█' => fill(0, 0, width, height, solid),
+        '▀' => fill(0, 0, width, height / 2, solid),
+        '▄' => fill(0, height / 2, width, height - height / 2, solid),
+        ch if quadrant_mask(ch).is_some() => {
+            let mask = quadrant_mask(ch).unwrap_or(0);
+            let (half_width, half_height) = (width / 2, height / 2);
+            let columns = [(0, half_width), (half_width, width - half_width)];
+            let rows = [(0, half_height), (half_height, height - half_height)];
+            for bit in 0..4 {
+                if mask & 1 << bit != 0 {
+                    let ((x0, w), (y0, h)) = (columns[bit % 2], rows[bit / 2]);
+                    fill(x0, y0, w, h, solid);
+                }
+            }
+        }
+        '░' => fill(0, 0, width, height, shade(0.25)),
+        '▒' => fill(0, 0, width, height, shade(0.5)),
+        '▓' => fill(0, 0, width, height, shade(0.75)),
+        ch => return Some(ch),
+    }
+    None
+}
+
+/// 用颜色填充图片中的矩形
+#[cfg(any(feature = "gui", feature = "png"))]
+pub(crate) fn fill_rect(
+    img: &mut RgbaImage,
+    (x0, y0): (u32, u32),
+    (w, h): (u32, u32),
+    pixel: Rgba<u8>,
+) {
+    for y in y0..y0 + h {
+        for x in x0..x0 + w {
+            img.put_pixel(x, y, pixel);
+        }
+    }
+}
+
+#[cfg(feature = "gui")]
+fn draw_cell(img: &mut RgbaImage, cell: &Cell, left: u32, top: u32, width: u32, height: u32) {
+    let Some(ch) = draw_shapes(img, cell, (left, top), (width, height)) else {
+        return;
+    };
+    let fg = cell.fg.unwrap_or(DEFAULT_FG);
+    let solid = Rgba([fg.r, fg.g, fg.b, 255]);
+    // 字体每个像素放大为 scale x scale，居中放在格子里
+    let scale = (width / 4).min(height / 6).max(1);
+    let x0 = width.saturating_sub(3 * scale) / 2;
+    let y0 = height.saturating_sub(5 * scale) / 2;
+    for (dy, row) in glyph(ch).into_iter().enumerate() {
+        for dx in 0..3 {
+            if row & (0b100 >> dx) != 0 {
+                let (x, y) = (x0 + dx * scale, y0 + dy as u32 * scale);
+                if x + scale <= width && y + scale <= height {
+                    fill_rect(img, (left + x, top + y), (scale, scale), solid);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{say_from_dynamic_image_with_options, OutputBackend, RenderOptions};
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn test_cells_from_ansi() {
+        let grid =
+            CellGrid::from_ansi("ab\n\x1b[38;2;255;0;0m█\x1b[0m\x1b[48;2;0;0;255m \x1b[0m\n");
+
+        assert_eq!((grid.width(), grid.height()), (2, 2));
+        assert_eq!(grid.get(0, 0).unwrap().ch, 'a');
+        assert_eq!(grid.get(0, 1).unwrap().fg, Some(Color::rgb(255, 0, 0)));
+        assert_eq!(grid.get(1, 1).unwrap().bg, Some(Color::rgb(0, 0, 255)));
+        assert_eq!(grid.get(1, 1).unwrap().fg, None);
+    }
+
+    #[test]
+    #[cfg(feature = "gui")]
+    fn test_cells_to_image() {
+        let grid =
+            CellGrid::from_ansi("ab\n\x1b[38;2;255;0;0m█\x1b[0m\x1b[48;2;0;0;255m \x1b[0m\n");
+        let img = grid.to_image(4, 6);
+        assert_eq!(img.dimensions(), (8, 12));
+        assert_eq!(img.get_pixel(0, 6), &Rgba([255, 0, 0, 255]));
+        assert_eq!(img.get_pixel(4, 6), &Rgba([0, 0, 255, 255]));
+    }
+
+    #[test]
+    fn test_cells_to_html() {
+        let grid = CellGrid::from_ansi("a<b  \n\x1b[38;2;255;0;0m██\x1b[48;2;0;0;255m▀\x1b[0m&\n");
+        assert_eq!(
+            grid.to_html(),
+            "<pre style=\"line-height:1\">\na&lt;b\n<span style=\"color:#ff0000\">██</span>\
+             <span style=\"color:#ff0000;background-color:#0000ff\">▀</span>&amp;\n</pre>\n"
+        );
+
+        let options = RenderOptions {
+            backend: OutputBackend::Html,
+            ..RenderOptions::default()
+        };
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(2, 2, Rgba([0, 255, 0, 255])));
+        let mut output = Vec::new();
+        let report = say_from_dynamic_image_with_options(img, "hi", &options, &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("<pre") && output.ends_with("</pre>\n"));
+        assert!(output.contains("<span style=\"color:#00ff00\">"));
+        assert!(!output.contains('\x1b'));
+        assert_eq!(report.bytes_written, output.len());
+    }
+
+    #[test]
+    fn test_cells_to_svg() {
+        let grid = CellGrid::from_ansi("\x1b[38;2;255;0;0m██\x1b[48;2;0;0;255m▀▗\x1b[0m a<b\n");
+        let svg = grid.to_svg();
+        let mut lines = svg.lines();
+        assert!(lines.next().unwrap().contains("width=\"64\" height=\"16\""));
+        assert_eq!(
+            lines.collect::<Vec<_>>(),
+            [
+                "<rect x=\"16\" y=\"0\" width=\"16\" height=\"16\" fill=\"#0000ff\"/>",
+                "<rect x=\"0\" y=\"0\" width=\"16\" height=\"16\" fill=\"#ff0000\"/>",
+                "<rect x=\"16\" y=\"0\" width=\"8\" height=\"8\" fill=\"#ff0000\"/>",
+                "<rect x=\"28\" y=\"8\" width=\"4\" height=\"8\" fill=\"#ff0000\"/>",
+                "<text x=\"40\" y=\"12\" textLength=\"24\" lengthAdjust=\"spacingAndGlyphs\" \
+                 xml:space=\"preserve\">a&lt;b</text>",
+                "</svg>",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cells_to_json() {
+        let grid = CellGrid::from_ansi("\x1b[38;2;255;0;0m█\x1b[0m\"\n中\n");
+        let json: serde_json::Value = serde_json::from_str(&grid.to_json()).unwrap();
+        assert_eq!(
+            (json["width"].as_u64(), json["height"].as_u64()),
+            (Some(2), Some(2))
+        );
+        assert_eq!(
+            json["rows"][0][0],
+            serde_json::json!({"glyph": "█", "fg": [255, 0, 0, 255], "bg": null, "alpha": null})
+        );
+        assert_eq!(json["rows"][0][1]["glyph"], "\"");
+        assert_eq!(json["rows"][1][1]["glyph"], "");
+
+        let options = RenderOptions {
+            backend: OutputBackend::Json,
+            ..RenderOptions::default()
+        };
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, Rgba([0, 255, 0, 255])));
+        let cells = render_cells(img.clone(), "hi", &options).unwrap();
+        let mut output = Vec::new();
+        say_from_dynamic_image_with_options(img, "hi", &options, &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), cells.to_json());
+    }
+
+    #[test]
+    fn test_cells_alpha() {
+        // 左边的像素完全透明，右边的像素半透明，按默认阈值解析后为不透明
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(2, 2, |x, _| {
+            Rgba([255, 0, 0, if x == 0 { 0 } else { 128 }])
+        }));
+        for options in [
+            RenderOptions::default(),
+            RenderOptions {
+                position: crate::BubblePosition::Right,
+                ..RenderOptions::default()
+            },
+            RenderOptions {
+                layout: crate::Layout::Enclosed,
+                margin: 2,
+                ..RenderOptions::default()
+            },
+        ] {
+            let cells = render_cells(img.clone(), "hi", &options).unwrap();
+            let mut art: Vec<_> = cells
+                .rows()
+                .flatten()
+                .filter_map(|cell| cell.alpha)
+                .collect();
+            // 每个像素占两列
+            art.dedup();
+            assert_eq!(art, [0, 255, 0, 255], "{:?}", options.position);
+            // 消息框中的空格不属于图片
+            assert!(cells
+                .rows()
+                .flatten()
+                .any(|cell| cell.ch == ' ' && cell.alpha.is_none()));
+        }
+
+        let options = RenderOptions {
+            backend: OutputBackend::Json,
+            ..RenderOptions::default()
+        };
+        let mut output = Vec::new();
+        crate::render_image_to_text_with_options(img, &options, &mut output).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(json["rows"][0][0]["alpha"], 0);
+        assert_eq!(
+            json["rows"][0][2]["fg"],
+            serde_json::json!([255, 0, 0, 255])
+        );
+    }
+}
@@ -0,0 +1,339 @@
+use crate::Luminance;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// 颜色立方体每一级的分量值
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// 每个分量值在颜色立方体中最接近的一级
+const CUBE_INDEX: [u8; 256] = {
+    let mut table = [0; 256];
+    let mut c: usize = 0;
+    while c < 256 {
+        table[c] = match c {
+            0..=47 => 0,
+            48..=114 => 1,
+            _ => (c as u8 - 35) / 40,
+        };
+        c += 1;
+    }
+    table
+};
+
+/// 三个分量之和对应的灰阶编号（0–23）
+const GRAY_INDEX: [u8; 766] = {
+    let mut table = [0; 766];
+    let mut sum: usize = 0;
+    while sum < 766 {
+        let average = sum / 3;
+        let index = average.saturating_sub(3) / 10;
+        table[sum] = if index > 23 { 23 } else { index as u8 };
+        sum += 1;
+    }
+    table
+};
+
+/// 24 位 RGB 颜色
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(into = "String", try_from = "String"))]
+pub struct Color {
+    /// 红色分量
+    pub r: u8,
+    /// 绿色分量
+    pub g: u8,
+    /// 蓝色分量
+    pub b: u8,
+}
+
+impl Color {
+    /// 由 RGB 分量创建颜色
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Color { r, g, b }
+    }
+
+    /// 色相为 `hue` 度的最鲜艳的颜色，0 为红色、120 为绿色、240 为蓝色，超出范围时取模
+    pub fn from_hue(hue: f32) -> Self {
+        let hue = hue.rem_euclid(360.0) / 60.0;
+        let x = 1.0 - (hue % 2.0 - 1.0).abs();
+        let (r, g, b) = match hue as u32 {
+            0 => (1.0, x, 0.0),
+            1 => (x, 1.0, 0.0),
+            2 => (0.0, 1.0, x),
+            3 => (0.0, x, 1.0),
+            4 => (x, 0.0, 1.0),
+            _ => (1.0, 0.0, x),
+        };
+        let channel = |v: f32| (v * 255.0).round() as u8;
+        Color::rgb(channel(r), channel(g), channel(b))
+    }
+
+    /// xterm 256 色调色板中最接近的颜色编号
+    ///
+    /// 只在 6x6x6 颜色立方体（16–231）和灰阶（232–255）中查找，
+    /// 前 16 种基本颜色的实际显示效果取决于终端主题，因此不使用。
+    pub fn to_ansi256(self) -> u8 {
+        let channels = [self.r, self.g, self.b];
+        let cube_index = channels.map(|c| CUBE_INDEX[c as usize]);
+        let cube_color = cube_index.map(|i| CUBE_LEVELS[i as usize]);
+        let sum: usize = channels.iter().map(|&c| c as usize).sum();
+        let gray_index = GRAY_INDEX[sum];
+        let gray = 8 + 10 * gray_index;
+
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+        let cube_distance: u32 = (0..3).map(|i| d(channels[i], cube_color[i])).sum();
+        let gray_distance: u32 = channels.iter().map(|&c| d(c, gray)).sum();
+        if gray_distance < cube_distance {
+            232 + gray_index
+        } else {
+            16 + 36 * cube_index[0] + 6 * cube_index[1] + cube_index[2]
+        }
+    }
+
+    /// xterm 256 色调色板中编号对应的颜色，前 16 种使用 xterm 的默认值
+    pub fn from_ansi256(index: u8) -> Self {
+        const BASIC: [(u8, u8, u8); 16] = [
+            (0, 0, 0),
+            (205, 0, 0),
+            (0, 205, 0),
+            (205, 205, 0),
+            (0, 0, 238),
+            (205, 0, 205),
+            (0, 205, 205),
+            (229, 229, 229),
+            (127, 127, 127),
+            (255, 0, 0),
+            (0, 255, 0),
+            (255, 255, 0),
+            (92, 92, 255),
+            (255, 0, 255),
+            (0, 255, 255),
+            (255, 255, 255),
+        ];
+        match index {
+            0..=15 => {
+                let (r, g, b) = BASIC[index as usize];
+                Color::rgb(r, g, b)
+            }
+            16..=231 => {
+                let level = |i: u8| if i == 0 { 0 } else { 55 + 40 * i };
+                let i = index - 16;
+                Color::rgb(level(i / 36), level(i / 6 % 6), level(i % 6))
+            }
+            _ => {
+                let v = 8 + 10 * (index - 232);
+                Color::rgb(v, v, v)
+            }
+        }
+    }
+
+    /// 两种颜色的距离（平方和）
+    pub(crate) fn distance(self, other: Color) -> u32 {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+        d(self.r, other.r) + d(self.g, other.g) + d(self.b, other.b)
+    }
+
+    /// 前景色转义序列
+    pub(crate) fn fg_escape(self) -> String {
+        format!("\x1b[38;2;{};{};{}m", self.r, self.g, self.b)
+    }
+
+    /// 背景色转义序列
+    pub(crate) fn bg_escape(self) -> String {
+        format!("\x1b[48;2;{};{};{}m", self.r, self.g, self.b)
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+/// 把 sRGB 编码的分量转换为线性光强（0.0–1.0）
+pub(crate) fn srgb_to_linear(value: u8) -> f32 {
+    let v = value as f32 / 255.0;
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// BT.709 的亮度权重，以 2^16 为 1 的定点数，三者之和正好为 2^16
+const WEIGHTS: [u32; 3] = [13933, 46871, 4732];
+
+/// 每个分量在 sRGB 编码值上的亮度贡献
+const FAST_TABLE: [[u32; 256]; 3] = weighted_table();
+
+const fn weighted_table() -> [[u32; 256]; 3] {
+    let mut table = [[0; 256]; 3];
+    let mut value = 0;
+    while value < 256 {
+        table[0][value] = WEIGHTS[0] * value as u32;
+        table[1][value] = WEIGHTS[1] * value as u32;
+        table[2][value] = WEIGHTS[2] * value as u32;
+        value += 1;
+    }
+    table
+}
+
+/// 在线性光强下计算亮度时使用的查找表，以 2^24 为 1 的定点数
+struct LinearTables {
+    /// 每个分量的线性光强乘以权重
+    channels: [[u32; 256]; 3],
+    /// 编码值 k 和 k + 1 之间的分界对应的线性光强，用来把亮度编码回 0–255
+    bounds: [u32; 255],
+}
+
+fn linear_tables() -> &'static LinearTables {
+    static TABLES: OnceLock<LinearTables> = OnceLock::new();
+    TABLES.get_or_init(|| {
+        let scale = (1 << 8) as f32;
+        let channels = WEIGHTS.map(|weight| {
+            std::array::from_fn(|value| {
+                (srgb_to_linear(value as u8) * weight as f32 * scale).round() as u32
+            })
+        });
+        let bounds = std::array::from_fn(|k| {
+            let v = (k as f32 + 0.5) / 255.0;
+            let linear = if v <= 0.04045 {
+                v / 12.92
+            } else {
+                ((v + 0.055) / 1.055).powf(2.4)
+            };
+            (linear * (1 << 24) as f32).round() as u32
+        });
+        LinearTables { channels, bounds }
+    })
+}
+
+/// 像素的亮度（ITU-R BT.709），结果为 sRGB 编码的 0–255
+///
+/// 两种方式都只查表和做整数运算，不需要逐个像素做浮点计算。
+pub(crate) fn luminance(r: u8, g: u8, b: u8, model: Luminance) -> u8 {
+    let (r, g, b) = (r as usize, g as usize, b as usize);
+    match model {
+        Luminance::Fast => ((FAST_TABLE[0][r] + FAST_TABLE[1][g] + FAST_TABLE[2][b]) >> 16) as u8,
+        Luminance::Linear => {
+            let tables = linear_tables();
+            let [red, green, blue] = &tables.channels;
+            let linear = red[r] + green[g] + blue[b];
+            tables.bounds.partition_point(|&bound| bound <= linear) as u8
+        }
+    }
+}
+
+/// 把线性光强转换回 sRGB 编码的分量
+pub(crate) fn linear_to_srgb(value: f32) -> u8 {
+    let v = value.clamp(0.0, 1.0);
+    let encoded = if v <= 0.0031308 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    };
+    (encoded * 255.0).round() as u8
+}
+
+/// 解析颜色时的错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseColorError(String);
+
+impl fmt::Display for ParseColorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "无效的颜色: {}", self.0)
+    }
+}
+
+impl std::error::Error for ParseColorError {}
+
+impl FromStr for Color {
+    type Err = ParseColorError;
+
+    /// 支持 `#rrggbb`、`rrggbb` 和 `#rgb` 三种写法
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseColorError(s.to_string());
+        let hex = s.trim().trim_start_matches('#');
+        if !hex.is_ascii() {
+            return Err(err());
+        }
+        let channel = |range: std::ops::Range<usize>| u8::from_str_radix(&hex[range], 16);
+        match hex.len() {
+            6 => Ok(Color::rgb(
+                channel(0..2).map_err(|_| err())?,
+                channel(2..4).map_err(|_| err())?,
+                channel(4..6).map_err(|_| err())?,
+            )),
+            3 => Ok(Color::rgb(
+                channel(0..1).map_err(|_| err())? * 17,
+                channel(1..2).map_err(|_| err())? * 17,
+                channel(2..3).map_err(|_| err())? * 17,
+            )),
+            _ => Err(err()),
+        }
+    }
+}
+
+/// 序列化为 `#rrggbb`
+impl From<Color> for String {
+    fn from(color: Color) -> String {
+        color.to_string()
+    }
+}
+
+impl TryFrom<String> for Color {
+    type Error = ParseColorError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!("#ff8000".parse(), Ok(Color::rgb(255, 128, 0)));
+        assert_eq!("0a0B0c".parse(), Ok(Color::rgb(10, 11, 12)));
+        assert_eq!("#fff".parse(), Ok(Color::rgb(255, 255, 255)));
+        assert!("#12345".parse::<Color>().is_err());
+        assert!("zzzzzz".parse::<Color>().is_err());
+        assert_eq!(Color::rgb(1, 2, 255).to_string(), "#0102ff");
+    }
+
+    #[test]
+    fn test_ansi256() {
+        assert_eq!(Color::rgb(255, 0, 0).to_ansi256(), 196);
+        assert_eq!(Color::rgb(0, 0, 0).to_ansi256(), 16);
+        assert_eq!(Color::rgb(255, 255, 255).to_ansi256(), 231);
+        assert_eq!(Color::rgb(128, 128, 128).to_ansi256(), 244);
+        assert_eq!(Color::rgb(95, 135, 175).to_ansi256(), 67);
+        for index in 16..=255 {
+            assert_eq!(Color::from_ansi256(index).to_ansi256(), index);
+        }
+    }
+
+    #[test]
+    fn test_luminance() {
+        // 灰色的亮度就是它本身
+        for value in [0, 1, 64, 128, 200, 255] {
+            assert_eq!(luminance(value, value, value, Luminance::Linear), value);
+        }
+        // 在线性光强下计算，纯红色不再显得过暗
+        assert_eq!(luminance(255, 0, 0, Luminance::Fast), 54);
+        assert!(luminance(255, 0, 0, Luminance::Linear) > 120);
+
+        // 查表的结果与直接计算相差不超过 1
+        for (r, g, b) in [(255, 128, 0), (12, 200, 99), (3, 7, 250), (90, 90, 91)] {
+            let fast = 0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32;
+            assert!((luminance(r, g, b, Luminance::Fast) as f32 - fast).abs() <= 1.0);
+            let linear = [r, g, b].map(srgb_to_linear);
+            let linear =
+                linear_to_srgb(0.2126 * linear[0] + 0.7152 * linear[1] + 0.0722 * linear[2]);
+            assert!(luminance(r, g, b, Luminance::Linear).abs_diff(linear) <= 1);
+        }
+    }
+}
@@ -0,0 +1,358 @@
+use crate::alt_text::{describe_image, describe_message};
+use crate::bubble::{bubble_width, horizontal_tail, write_bubble};
+use crate::cells::{alpha_map, write_export, CellGrid};
+use crate::convert::{
+    art_height, art_width, convert_image_to_text, crop_image, graphics_source, is_export,
+    profile_frame, render_art, render_art_from, resize_image,
+};
+use crate::filters::preprocess;
+use crate::frame::write_enclosed;
+use crate::layout::{beside, Block};
+use crate::output::LineWriter;
+use crate::report::CountingWriter;
+use crate::text::{expand_template, longest_line, wrap_message};
+use crate::{
+    count_colors, orient_art, write_indented_bubble, write_tail, AltText, BubblePosition, Facing,
+    Frame, Layout, OutputBackend, OutputProfile, RenderOptions, RenderReport, BUFSIZE,
+};
+use image::DynamicImage;
+use smallvec::SmallVec;
+use std::borrow::Cow;
+use std::io::{Result, Write};
+
+/// 预先转换好的图片，可以配合不同的消息多次输出
+///
+/// 解码、缩放和转换只在创建时进行一次，之后每次输出只需要绘制消息框。适合每次调用
+/// 都显示同一张图片的场合，如 shell 提示符和聊天机器人。输出与
+/// [`crate::say_from_dynamic_image_with_options`] 相同。
+///
+/// # Example
+///
+/// ```rust
+/// use image::{DynamicImage, RgbaImage};
+/// use pixel_says::{CompiledSprite, RenderOptions};
+///
+/// let sprite = DynamicImage::ImageRgba8(RgbaImage::new(4, 4));
+/// let sprite = CompiledSprite::new(sprite, &RenderOptions::default()).unwrap();
+/// for message in ["Hello!", "Goodbye!"] {
+///     let mut output = Vec::new();
+///     sprite.say(message, &mut output).unwrap();
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct CompiledSprite {
+    options: RenderOptions,
+    /// 文字描述中与消息无关的部分
+    description: Option<String>,
+    /// 只输出描述时为 `None`
+    art: Option<CompiledArt>,
+}
+
+/// 转换好的图片
+#[derive(Debug, Clone)]
+struct CompiledArt {
+    text: Vec<u8>,
+    /// 水平翻转后的图片，只有图片需要朝向消息框时才会用到
+    flipped: Option<Vec<u8>>,
+    /// 导出时与 `text`、`flipped` 排版相同的不透明度对照文本，见 [`alpha_map`]
+    alpha: Option<(Vec<u8>, Option<Vec<u8>>)>,
+    source_width: u32,
+    width: u32,
+    report: RenderReport,
+}
+
+impl CompiledSprite {
+    /// 按照选项转换图片
+    pub fn new(img: DynamicImage, options: &RenderOptions) -> Result<Self> {
+        CompiledSprite::compile(Cow::Owned(img), None, options)
+    }
+
+    /// 与 [`CompiledSprite::new`] 相同，`name` 为文字描述中的图片名称
+    pub(crate) fn compile(
+        img: Cow<'_, DynamicImage>,
+        name: Option<&str>,
+        options: &RenderOptions,
+    ) -> Result<Self> {
+        let mut options = options.clone();
+        // 边框里和逐行拼接都只能用字符
+        if !is_export(&options)
+            && (options.layout == Layout::Enclosed
+                || matches!(
+                    options.position,
+                    BubblePosition::Left | BubblePosition::Right
+                ))
+        {
+            options.backend = OutputBackend::Text;
+        }
+        let img = crop_image(img, &options);
+        let description = (options.alt_text != AltText::Off).then(|| describe_image(name, &img));
+        let art = match options.alt_text {
+            AltText::Only => None,
+            _ => Some(CompiledArt::new(img, &options)?),
+        };
+        Ok(CompiledSprite {
+            options,
+            description,
+            art,
+        })
+    }
+
+    /// 绘制消息框并输出图片
+    pub fn say<W>(&self, message: &str, writer: W) -> Result<RenderReport>
+    where
+        W: Write,
+    {
+        if !is_export(&self.options) {
+            return self.say_text(message, writer);
+        }
+        let mut text = Vec::new();
+        let report = self.say_text(message, &mut text)?;
+        let grid = self.cells(message, &text)?;
+        write_export(&grid, &self.options, report, writer)
+    }
+
+    /// 渲染为字符格
+    pub(crate) fn say_cells(&self, message: &str) -> Result<CellGrid> {
+        let mut text = Vec::new();
+        self.say_text(message, &mut text)?;
+        self.cells(message, &text)
+    }
+
+    /// 把渲染好的文本转换为字符格，导出时图片字符格带有像素的不透明度
+    fn cells(&self, message: &str, text: &[u8]) -> Result<CellGrid> {
+        let mut grid = CellGrid::from_ansi(&String::from_utf8_lossy(text));
+        if let Some(
+            art @ CompiledArt {
+                alpha: Some((map, flipped)),
+                ..
+            },
+        ) = &self.art
+        {
+            // 对照文本与图片按同样的方式和消息框一起排版
+            let map_art = CompiledArt {
+                text: map.clone(),
+                flipped: flipped.clone(),
+                alpha: None,
+                ..art.clone()
+            };
+            let mut map = Vec::new();
+            self.write_bubble_and_art(&map_art, message, &mut map)?;
+            grid.apply_alpha(&CellGrid::from_ansi(&String::from_utf8_lossy(&map)));
+        }
+        Ok(grid)
+    }
+
+    /// 以终端文本的形式绘制消息框并输出图片
+    fn say_text<W>(&self, message: &str, writer: W) -> Result<RenderReport>
+    where
+        W: Write,
+    {
+        let mut counter = CountingWriter::new(writer);
+        let mut report = match &self.art {
+            Some(art) => self.write_bubble_and_art(art, message, &mut counter)?,
+            None => RenderReport::default(),
+        };
+        if let Some(description) = &self.description {
+            let alt = describe_message(description, &expand_template(message, &self.options));
+            LineWriter::new(&mut counter, &self.options).write_all(alt.as_bytes())?;
+        }
+        report.lines = counter.lines;
+        report.bytes_written = counter.bytes;
+        Ok(report)
+    }
+
+    /// 绘制消息框和图片
+    fn write_bubble_and_art<W>(
+        &self,
+        art: &CompiledArt,
+        message: &str,
+        writer: W,
+    ) -> Result<RenderReport>
+    where
+        W: Write,
+    {
+        let options = &self.options;
+        let mut writer = LineWriter::new(writer, options);
+        let mut write_buffer = SmallVec::<[u8; BUFSIZE]>::new();
+
+        // 预处理消息文本
+        let wrapped = wrap_message(message, options)?;
+        let lines: Vec<&str> = wrapped.lines().collect();
+        let actual_width = longest_line(&lines, options.ambiguous_width);
+        let bubble_columns = bubble_width(actual_width, options);
+        let mut report = RenderReport {
+            bubble_width: bubble_columns,
+            ..art.report
+        };
+
+        if options.layout == Layout::Enclosed {
+            let frame = profile_frame(options.frame.as_ref().unwrap_or(&Frame::default()), options);
+            report.bubble_width = write_enclosed(
+                &art.text,
+                art.report.art_width,
+                &lines,
+                options.ambiguous_width,
+                &frame,
+                &mut writer,
+            )?;
+            return Ok(report);
+        }
+
+        if matches!(
+            options.position,
+            BubblePosition::Left | BubblePosition::Right
+        ) {
+            // 图片和消息框左右并排绘制
+            write_bubble(&mut write_buffer, &lines, actual_width, options);
+            // 连接线指向消息内容的中间一行，顶部边框占一行
+            let tail_row = 1 + lines.len().saturating_sub(1) / 2;
+            let output = beside(
+                Block::from_text_with(&String::from_utf8_lossy(&art.text), options.ambiguous_width),
+                Block::from_text_with(
+                    &String::from_utf8_lossy(&write_buffer),
+                    options.ambiguous_width,
+                ),
+                tail_row,
+                horizontal_tail(options.bubble, options.position),
+                options.position == BubblePosition::Left,
+            );
+            writer.write_all(output.as_bytes())?;
+            return Ok(report);
+        }
+
+        let (flip, indent) = orient_art(art.source_width, art.width, bubble_columns, options);
+        let text = match &art.flipped {
+            Some(flipped) if flip => flipped,
+            _ => &art.text,
+        };
+        let margin = if options.center_bubble {
+            art.report.art_width.saturating_sub(bubble_columns) / 2
+        } else {
+            0
+        };
+        if options.position == BubblePosition::Below {
+            // 图片在上，连接线从图片向下指向消息框
+            writer.write_all(text)?;
+            write_tail(&mut write_buffer, indent, options.bubble, true);
+            write_indented_bubble(&mut write_buffer, &lines, actual_width, margin, options);
+            writer.write_all(&write_buffer)?;
+        } else {
+            write_indented_bubble(&mut write_buffer, &lines, actual_width, margin, options);
+            write_tail(&mut write_buffer, indent, options.bubble, false);
+            writer.write_all(&write_buffer)?;
+            writer.write_all(text)?;
+        }
+        Ok(report)
+    }
+}
+
+impl CompiledArt {
+    /// 缩放、预处理并转换图片
+    fn new(img: Cow<'_, DynamicImage>, options: &RenderOptions) -> Result<Self> {
+        let source_width = img.width();
+        let source = graphics_source(&img, options);
+        let mut img = preprocess(resize_image(img, options), options);
+        let mut text = Vec::new();
+        let mut flipped = None;
+        let mut alpha = None;
+        // 对照文本不是 ASCII，ASCII 输出时没有不透明度
+        let with_alpha = is_export(options) && options.profile != OutputProfile::Ascii;
+        // 边框在两侧和上下各占一格
+        let mut frame = if options.frame.is_some() { 2 } else { 0 };
+
+        if options.layout == Layout::Enclosed {
+            // 图片和说明文字共用的边框在输出时绘制
+            convert_image_to_text(&img, options, &mut text)?;
+            frame = 0;
+            if with_alpha {
+                alpha = Some((alpha_map(&text, &img, 0, options), None));
+            }
+        } else if matches!(
+            options.position,
+            BubblePosition::Left | BubblePosition::Right
+        ) {
+            // 消息框在哪一侧是固定的，只需要转换一次
+            let bubble_side = match options.position {
+                BubblePosition::Left => Facing::Left,
+                _ => Facing::Right,
+            };
+            if options
+                .face_bubble
+                .is_some_and(|facing| facing != bubble_side)
+            {
+                img = Cow::Owned(img.fliph());
+            }
+            render_art(&img, options, &mut text)?;
+            if with_alpha {
+                alpha = Some((alpha_map(&text, &img, frame / 2, options), None));
+            }
+        } else {
+            // 是否翻转取决于消息框的宽度，两种朝向都转换好
+            render_art_from(&img, source.as_ref().unwrap_or(&img), options, &mut text)?;
+            let mut flipped_map = None;
+            if options.face_bubble.is_some() {
+                let mut output = Vec::new();
+                let flipped_img = img.fliph();
+                let flipped_source = source.as_ref().map(DynamicImage::fliph);
+                let flipped_source = flipped_source.as_ref().unwrap_or(&flipped_img);
+                render_art_from(&flipped_img, flipped_source, options, &mut output)?;
+                if with_alpha {
+                    flipped_map = Some(alpha_map(&output, &flipped_img, frame / 2, options));
+                }
+                flipped = Some(output);
+            }
+            if with_alpha {
+                alpha = Some((alpha_map(&text, &img, frame / 2, options), flipped_map));
+            }
+        }
+
+        Ok(CompiledArt {
+            text,
+            flipped,
+            alpha,
+            source_width,
+            width: img.width(),
+            report: RenderReport {
+                art_width: art_width(img.width(), options) + frame,
+                art_height: art_height(img.height(), options) + frame,
+                colors_used: count_colors(&img),
+                ..RenderReport::default()
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{render_lines, PixelMode, TailAnchor};
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn test_compiled_sprite_matches_say() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(6, 3, |x, y| {
+            Rgba([x as u8 * 40, y as u8 * 80, 200, 255])
+        }));
+        let options = RenderOptions {
+            mode: PixelMode::Ansi256,
+            face_bubble: Some(Facing::Left),
+            tail: TailAnchor::BubbleCenter,
+            alt_text: AltText::Append,
+            ..RenderOptions::default()
+        };
+        let sprite = CompiledSprite::new(img.clone(), &options).unwrap();
+        // 消息框的宽度不同，图片的朝向也不同
+        let mut outputs = Vec::new();
+        for message in ["hi", "a much longer message than the image"] {
+            let lines = render_lines(img.clone(), message, &options).unwrap();
+            let expected: String = lines.map(|line| line.unwrap() + "\n").collect();
+            let mut output = Vec::new();
+            let report = sprite.say(message, &mut output).unwrap();
+            assert_eq!(String::from_utf8_lossy(&output), expected);
+            assert_eq!(report.art_width, 12);
+            outputs.push(expected);
+        }
+        let last_row = |output: &str| output.lines().nth_back(1).unwrap_or_default().to_string();
+        assert_ne!(last_row(&outputs[0]), last_row(&outputs[1]));
+    }
+}
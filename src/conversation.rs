@@ -0,0 +1,196 @@
+use crate::bubble::{bubble_width, tail_marks, write_bubble};
+use crate::convert::{art_width, render_art, resize_image};
+use crate::filters::preprocess;
+use crate::layout::{hstack, Block};
+use crate::output::LineWriter;
+use crate::report::CountingWriter;
+use crate::text::{longest_line, wrap_message};
+use crate::{
+    visible_colors, BubbleKind, Facing, OutputBackend, RenderOptions, RenderReport, BUFSIZE,
+};
+use image::DynamicImage;
+use smallvec::SmallVec;
+use std::borrow::Cow;
+use std::io::{Result, Write};
+
+/// 对话中说话的一方
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Speaker {
+    /// 左边的角色
+    Left,
+    /// 右边的角色
+    Right,
+}
+
+/// 两个角色面对面的对话
+///
+/// 两个角色分别位于左右两侧，消息框按顺序排列在中间，
+/// 左边角色的消息靠左、右边角色的消息靠右，连接线指向说话的一方。
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use pixel_says::{Conversation, RenderOptions, Speaker};
+/// use std::io::stdout;
+///
+/// let ferris = image::open("ferris.png").unwrap();
+/// let cat = image::open("cat.png").unwrap();
+/// Conversation::new(ferris, cat, RenderOptions::default())
+///     .render(&[(Speaker::Left, "Hi!"), (Speaker::Right, "Meow.")], stdout())
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Conversation {
+    left: DynamicImage,
+    right: DynamicImage,
+    options: RenderOptions,
+}
+
+impl Conversation {
+    /// 创建对话
+    ///
+    /// 如果设置了 `options.face_bubble`（原图中角色的朝向），两个角色会被翻转为面对面。
+    pub fn new(left: DynamicImage, right: DynamicImage, options: RenderOptions) -> Self {
+        Conversation {
+            left,
+            right,
+            options,
+        }
+    }
+
+    /// 按顺序渲染对话
+    ///
+    /// 返回的统计中图片的宽度为两个角色的宽度之和，高度为较高的一个角色，
+    /// 消息框的宽度为中间一列的宽度。
+    pub fn render<W>(&self, lines: &[(Speaker, &str)], writer: W) -> Result<RenderReport>
+    where
+        W: Write,
+    {
+        let mut counter = CountingWriter::new(writer);
+        let mut writer = LineWriter::new(&mut counter, &self.options);
+
+        let (left, left_img) = self.render_mascot(&self.left, Facing::Right)?;
+        let (right, right_img) = self.render_mascot(&self.right, Facing::Left)?;
+        let mut colors = visible_colors(&left_img);
+        colors.extend(visible_colors(&right_img));
+        let mut report = RenderReport {
+            art_width: left.width + right.width,
+            art_height: left.lines.len().max(right.lines.len()),
+            colors_used: colors.len(),
+            ..RenderReport::default()
+        };
+
+        // 中间一列：依次排列的消息框
+        let mut bubbles = Block::from_text_with("", self.options.ambiguous_width);
+        let mut rendered = Vec::new();
+        for &(speaker, message) in lines {
+            let wrapped = wrap_message(message, &self.options)?;
+            let message_lines: Vec<&str> = wrapped.lines().collect();
+            let width = longest_line(&message_lines, self.options.ambiguous_width);
+
+            let mut buffer = SmallVec::<[u8; BUFSIZE]>::new();
+            write_bubble(&mut buffer, &message_lines, width, &self.options);
+            buffer.push(b'\n');
+            // 连接线从消息框下方斜向说话的角色
+            match (speaker, self.options.bubble) {
+                (Speaker::Left, BubbleKind::Say) => buffer.extend_from_slice(b" /\n"),
+                (Speaker::Left, BubbleKind::Think) => buffer.extend_from_slice(b" o\n"),
+                (Speaker::Right, kind) => {
+                    let indent = bubble_width(width, &self.options) - 2;
+                    buffer.extend(std::iter::repeat_n(b' ', indent));
+                    buffer.extend_from_slice(tail_marks(kind)[1]);
+                    buffer.push(b'\n');
+                }
+            }
+            let block = Block::from_text_with(
+                &String::from_utf8_lossy(&buffer),
+                self.options.ambiguous_width,
+            );
+            bubbles.width = bubbles.width.max(block.width);
+            rendered.push((speaker, block));
+        }
+        for (speaker, mut block) in rendered {
+            // 右边角色的消息框靠右对齐
+            if speaker == Speaker::Right {
+                let shift = bubbles.width - block.width;
+                for line in &mut block.lines {
+                    line.insert_str(0, &" ".repeat(shift));
+                }
+                block.width = bubbles.width;
+            }
+            bubbles.push_below(block);
+        }
+        report.bubble_width = bubbles.width;
+
+        writer.write_all(hstack(&[left, bubbles, right], 1).as_bytes())?;
+        report.lines = counter.lines;
+        report.bytes_written = counter.bytes;
+        Ok(report)
+    }
+
+    /// 渲染一个角色，必要时翻转为朝向 `facing`，同时返回缩放后的图片
+    fn render_mascot<'a>(
+        &self,
+        img: &'a DynamicImage,
+        facing: Facing,
+    ) -> Result<(Block, Cow<'a, DynamicImage>)> {
+        let mut resized = preprocess(
+            resize_image(Cow::Borrowed(img), &self.options),
+            &self.options,
+        );
+        if self
+            .options
+            .face_bubble
+            .is_some_and(|source| source != facing)
+        {
+            resized = Cow::Owned(resized.fliph());
+        }
+        // 角色与消息框并排排列，只能使用字符
+        let options = RenderOptions {
+            backend: OutputBackend::Text,
+            ..self.options.clone()
+        };
+        let mut art = Vec::new();
+        render_art(&resized, &options, &mut art)?;
+        let mut block = Block::from_text(&String::from_utf8_lossy(&art));
+        block.width = block.width.max(art_width(resized.width(), &self.options));
+        Ok((block, resized))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PixelMode;
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn test_conversation_layout() {
+        let mascot = DynamicImage::ImageRgba8(RgbaImage::from_pixel(2, 2, Rgba([255; 4])));
+        let options = RenderOptions {
+            mode: PixelMode::Monochrome,
+            ..RenderOptions::default()
+        };
+        let conversation = Conversation::new(mascot.clone(), mascot, options);
+        let mut output = Vec::new();
+        let report = conversation
+            .render(
+                &[(Speaker::Left, "hi"), (Speaker::Right, "hey")],
+                &mut output,
+            )
+            .unwrap();
+
+        let expected = "      ____
+     < hi >
+      ----
+      /
+      _____
+     < hey >
+████  -----  ████
+████      \\  ████
+";
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
+        assert_eq!((report.art_width, report.art_height), (8, 2));
+        assert_eq!((report.bubble_width, report.lines), (7, 8));
+    }
+}
@@ -0,0 +1,1243 @@
+use crate::ans::nearest_basic;
+use crate::color::luminance;
+use crate::custom::convert_to_custom;
+use crate::filters::{is_visible, preprocess, resolve_threshold, shade_table};
+use crate::frame::write_framed;
+use crate::iterm::write_iterm2;
+use crate::sixel::write_sixel;
+use crate::{
+    AspectCorrection, AspectFit, Color, ColorPalette, Frame, FrameStyle, OutputBackend,
+    OutputProfile, PixelMode, RenderOptions, DEFAULT_RAMP,
+};
+use image::{DynamicImage, GenericImageView};
+use std::borrow::Cow;
+use std::io::{Result, Write};
+
+/// 将图片转换为终端文本
+pub(crate) fn convert_image_to_text<W>(
+    img: &DynamicImage,
+    options: &RenderOptions,
+    mut writer: W,
+) -> Result<()>
+where
+    W: Write,
+{
+    match options.background {
+        Some(background) if options.profile.allows_escapes() => {
+            let mut buffer = Vec::new();
+            convert_pixels(img, options, &mut buffer)?;
+            write_with_background(&buffer, background, &mut writer)
+        }
+        _ => convert_pixels(img, options, writer),
+    }
+}
+
+/// 转换图片，并在设置了边框时加上边框
+pub(crate) fn render_art<W>(img: &DynamicImage, options: &RenderOptions, writer: W) -> Result<()>
+where
+    W: Write,
+{
+    render_art_from(img, img, options, writer)
+}
+
+/// 与 [`render_art`] 相同，但 iTerm2 协议发送的是没有缩放的 `source`（见 [`graphics_source`]），
+/// `img` 只决定图片占用的字符格数
+pub(crate) fn render_art_from<W>(
+    img: &DynamicImage,
+    source: &DynamicImage,
+    options: &RenderOptions,
+    mut writer: W,
+) -> Result<()>
+where
+    W: Write,
+{
+    if options.profile.allows_escapes() {
+        match options.backend {
+            OutputBackend::Text
+            | OutputBackend::Html
+            | OutputBackend::Svg
+            | OutputBackend::Png
+            | OutputBackend::Json
+            | OutputBackend::Ans => {}
+            OutputBackend::Sixel => return write_sixel(img, options, writer),
+            OutputBackend::Iterm2 => return write_iterm2(img, source, options, writer),
+        }
+    }
+    let Some(frame) = &options.frame else {
+        return convert_image_to_text(img, options, writer);
+    };
+
+    let mut art = Vec::new();
+    convert_image_to_text(img, options, &mut art)?;
+    let width = art_width(img.width(), options);
+    write_framed(&art, width, &profile_frame(frame, options), &mut writer)
+}
+
+/// 是否把整个输出导出为 HTML、SVG、PNG、JSON 或 `.ans` 文件
+pub(crate) fn is_export(options: &RenderOptions) -> bool {
+    matches!(
+        options.backend,
+        OutputBackend::Html
+            | OutputBackend::Svg
+            | OutputBackend::Png
+            | OutputBackend::Json
+            | OutputBackend::Ans
+    )
+}
+
+/// iTerm2 协议发送的原图：终端自己缩放图片，因此裁剪好的图片只做预处理、不缩放，
+/// 以免丢失细节；其他输出不需要原图，返回 `None`
+pub(crate) fn graphics_source(img: &DynamicImage, options: &RenderOptions) -> Option<DynamicImage> {
+    (is_graphics(options) && options.backend == OutputBackend::Iterm2)
+        .then(|| preprocess(Cow::Borrowed(img), options).into_owned())
+}
+
+/// 是否以图形而不是字符输出（ASCII 和纯文本输出时仍然使用字符）
+pub(crate) fn is_graphics(options: &RenderOptions) -> bool {
+    matches!(
+        options.backend,
+        OutputBackend::Sixel | OutputBackend::Iterm2
+    ) && options.profile.allows_escapes()
+}
+
+/// 实际使用的像素模式：`Auto` 替换为检测到的模式，纯文本输出时彩色的模式改用黑白模式
+pub(crate) fn resolve_mode(options: &RenderOptions) -> PixelMode {
+    match options.mode.resolve() {
+        PixelMode::TrueColor
+        | PixelMode::Ansi256
+        | PixelMode::Ansi16
+        | PixelMode::Grayscale
+        | PixelMode::Quadrant
+        | PixelMode::Custom(_)
+            if options.profile == OutputProfile::Plain =>
+        {
+            PixelMode::Monochrome
+        }
+        mode => mode,
+    }
+}
+
+/// 一个字符表示多个像素的模式中，每个字符覆盖的像素宽度和高度（ASCII 输出时不适用）
+fn pixels_per_cell(options: &RenderOptions) -> Option<(usize, usize)> {
+    // 图形输出的大小与 `PixelWidth` 对应的字符输出相同
+    if options.profile == OutputProfile::Ascii || is_graphics(options) {
+        return None;
+    }
+    match resolve_mode(options) {
+        PixelMode::Braille => Some((BRAILLE_WIDTH, BRAILLE_HEIGHT)),
+        PixelMode::Quadrant => Some((2, 2)),
+        _ => None,
+    }
+}
+
+/// 图片转换后占用的终端列数
+pub(crate) fn art_width(width: u32, options: &RenderOptions) -> usize {
+    match pixels_per_cell(options) {
+        Some((cell_width, _)) => (width as usize).div_ceil(cell_width),
+        None => width as usize * options.pixel_width.columns(),
+    }
+}
+
+/// `columns` 列终端最多能容纳的像素列数
+pub(crate) fn pixels_in_columns(columns: usize, options: &RenderOptions) -> usize {
+    match pixels_per_cell(options) {
+        Some((cell_width, _)) => columns * cell_width,
+        None => columns / options.pixel_width.columns().max(1),
+    }
+}
+
+/// 图片转换后占用的行数
+pub(crate) fn art_height(height: u32, options: &RenderOptions) -> usize {
+    match pixels_per_cell(options) {
+        Some((_, cell_height)) => (height as usize).div_ceil(cell_height),
+        None => height as usize,
+    }
+}
+
+/// 每行字符覆盖的像素行数
+pub(crate) fn pixel_rows_per_line(options: &RenderOptions) -> u32 {
+    pixels_per_cell(options).map_or(1, |(_, cell_height)| cell_height as u32)
+}
+
+/// 第 `x` 个像素所在的终端列
+pub(crate) fn pixel_column(x: usize, options: &RenderOptions) -> usize {
+    match pixels_per_cell(options) {
+        Some((cell_width, _)) => x / cell_width,
+        None => x * options.pixel_width.columns(),
+    }
+}
+
+/// ASCII 输出时边框只能使用 ASCII 字符
+pub(crate) fn profile_frame(frame: &Frame, options: &RenderOptions) -> Frame {
+    if options.profile == OutputProfile::Ascii {
+        Frame {
+            style: FrameStyle::Ascii,
+            ..frame.clone()
+        }
+    } else {
+        frame.clone()
+    }
+}
+
+/// 在每一行（包括透明像素）下面铺上背景色
+///
+/// 转换函数在每个像素之后都会重置样式，这里在重置之后重新设置背景色。
+fn write_with_background<W>(text: &[u8], background: Color, mut writer: W) -> Result<()>
+where
+    W: Write,
+{
+    let bg = background.bg_escape();
+    let reset_with_bg = format!("{}{}", RESET, bg);
+    for line in String::from_utf8_lossy(text).lines() {
+        writeln!(writer, "{}{}{}", bg, line.replace(RESET, &reset_with_bg), RESET)?;
+    }
+    Ok(())
+}
+
+/// 重置所有样式
+const RESET: &str = "\x1b[0m";
+
+/// 按照像素模式转换图片
+///
+/// 先转换到缓冲区再一次写出，在没有缓冲的输出（如网络连接）上也不会逐个像素地写入
+fn convert_pixels<W>(img: &DynamicImage, options: &RenderOptions, mut writer: W) -> Result<()>
+where
+    W: Write,
+{
+    // 大致的输出大小：每个像素占一两列，彩色模式还有转义序列
+    let mut buffer = Vec::with_capacity(img.width() as usize * img.height() as usize * 8);
+    write_pixels(img, options, &mut buffer)?;
+    writer.write_all(&buffer)
+}
+
+/// 按照像素模式把图片转换到缓冲区
+fn write_pixels(img: &DynamicImage, options: &RenderOptions, writer: &mut Vec<u8>) -> Result<()> {
+    if options.profile == OutputProfile::Ascii {
+        // 自定义的字符中含有非 ASCII 字符时改用默认的字符
+        let ramp = match options.ramp.is_ascii() {
+            true => options.ramp.as_str(),
+            false => DEFAULT_RAMP,
+        };
+        return convert_to_ramp(img, ramp, options, writer);
+    }
+
+    match resolve_mode(options) {
+        PixelMode::TrueColor => convert_to_truecolor(img, options, writer),
+        PixelMode::Ansi256 => convert_to_ansi256(img, options, writer),
+        PixelMode::Ansi16 => convert_to_ansi16(img, options, writer),
+        PixelMode::Grayscale => convert_to_truecolor(&grayscale(img, options), options, writer),
+        PixelMode::Monochrome => convert_to_monochrome(img, options, writer),
+        PixelMode::Invert => convert_to_invert(img, options, writer),
+        PixelMode::Shade => convert_to_shade(img, options, writer),
+        PixelMode::Braille => convert_to_braille(img, options, writer),
+        PixelMode::Quadrant => convert_to_quadrant(img, options.palette.as_ref(), writer),
+        PixelMode::Ascii => convert_to_ramp(img, &options.ramp, options, writer),
+        PixelMode::Custom(renderer) => convert_to_custom(img, &renderer, writer),
+        // `resolve` 不会返回 `Auto`
+        PixelMode::Auto => convert_to_truecolor(img, options, writer),
+    }
+}
+
+/// 按照 `options.crop` 裁剪原图
+pub(crate) fn crop_image<'a>(
+    img: Cow<'a, DynamicImage>,
+    options: &RenderOptions,
+) -> Cow<'a, DynamicImage> {
+    let Some(rect) = options.crop else {
+        return img;
+    };
+    let (width, height) = img.dimensions();
+    let x = rect.x.min(width.saturating_sub(1));
+    let y = rect.y.min(height.saturating_sub(1));
+    let crop_width = rect.width.clamp(1, (width - x).max(1));
+    let crop_height = rect.height.clamp(1, (height - y).max(1));
+    Cow::Owned(img.crop_imm(x, y, crop_width, crop_height))
+}
+
+/// 缩放图片以适应终端显示，大小合适时不会复制
+pub(crate) fn resize_image<'a>(
+    img: Cow<'a, DynamicImage>,
+    options: &RenderOptions,
+) -> Cow<'a, DynamicImage> {
+    let (width, height) = img.dimensions();
+    // 修正字符格的宽高比
+    let factor = aspect_factor(options);
+    let corrected_height = if factor == 1.0 {
+        height
+    } else {
+        (height as f32 * factor).round() as u32
+    };
+
+    // 限制图片大小，避免输出过大
+    let (new_width, new_height) = match options.max_cells {
+        Some((max_width, max_height)) if width > max_width || corrected_height > max_height => {
+            let ratio =
+                (max_width as f32 / width as f32).min(max_height as f32 / corrected_height as f32);
+            ((width as f32 * ratio) as u32, (corrected_height as f32 * ratio) as u32)
+        }
+        _ => (width, corrected_height),
+    };
+    // 极端宽高比（例如 1x2000）时某一边会被算成 0，至少保留 1 个像素
+    let (new_width, new_height) = (new_width.max(1), new_height.max(1));
+
+    if (new_width, new_height) == (width, height) {
+        img
+    } else {
+        let img = match options.aspect_fit {
+            AspectFit::Squash => img,
+            // 按修正之前的宽高比填充
+            AspectFit::Pad => {
+                let unscaled_height = ((new_height as f32 / factor).round() as u32).max(1);
+                Cow::Owned(pad_to_aspect(&img, new_width, unscaled_height))
+            }
+        };
+        Cow::Owned(img.resize_exact(new_width, new_height, image::imageops::FilterType::Nearest))
+    }
+}
+
+/// 缩放时图片高度乘以的系数
+fn aspect_factor(options: &RenderOptions) -> f32 {
+    match options.aspect_correction {
+        AspectCorrection::Off => 1.0,
+        AspectCorrection::Factor(factor) if factor.is_finite() && factor > 0.0 => factor,
+        AspectCorrection::Factor(_) => 1.0,
+        AspectCorrection::Auto => {
+            // 一个像素占用的字符格宽度和高度
+            let (columns, rows) = match pixels_per_cell(options) {
+                Some((cell_width, cell_height)) => {
+                    (1.0 / cell_width as f32, 1.0 / cell_height as f32)
+                }
+                None => (options.pixel_width.columns() as f32, 1.0),
+            };
+            columns * CELL_ASPECT / rows
+        }
+    }
+}
+
+/// 字符格的宽度与高度之比
+const CELL_ASPECT: f32 = 0.5;
+
+/// 用透明像素把图片居中填充到与目标尺寸相同的宽高比
+fn pad_to_aspect(img: &DynamicImage, target_width: u32, target_height: u32) -> DynamicImage {
+    let (width, height) = img.dimensions();
+    let (target_width, target_height) = (target_width as u64, target_height as u64);
+    let canvas_width = (width as u64).max((height as u64 * target_width).div_ceil(target_height));
+    let canvas_height = (height as u64).max((width as u64 * target_height).div_ceil(target_width));
+
+    let mut canvas = image::RgbaImage::new(canvas_width as u32, canvas_height as u32);
+    image::imageops::overlay(
+        &mut canvas,
+        &img.to_rgba8(),
+        ((canvas_width - width as u64) / 2) as i64,
+        ((canvas_height - height as u64) / 2) as i64,
+    );
+    DynamicImage::ImageRgba8(canvas)
+}
+
+/// 转换为真彩色输出
+fn convert_to_truecolor(
+    img: &DynamicImage,
+    options: &RenderOptions,
+    writer: &mut Vec<u8>,
+) -> Result<()> {
+    let (width, height) = img.dimensions();
+    let (block, blank) = options.pixel_width.cells();
+
+    for y in 0..height {
+        // 当前生效的颜色，相同颜色的连续像素只输出一次转义序列
+        let mut current = None;
+        for x in 0..width {
+            let pixel = img.get_pixel(x, y);
+            let image::Rgba([r, g, b, _]) = pixel;
+            // 如果像素是透明的，输出空格
+            if !is_visible(&pixel) {
+                if current.take().is_some() {
+                    write!(writer, "{}", RESET)?;
+                }
+                write!(writer, "{}", blank)?;
+                continue;
+            }
+            if current != Some((r, g, b)) {
+                current = Some((r, g, b));
+                let color = Color::rgb(r, g, b);
+                if options.background_cells {
+                    // 背景色 - 用空格填充整个字符格
+                    write!(writer, "{}", color.bg_escape())?;
+                } else {
+                    // 使用 ANSI 真彩色转义序列 - 前景色
+                    write!(writer, "{}", color.fg_escape())?;
+                }
+            }
+            let cell = if options.background_cells { blank } else { block };
+            write!(writer, "{}", cell)?;
+        }
+        // 每行末尾重置一次
+        if current.is_some() {
+            write!(writer, "{}", RESET)?;
+        }
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+/// 把每个像素换成亮度相同的灰色，透明度不变
+fn grayscale(img: &DynamicImage, options: &RenderOptions) -> DynamicImage {
+    let mut img = img.to_rgba8();
+    for pixel in img.pixels_mut() {
+        let level = luminance(pixel[0], pixel[1], pixel[2], options.luminance);
+        pixel.0[..3].fill(level);
+    }
+    DynamicImage::ImageRgba8(img)
+}
+
+/// 转换为 256 色输出
+fn convert_to_ansi256(
+    img: &DynamicImage,
+    options: &RenderOptions,
+    writer: &mut Vec<u8>,
+) -> Result<()> {
+    let (width, height) = img.dimensions();
+    let (block, blank) = options.pixel_width.cells();
+
+    for y in 0..height {
+        for x in 0..width {
+            let pixel = img.get_pixel(x, y);
+            let image::Rgba([r, g, b, _]) = pixel;
+            // 如果像素是透明的，输出空格
+            if !is_visible(&pixel) {
+                write!(writer, "{}", blank)?;
+            } else {
+                let index = Color::rgb(r, g, b).to_ansi256();
+                if options.background_cells {
+                    write!(writer, "\x1b[48;5;{}m{}\x1b[0m", index, blank)?;
+                } else {
+                    write!(writer, "\x1b[38;5;{}m{}\x1b[0m", index, block)?;
+                }
+            }
+        }
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+/// 转换为 16 色输出
+fn convert_to_ansi16(
+    img: &DynamicImage,
+    options: &RenderOptions,
+    writer: &mut Vec<u8>,
+) -> Result<()> {
+    let (width, height) = img.dimensions();
+    let (block, blank) = options.pixel_width.cells();
+
+    for y in 0..height {
+        for x in 0..width {
+            let pixel = img.get_pixel(x, y);
+            let image::Rgba([r, g, b, _]) = pixel;
+            // 如果像素是透明的，输出空格
+            if !is_visible(&pixel) {
+                write!(writer, "{}", blank)?;
+            } else {
+                // 前 8 种颜色为 30–37，亮色为 90–97，背景色各加 10
+                let index = nearest_basic(Color::rgb(r, g, b), true);
+                let code = if index < 8 { 30 + index } else { 82 + index };
+                if options.background_cells {
+                    write!(writer, "\x1b[{}m{}\x1b[0m", code + 10, blank)?;
+                } else {
+                    write!(writer, "\x1b[{}m{}\x1b[0m", code, block)?;
+                }
+            }
+        }
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+/// 转换为黑白模式输出
+fn convert_to_monochrome(
+    img: &DynamicImage,
+    options: &RenderOptions,
+    writer: &mut Vec<u8>,
+) -> Result<()> {
+    let (width, height) = img.dimensions();
+    let (block, blank) = options.pixel_width.cells();
+    let threshold = resolve_threshold(img, options.threshold, options.luminance);
+    
+    for y in 0..height {
+        for x in 0..width {
+            let pixel = img.get_pixel(x, y);
+            let char = match pixel {
+                image::Rgba([r, g, b, _]) => {
+                    // 如果像素是透明的，输出空格
+                    if !is_visible(&pixel) {
+                        blank
+                    } else {
+                        let luminance = luminance(r, g, b, options.luminance);
+                        // 根据亮度选择字符
+                        if luminance > threshold { block } else { blank }
+                    }
+                },
+            };
+            write!(writer, "{}", char)?;
+        }
+        writeln!(writer)?;
+    }
+    
+    Ok(())
+}
+
+/// 转换为反色模式输出
+fn convert_to_invert(
+    img: &DynamicImage,
+    options: &RenderOptions,
+    writer: &mut Vec<u8>,
+) -> Result<()> {
+    let (width, height) = img.dimensions();
+    let (block, blank) = options.pixel_width.cells();
+    let threshold = resolve_threshold(img, options.threshold, options.luminance);
+    
+    for y in 0..height {
+        for x in 0..width {
+            let pixel = img.get_pixel(x, y);
+            let char = match pixel {
+                image::Rgba([r, g, b, _]) => {
+                    // 如果像素是透明的，输出空格
+                    if !is_visible(&pixel) {
+                        blank
+                    } else {
+                        let luminance = luminance(r, g, b, options.luminance);
+                        // 反色：根据亮度选择字符，与monochrome相反
+                        if luminance > threshold { blank } else { block }
+                    }
+                },
+            };
+            write!(writer, "{}", char)?;
+        }
+        writeln!(writer)?;
+    }
+    
+    Ok(())
+}
+
+/// 由暗到亮的明暗字符
+const SHADES: [char; 5] = [' ', '░', '▒', '▓', '█'];
+
+/// 转换为明暗字符输出
+fn convert_to_shade(
+    img: &DynamicImage,
+    options: &RenderOptions,
+    writer: &mut Vec<u8>,
+) -> Result<()> {
+    let (width, height) = img.dimensions();
+    let columns = options.pixel_width.columns();
+    let levels = shade_table(img, options.shade_levels, options.luminance);
+
+    for y in 0..height {
+        for x in 0..width {
+            let pixel = img.get_pixel(x, y);
+            let image::Rgba([r, g, b, _]) = pixel;
+            // 如果像素是透明的，输出空格
+            let shade = if !is_visible(&pixel) {
+                SHADES[0]
+            } else {
+                SHADES[levels[luminance(r, g, b, options.luminance) as usize] as usize]
+            };
+            for _ in 0..columns {
+                write!(writer, "{}", shade)?;
+            }
+        }
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+/// 按左上、右上、左下、右下的位（1、2、4、8）索引的象限字符
+const QUADRANTS: [char; 16] = [
+    ' ', '▘', '▝', '▀', '▖', '▌', '▞', '▛', '▗', '▚', '▐', '▜', '▄', '▙', '▟', '█',
+];
+
+/// 象限字符中亮起的部分，与 [`QUADRANTS`] 的位相同
+pub(crate) fn quadrant_mask(c: char) -> Option<u8> {
+    QUADRANTS.iter().position(|&q| q == c).map(|mask| mask as u8)
+}
+
+/// 转换为象限字符输出
+///
+/// 每 2x2 个像素合成一个字符，用前景色和背景色各表示一种颜色：在所有把像素分为两组的
+/// 方式中，选择用两组的平均色代替后误差最小的一种。有透明像素时不输出背景色，
+/// 不透明的像素统一使用它们的平均色。每个字符固定占一列，不受 `pixel_width` 影响。
+///
+/// 平均色通常不在调色板中，设置了 `palette` 时再换成调色板中最接近的颜色。
+fn convert_to_quadrant(
+    img: &DynamicImage,
+    palette: Option<&ColorPalette>,
+    writer: &mut Vec<u8>,
+) -> Result<()> {
+    let (width, height) = img.dimensions();
+
+    for top in (0..height).step_by(2) {
+        for left in (0..width).step_by(2) {
+            // 超出图片的部分按透明处理
+            let pixels: [Option<[u8; 3]>; 4] = [(0, 0), (1, 0), (0, 1), (1, 1)].map(|(dx, dy)| {
+                let (x, y) = (left + dx, top + dy);
+                if x >= width || y >= height {
+                    return None;
+                }
+                let pixel = img.get_pixel(x, y);
+                let image::Rgba([r, g, b, _]) = pixel;
+                is_visible(&pixel).then_some([r, g, b])
+            });
+            let visible = (0..4)
+                .filter(|&i| pixels[i].is_some())
+                .fold(0, |mask, i| mask | 1 << i);
+
+            let (mask, fg, bg) = if visible == 0 {
+                (0, None, None)
+            } else if visible != 0b1111 {
+                (visible, Some(average(&pixels, visible)), None)
+            } else {
+                best_split(&pixels)
+            };
+            let snap = |color: Color| palette.map_or(color, |palette| palette.nearest(color));
+            let (fg, bg) = (fg.map(snap), bg.map(snap));
+            match (fg, bg) {
+                (Some(fg), Some(bg)) => write!(
+                    writer,
+                    "{}{}{}\x1b[0m",
+                    fg.fg_escape(),
+                    bg.bg_escape(),
+                    QUADRANTS[mask as usize]
+                )?,
+                (Some(fg), None) => write!(
+                    writer,
+                    "{}{}\x1b[0m",
+                    fg.fg_escape(),
+                    QUADRANTS[mask as usize]
+                )?,
+                _ => write!(writer, " ")?,
+            }
+        }
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+/// `mask` 选中的像素的平均色
+fn average(pixels: &[Option<[u8; 3]>; 4], mask: u8) -> Color {
+    let selected: Vec<[u8; 3]> = (0..4)
+        .filter(|&i| mask & 1 << i != 0)
+        .filter_map(|i| pixels[i])
+        .collect();
+    let count = selected.len().max(1) as u32;
+    let channel = |c: usize| (selected.iter().map(|p| p[c] as u32).sum::<u32>() / count) as u8;
+    Color::rgb(channel(0), channel(1), channel(2))
+}
+
+/// 为四个不透明像素选择误差最小的前景（`mask` 中的像素）和背景
+fn best_split(pixels: &[Option<[u8; 3]>; 4]) -> (u8, Option<Color>, Option<Color>) {
+    let error = |mask: u8, color: Color| -> u32 {
+        (0..4)
+            .filter(|&i| mask & 1 << i != 0)
+            .filter_map(|i| pixels[i])
+            .map(|[r, g, b]| {
+                let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+                d(r, color.r) + d(g, color.g) + d(b, color.b)
+            })
+            .sum()
+    };
+
+    // 只枚举包含左上像素的分组，另一半由背景色表示；0b1111 表示只用一种颜色
+    let (mut best_mask, mut best_error) = (0b1111, error(0b1111, average(pixels, 0b1111)));
+    for mask in (1..0b1111u8).step_by(2) {
+        let (fg, bg) = (average(pixels, mask), average(pixels, !mask & 0b1111));
+        let total = error(mask, fg) + error(!mask & 0b1111, bg);
+        if total < best_error {
+            (best_mask, best_error) = (mask, total);
+        }
+    }
+    let fg = average(pixels, best_mask);
+    if best_mask == 0b1111 {
+        (best_mask, Some(fg), None)
+    } else {
+        (best_mask, Some(fg), Some(average(pixels, !best_mask & 0b1111)))
+    }
+}
+
+/// 每个盲文字符覆盖的像素宽度和高度
+const BRAILLE_WIDTH: usize = 2;
+const BRAILLE_HEIGHT: usize = 4;
+
+/// 盲文字符中 `(x, y)` 处的点对应的位，见 Unicode 盲文区（U+2800–U+28FF）
+const BRAILLE_DOTS: [[u32; BRAILLE_WIDTH]; BRAILLE_HEIGHT] =
+    [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+/// 转换为盲文点阵输出
+///
+/// 每 2x4 个像素合成一个盲文字符，亮的不透明像素对应凸起的点，
+/// 没有任何点的格子输出空格。每个字符固定占一列，不受 `pixel_width` 影响。
+fn convert_to_braille(
+    img: &DynamicImage,
+    options: &RenderOptions,
+    writer: &mut Vec<u8>,
+) -> Result<()> {
+    let (width, height) = img.dimensions();
+    let threshold = resolve_threshold(img, options.threshold, options.luminance);
+
+    for top in (0..height).step_by(BRAILLE_HEIGHT) {
+        for left in (0..width).step_by(BRAILLE_WIDTH) {
+            let mut bits = 0;
+            for (dy, row) in BRAILLE_DOTS.iter().enumerate() {
+                for (dx, bit) in row.iter().enumerate() {
+                    let (x, y) = (left + dx as u32, top + dy as u32);
+                    if x >= width || y >= height {
+                        continue;
+                    }
+                    let pixel = img.get_pixel(x, y);
+                    let image::Rgba([r, g, b, _]) = pixel;
+                    // 与黑白模式相同的阈值
+                    if is_visible(&pixel) && luminance(r, g, b, options.luminance) > threshold {
+                        bits |= bit;
+                    }
+                }
+            }
+            let c = match bits {
+                0 => ' ',
+                _ => char::from_u32(0x2800 + bits).unwrap_or(' '),
+            };
+            write!(writer, "{}", c)?;
+        }
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+/// 按亮度把像素映射为 `ramp` 中的字符
+fn convert_to_ramp(
+    img: &DynamicImage,
+    ramp: &str,
+    options: &RenderOptions,
+    writer: &mut Vec<u8>,
+) -> Result<()> {
+    let (width, height) = img.dimensions();
+    let columns = options.pixel_width.columns();
+    let ramp: Vec<char> = ramp.chars().collect();
+
+    for y in 0..height {
+        for x in 0..width {
+            let pixel = img.get_pixel(x, y);
+            let image::Rgba([r, g, b, _]) = pixel;
+            // 如果像素是透明的，输出空格
+            let c = if !is_visible(&pixel) || ramp.is_empty() {
+                ' '
+            } else {
+                ramp[luminance(r, g, b, options.luminance) as usize * ramp.len() / 256]
+            };
+            for _ in 0..columns {
+                write!(writer, "{}", c)?;
+            }
+        }
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filters::preprocess;
+    use crate::{Luminance, PixelWidth, Rect, Threshold, DEFAULT_MAX_CELLS};
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn test_transparent_pixels_in_monochrome() {
+        // 创建一个 2x2 的测试图片，包含透明和不透明像素
+        let mut img = RgbaImage::new(2, 2);
+        img.put_pixel(0, 0, Rgba([255, 255, 255, 255])); // 白色，不透明
+        img.put_pixel(1, 0, Rgba([0, 0, 0, 255]));       // 黑色，不透明
+        img.put_pixel(0, 1, Rgba([255, 255, 255, 0]));   // 白色，透明
+        img.put_pixel(1, 1, Rgba([0, 0, 0, 0]));         // 黑色，透明
+        
+        let dynamic_img = DynamicImage::ImageRgba8(img);
+        let mut output = Vec::new();
+        
+        convert_to_monochrome(&dynamic_img, &RenderOptions::default(), &mut output).unwrap();
+        let result = String::from_utf8(output).unwrap();
+        
+        // 期望：第一行是"██  "（白色块+黑色空格），第二行是"    "（两个透明像素都是空格）
+        assert_eq!(result, "██  \n    \n");
+    }
+
+    #[test]
+    fn test_monochrome_threshold() {
+        let img = RgbaImage::from_fn(3, 1, |x, _| {
+            let value = [20, 60, 100][x as usize];
+            Rgba([value, value, value, 255])
+        });
+        let img = DynamicImage::ImageRgba8(img);
+        let convert = |threshold| {
+            let options = RenderOptions {
+                threshold,
+                ..RenderOptions::default()
+            };
+            let mut output = Vec::new();
+            convert_to_monochrome(&img, &options, &mut output).unwrap();
+            String::from_utf8(output).unwrap()
+        };
+
+        // 默认的阈值下整张暗色图片都是空白
+        assert_eq!(convert(Threshold::default()), "      \n");
+        assert_eq!(convert(Threshold::Absolute(50)), "  ████\n");
+        assert_eq!(convert(Threshold::Percentile(34)), "    ██\n");
+    }
+
+    #[test]
+    fn test_grayscale() {
+        let mut img = RgbaImage::from_pixel(2, 1, Rgba([255, 0, 0, 255]));
+        img.put_pixel(1, 0, Rgba([0, 0, 0, 0]));
+        let options = RenderOptions {
+            mode: PixelMode::Grayscale,
+            luminance: Luminance::Fast,
+            ..RenderOptions::default()
+        };
+        let mut output = Vec::new();
+        convert_pixels(&DynamicImage::ImageRgba8(img), &options, &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "\x1b[38;2;54;54;54m██\x1b[0m  \n"
+        );
+    }
+
+    #[test]
+    fn test_transparent_pixels_in_invert() {
+        // 创建一个 2x2 的测试图片，包含透明和不透明像素
+        let mut img = RgbaImage::new(2, 2);
+        img.put_pixel(0, 0, Rgba([255, 255, 255, 255])); // 白色，不透明
+        img.put_pixel(1, 0, Rgba([0, 0, 0, 255]));       // 黑色，不透明
+        img.put_pixel(0, 1, Rgba([255, 255, 255, 0]));   // 白色，透明
+        img.put_pixel(1, 1, Rgba([0, 0, 0, 0]));         // 黑色，透明
+        
+        let dynamic_img = DynamicImage::ImageRgba8(img);
+        let mut output = Vec::new();
+        
+        convert_to_invert(&dynamic_img, &RenderOptions::default(), &mut output).unwrap();
+        let result = String::from_utf8(output).unwrap();
+        
+        // 期望：第一行是"  ██"（白色空格+黑色块），第二行是"    "（两个透明像素都是空格）
+        assert_eq!(result, "  ██\n    \n");
+    }
+
+    #[test]
+    fn test_transparent_pixels_in_truecolor() {
+        // 创建一个 2x2 的测试图片，包含透明和不透明像素
+        let mut img = RgbaImage::new(2, 2);
+        img.put_pixel(0, 0, Rgba([255, 0, 0, 255]));     // 红色，不透明
+        img.put_pixel(1, 0, Rgba([0, 255, 0, 255]));     // 绿色，不透明
+        img.put_pixel(0, 1, Rgba([0, 0, 255, 0]));       // 蓝色，透明
+        img.put_pixel(1, 1, Rgba([255, 255, 255, 50]));  // 白色，半透明
+        
+        let dynamic_img = DynamicImage::ImageRgba8(img);
+        let mut output = Vec::new();
+        
+        convert_to_truecolor(&dynamic_img, &RenderOptions::default(), &mut output).unwrap();
+        let result = String::from_utf8(output).unwrap();
+        
+        // 期望：第一行有彩色块，第二行两个透明像素都是空格
+        // 第一行是红色块和绿色块
+        assert!(result.starts_with("\x1b[38;2;255;0;0m██\x1b[38;2;0;255;0m██\x1b[0m\n"));
+        assert!(result.ends_with("    \n")); // 第二行全是空格
+
+        let options = RenderOptions {
+            background_cells: true,
+            ..RenderOptions::default()
+        };
+        let mut output = Vec::new();
+        convert_to_truecolor(&dynamic_img, &options, &mut output).unwrap();
+        let result = String::from_utf8(output).unwrap();
+        assert!(result.contains("\x1b[48;2;255;0;0m  \x1b[48;2;0;255;0m  \x1b[0m")); // 红色背景
+    }
+
+    #[test]
+    fn test_small_image_is_borrowed() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::new(8, 8));
+        let options = RenderOptions::default();
+        let resized = resize_image(Cow::Borrowed(&img), &options);
+        assert!(matches!(resized, Cow::Borrowed(_)));
+        assert!(matches!(preprocess(resized, &options), Cow::Borrowed(_)));
+
+        let options = RenderOptions {
+            max_cells: Some((4, 4)),
+            ..RenderOptions::default()
+        };
+        assert!(matches!(resize_image(Cow::Borrowed(&img), &options), Cow::Owned(_)));
+    }
+
+    #[test]
+    fn test_pixels_written_at_once() {
+        /// 记录写入次数的输出
+        struct CountingWrites(usize);
+
+        impl Write for CountingWrites {
+            fn write(&mut self, buf: &[u8]) -> Result<usize> {
+                self.0 += 1;
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> Result<()> {
+                Ok(())
+            }
+        }
+
+        let img = RgbaImage::from_fn(8, 8, |x, y| Rgba([x as u8 * 30, y as u8 * 30, 0, 255]));
+        let img = DynamicImage::ImageRgba8(img);
+        let mut writer = CountingWrites(0);
+        convert_pixels(&img, &RenderOptions::default(), &mut writer).unwrap();
+        assert_eq!(writer.0, 1);
+    }
+
+    #[test]
+    fn test_truecolor_merges_runs() {
+        let red = Rgba([255, 0, 0, 255]);
+        let mut img = RgbaImage::from_pixel(6, 2, red);
+        img.put_pixel(3, 0, Rgba([0, 0, 0, 0]));
+        img.put_pixel(5, 0, Rgba([0, 0, 255, 255]));
+        let img = DynamicImage::ImageRgba8(img);
+        let mut output = Vec::new();
+        convert_to_truecolor(&img, &RenderOptions::default(), &mut output).unwrap();
+
+        // 同色的连续像素共用一个转义序列，透明像素之前和每行末尾重置
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "\x1b[38;2;255;0;0m██████\x1b[0m  \x1b[38;2;255;0;0m██\x1b[38;2;0;0;255m██\x1b[0m\n\
+             \x1b[38;2;255;0;0m████████████\x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn test_extreme_aspect_ratio_keeps_one_column() {
+        let white = Rgba([255, 255, 255, 255]);
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 2000, white));
+
+        for aspect_fit in [AspectFit::Squash, AspectFit::Pad] {
+            let options = RenderOptions {
+                mode: PixelMode::Monochrome,
+                aspect_fit,
+                ..RenderOptions::default()
+            };
+            let mut output = Vec::new();
+            let resized = resize_image(Cow::Borrowed(&img), &options);
+            convert_image_to_text(&resized, &options, &mut output).unwrap();
+            let result = String::from_utf8(output).unwrap();
+
+            assert_eq!(result.lines().count(), 80);
+            assert!(result.lines().all(|line| line == "██"));
+        }
+    }
+
+    #[test]
+    fn test_pad_keeps_aspect_with_transparent_columns() {
+        // 10x3 缩小到 6x1 时宽高比会被拉伸，填充后两侧各多出一列透明像素
+        let white = Rgba([255, 255, 255, 255]);
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(10, 3, white));
+        let render = |aspect_fit| {
+            let options = RenderOptions {
+                mode: PixelMode::Monochrome,
+                aspect_correction: AspectCorrection::Off,
+                max_cells: Some((6, 2)),
+                aspect_fit,
+                ..RenderOptions::default()
+            };
+            let resized = resize_image(Cow::Borrowed(&img), &options);
+            let alpha: Vec<u8> = resized.pixels().map(|(_, _, pixel)| pixel[3]).collect();
+            let mut output = Vec::new();
+            convert_image_to_text(&resized, &options, &mut output).unwrap();
+            (alpha, String::from_utf8(output).unwrap())
+        };
+
+        let (alpha, text) = render(AspectFit::Squash);
+        assert_eq!(alpha, [255; 6]);
+        assert_eq!(text, format!("{}\n", "██".repeat(6)));
+        let (alpha, text) = render(AspectFit::Pad);
+        assert_eq!(alpha, [0, 255, 255, 255, 255, 0]);
+        assert_eq!(text, format!("  {}  \n", "██".repeat(4)));
+    }
+
+    #[test]
+    fn test_max_cells() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::new(200, 100));
+        let resized = |max_cells| {
+            let options = RenderOptions {
+                max_cells,
+                ..RenderOptions::default()
+            };
+            resize_image(Cow::Borrowed(&img), &options).dimensions()
+        };
+
+        assert_eq!(resized(Some(DEFAULT_MAX_CELLS)), (80, 40));
+        assert_eq!(resized(Some((200, 20))), (40, 20));
+        assert_eq!(resized(None), (200, 100));
+    }
+
+    #[test]
+    fn test_aspect_correction() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::new(4, 6));
+        let resized = |mode, pixel_width, aspect_correction| {
+            let options = RenderOptions {
+                mode,
+                pixel_width,
+                aspect_correction,
+                ..RenderOptions::default()
+            };
+            resize_image(Cow::Borrowed(&img), &options).dimensions()
+        };
+
+        let (double, single) = (PixelWidth::Double, PixelWidth::Single);
+        let auto = AspectCorrection::Auto;
+        assert_eq!(resized(PixelMode::TrueColor, double, auto), (4, 6));
+        assert_eq!(resized(PixelMode::Braille, double, auto), (4, 6));
+        assert_eq!(resized(PixelMode::TrueColor, single, auto), (4, 3));
+        assert_eq!(resized(PixelMode::Quadrant, double, auto), (4, 3));
+        let off = AspectCorrection::Off;
+        assert_eq!(resized(PixelMode::Quadrant, double, off), (4, 6));
+        let factor = AspectCorrection::Factor(1.5);
+        assert_eq!(resized(PixelMode::TrueColor, double, factor), (4, 9));
+    }
+
+    #[test]
+    fn test_crop_image() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(4, 4, |x, y| {
+            Rgba([x as u8, y as u8, 0, 255])
+        }));
+        let crop = |rect| {
+            let options = RenderOptions {
+                crop: Some(rect),
+                ..RenderOptions::default()
+            };
+            crop_image(Cow::Borrowed(&img), &options)
+        };
+
+        let cropped = crop(Rect::new(1, 2, 2, 1));
+        assert_eq!(cropped.dimensions(), (2, 1));
+        assert_eq!(cropped.get_pixel(0, 0), Rgba([1, 2, 0, 255]));
+        // 超出图片的部分被忽略
+        assert_eq!(crop(Rect::new(3, 1, 10, 10)).dimensions(), (1, 3));
+        assert_eq!(crop(Rect::new(9, 9, 0, 0)).dimensions(), (1, 1));
+    }
+
+    #[test]
+    fn test_single_width_pixels() {
+        let mut img = RgbaImage::new(2, 1);
+        img.put_pixel(0, 0, Rgba([255, 255, 255, 255]));
+        img.put_pixel(1, 0, Rgba([0, 0, 0, 255]));
+
+        let options = RenderOptions {
+            pixel_width: PixelWidth::Single,
+            ..RenderOptions::default()
+        };
+        let mut output = Vec::new();
+        convert_to_monochrome(&DynamicImage::ImageRgba8(img), &options, &mut output).unwrap();
+
+        assert_eq!(String::from_utf8(output).unwrap(), "█ \n");
+    }
+
+    #[test]
+    fn test_shade_levels() {
+        let mut img = RgbaImage::new(5, 1);
+        for (x, level) in [0u8, 60, 120, 180, 255].into_iter().enumerate() {
+            img.put_pixel(x as u32, 0, Rgba([level, level, level, 255]));
+        }
+
+        let mut output = Vec::new();
+        let options = RenderOptions::default();
+        convert_to_shade(&DynamicImage::ImageRgba8(img), &options, &mut output).unwrap();
+
+        assert_eq!(String::from_utf8(output).unwrap(), "  ░░▒▒▓▓██\n");
+    }
+
+    #[test]
+    fn test_ansi256_mode() {
+        let mut img = RgbaImage::new(2, 1);
+        img.put_pixel(0, 0, Rgba([255, 0, 0, 255]));
+        let img = DynamicImage::ImageRgba8(img);
+        let options = RenderOptions {
+            mode: PixelMode::Ansi256,
+            ..RenderOptions::default()
+        };
+        let mut output = Vec::new();
+        convert_image_to_text(&img, &options, &mut output).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "\x1b[38;5;196m██\x1b[0m  \n"
+        );
+        let options = RenderOptions {
+            background_cells: true,
+            ..options
+        };
+        let mut output = Vec::new();
+        convert_image_to_text(&img, &options, &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "\x1b[48;5;196m  \x1b[0m  \n"
+        );
+    }
+
+    #[test]
+    fn test_ansi16_mode() {
+        let mut img = RgbaImage::new(2, 1);
+        img.put_pixel(0, 0, Rgba([255, 0, 0, 255]));
+        img.put_pixel(1, 0, Rgba([0, 0, 0, 255]));
+        let img = DynamicImage::ImageRgba8(img);
+        let options = RenderOptions {
+            mode: PixelMode::Ansi16,
+            ..RenderOptions::default()
+        };
+        let mut output = Vec::new();
+        convert_image_to_text(&img, &options, &mut output).unwrap();
+
+        // 亮红色为 91，黑色为 30
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "\x1b[91m██\x1b[0m\x1b[30m██\x1b[0m\n"
+        );
+        let options = RenderOptions {
+            background_cells: true,
+            ..options
+        };
+        let mut output = Vec::new();
+        convert_image_to_text(&img, &options, &mut output).unwrap();
+        assert!(String::from_utf8(output)
+            .unwrap()
+            .starts_with("\x1b[101m  \x1b[0m"));
+    }
+
+    #[test]
+    fn test_ascii_ramp_mode() {
+        let mut img = image::RgbaImage::new(3, 1);
+        img.put_pixel(0, 0, image::Rgba([0, 0, 0, 255]));
+        img.put_pixel(1, 0, image::Rgba([128, 128, 128, 255]));
+        img.put_pixel(2, 0, image::Rgba([255, 255, 255, 255]));
+        let img = DynamicImage::ImageRgba8(img);
+        let options = RenderOptions {
+            mode: PixelMode::Ascii,
+            pixel_width: PixelWidth::Single,
+            ..RenderOptions::default()
+        };
+        let mut output = Vec::new();
+        convert_image_to_text(&img, &options, &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), " +@\n");
+
+        let custom = RenderOptions {
+            ramp: "ab".to_string(),
+            ..options
+        };
+        let mut output = Vec::new();
+        convert_image_to_text(&img, &custom, &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "abb\n");
+    }
+
+    #[test]
+    fn test_quadrant_two_colors() {
+        let (red, blue) = (Rgba([255, 0, 0, 255]), Rgba([0, 0, 255, 255]));
+        let mut img = RgbaImage::from_pixel(4, 2, red);
+        img.put_pixel(1, 0, blue);
+        img.put_pixel(1, 1, blue);
+        // 第二个格子只有左上角不透明
+        for (x, y) in [(3, 0), (2, 1), (3, 1)] {
+            img.put_pixel(x, y, Rgba([0, 0, 0, 0]));
+        }
+        let img = DynamicImage::ImageRgba8(img);
+        let options = RenderOptions {
+            mode: PixelMode::Quadrant,
+            ..RenderOptions::default()
+        };
+        let mut output = Vec::new();
+        convert_image_to_text(&img, &options, &mut output).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "\x1b[38;2;255;0;0m\x1b[48;2;0;0;255m▌\x1b[0m\x1b[38;2;255;0;0m▘\x1b[0m\n"
+        );
+        assert_eq!((art_width(4, &options), art_height(2, &options)), (2, 1));
+    }
+
+    #[test]
+    fn test_quadrant_palette() {
+        // 四个像素各不相同，平均色都不在调色板中
+        let colors = [[200, 30, 30], [30, 200, 30], [30, 30, 200], [120, 120, 120]];
+        let mut img = RgbaImage::from_fn(4, 2, |x, y| {
+            let [r, g, b] = colors[(x % 2 + y * 2) as usize];
+            Rgba([r, g, b, 255])
+        });
+        img.put_pixel(3, 1, Rgba([0, 0, 0, 0]));
+        let palette = ColorPalette::new(vec![
+            Color::rgb(0, 0, 0),
+            Color::rgb(255, 255, 255),
+            Color::rgb(255, 0, 0),
+            Color::rgb(0, 0, 255),
+        ]);
+        let options = RenderOptions {
+            mode: PixelMode::Quadrant,
+            palette: Some(palette.clone()),
+            ..RenderOptions::default()
+        };
+        let mut output = Vec::new();
+        convert_image_to_text(&DynamicImage::ImageRgba8(img), &options, &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let escapes = regex::Regex::new(r"\x1b\[[34]8;2;(\d+);(\d+);(\d+)m").unwrap();
+        let emitted: Vec<Color> = escapes
+            .captures_iter(&output)
+            .map(|c| {
+                let channel = |i: usize| c[i].parse().unwrap();
+                Color::rgb(channel(1), channel(2), channel(3))
+            })
+            .collect();
+        assert!(!emitted.is_empty(), "{:?}", output);
+        for color in emitted {
+            assert!(palette.colors().contains(&color), "{:?}", color);
+        }
+    }
+
+    #[test]
+    fn test_braille_dots() {
+        // 3x5 的图片需要 2x2 个盲文字符，不足的部分按空白处理
+        let mut img = image::RgbaImage::new(3, 5);
+        for (x, y) in [(0, 0), (1, 1), (0, 3), (2, 0), (1, 4)] {
+            img.put_pixel(x, y, image::Rgba([255, 255, 255, 255]));
+        }
+        let img = DynamicImage::ImageRgba8(img);
+        let options = RenderOptions {
+            mode: PixelMode::Braille,
+            ..RenderOptions::default()
+        };
+        let mut output = Vec::new();
+        convert_image_to_text(&img, &options, &mut output).unwrap();
+
+        assert_eq!(String::from_utf8(output).unwrap(), "\u{2851}\u{2801}\n\u{2808} \n");
+        assert_eq!((art_width(3, &options), art_height(5, &options)), (2, 2));
+    }
+
+    #[test]
+    fn test_background_fill() {
+        let mut img = RgbaImage::new(2, 1);
+        img.put_pixel(0, 0, Rgba([255, 0, 0, 255]));
+
+        let options = RenderOptions {
+            background: Some(Color::rgb(0, 0, 64)),
+            ..RenderOptions::default()
+        };
+        let mut output = Vec::new();
+        convert_image_to_text(&DynamicImage::ImageRgba8(img), &options, &mut output).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "\x1b[48;2;0;0;64m\x1b[38;2;255;0;0m██\x1b[0m\x1b[48;2;0;0;64m  \x1b[0m\n"
+        );
+    }
+}
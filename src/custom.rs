@@ -0,0 +1,111 @@
+use image::{DynamicImage, GenericImageView, Rgba};
+use std::borrow::Cow;
+use std::io::{Result, Write};
+use std::sync::Arc;
+
+/// 自定义的像素渲染方式，通过 [`crate::PixelMode::custom`] 使用
+///
+/// 每个像素对应一个字符格，返回的内容可以包含 ANSI 转义序列，显示宽度应当与
+/// `RenderOptions::pixel_width` 一致，否则连接线和消息框会对不齐。
+///
+/// # Example
+///
+/// ```rust
+/// use image::Rgba;
+/// use pixel_says::{PixelMode, PixelRenderer};
+/// use std::borrow::Cow;
+///
+/// struct Logo;
+///
+/// impl PixelRenderer for Logo {
+///     fn cell(&self, rgba: Rgba<u8>, x: u32, _y: u32) -> Cow<'_, str> {
+///         if rgba[3] < 128 {
+///             Cow::Borrowed("  ")
+///         } else {
+///             Cow::Owned(["<>", "[]"][x as usize % 2].to_string())
+///         }
+///     }
+/// }
+///
+/// let mode = PixelMode::custom(Logo);
+/// ```
+pub trait PixelRenderer: Send + Sync {
+    /// 位于 (`x`, `y`) 的像素对应的字符格内容
+    fn cell(&self, rgba: Rgba<u8>, x: u32, y: u32) -> Cow<'_, str>;
+}
+
+/// 共享的自定义渲染方式，克隆时不会复制渲染器；只有同一个渲染器的副本才相等
+#[derive(Clone)]
+pub struct CustomRenderer(Arc<dyn PixelRenderer>);
+
+impl CustomRenderer {
+    /// 包装渲染器
+    pub fn new<R>(renderer: R) -> Self
+    where
+        R: PixelRenderer + 'static,
+    {
+        CustomRenderer(Arc::new(renderer))
+    }
+}
+
+impl std::fmt::Debug for CustomRenderer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("CustomRenderer")
+    }
+}
+
+impl PartialEq for CustomRenderer {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for CustomRenderer {}
+
+/// 用自定义的渲染方式转换图片
+pub(crate) fn convert_to_custom(
+    img: &DynamicImage,
+    renderer: &CustomRenderer,
+    writer: &mut Vec<u8>,
+) -> Result<()> {
+    let (width, height) = img.dimensions();
+    for y in 0..height {
+        for x in 0..width {
+            writer.write_all(renderer.0.cell(img.get_pixel(x, y), x, y).as_bytes())?;
+        }
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{render_image_to_text, PixelMode};
+    use image::RgbaImage;
+
+    struct Coordinates;
+
+    impl PixelRenderer for Coordinates {
+        fn cell(&self, rgba: Rgba<u8>, x: u32, y: u32) -> Cow<'_, str> {
+            match rgba[3] {
+                0 => Cow::Borrowed(".."),
+                _ => Cow::Owned(format!("{}{}", x, y)),
+            }
+        }
+    }
+
+    #[test]
+    fn test_custom_renderer() {
+        let mut img = RgbaImage::from_pixel(3, 2, Rgba([255; 4]));
+        img.put_pixel(1, 0, Rgba([0; 4]));
+        let mut output = Vec::new();
+        let mode = PixelMode::custom(Coordinates);
+        render_image_to_text(DynamicImage::ImageRgba8(img), mode.clone(), &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "00..20\n011121\n");
+
+        assert_eq!(mode, mode.clone());
+        assert_ne!(mode, PixelMode::custom(Coordinates));
+        assert_eq!(mode.to_string(), "custom");
+    }
+}
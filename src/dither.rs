@@ -0,0 +1,233 @@
+use crate::ans::nearest_basic;
+use crate::color::luminance;
+use crate::convert::resolve_mode;
+use crate::filters::{is_visible, resolve_threshold};
+use crate::{
+    BayerSize, Color, ColorPalette, Dither, Luminance, OutputProfile, PixelMode, RenderOptions,
+};
+use image::RgbaImage;
+
+/// 抖动时可以使用的颜色
+#[derive(Clone, Copy)]
+enum Palette<'a> {
+    /// 黑白两色，对应以亮度为阈值的模式；亮度高于阈值时取白色
+    BlackWhite(u8, Luminance),
+    /// xterm 256 色中的颜色立方体和灰阶
+    Ansi256,
+    /// 终端的基本 16 色
+    Ansi16,
+    /// 用户提供的调色板
+    Custom(&'a ColorPalette),
+}
+
+impl<'a> Palette<'a> {
+    /// 与像素模式对应的调色板，不限制颜色的模式不需要抖动
+    fn for_options(img: &RgbaImage, options: &'a RenderOptions) -> Option<Palette<'a>> {
+        if options.profile == OutputProfile::Ascii {
+            return None;
+        }
+        match resolve_mode(options) {
+            PixelMode::TrueColor | PixelMode::Quadrant if options.palette.is_some() => {
+                options.palette.as_ref().map(Palette::Custom)
+            }
+            PixelMode::Monochrome | PixelMode::Invert | PixelMode::Braille => {
+                let threshold = resolve_threshold(img, options.threshold, options.luminance);
+                Some(Palette::BlackWhite(threshold, options.luminance))
+            }
+            PixelMode::Ansi256 => Some(Palette::Ansi256),
+            PixelMode::Ansi16 => Some(Palette::Ansi16),
+            _ => None,
+        }
+    }
+
+    /// 相邻两种颜色在一个通道上的大致间隔，决定有序抖动的幅度
+    fn spread(self) -> f32 {
+        match self {
+            Palette::BlackWhite(..) => 255.0,
+            // 颜色立方体每个通道有 6 级
+            Palette::Ansi256 => 255.0 / 5.0,
+            // 每个通道大致只有暗、中、亮 3 级
+            Palette::Ansi16 => 255.0 / 2.0,
+            // 假设颜色在三个通道上均匀分布
+            Palette::Custom(palette) => 255.0 / (palette.colors().len() as f32).cbrt().max(1.0),
+        }
+    }
+
+    /// 调色板中最接近的颜色
+    fn nearest(self, rgb: [f32; 3]) -> [u8; 3] {
+        let [r, g, b] = rgb.map(|c| c.round().clamp(0.0, 255.0) as u8);
+        match self {
+            Palette::BlackWhite(threshold, model) => {
+                if luminance(r, g, b, model) > threshold {
+                    [255; 3]
+                } else {
+                    [0; 3]
+                }
+            }
+            Palette::Ansi256 => {
+                let color = Color::from_ansi256(Color::rgb(r, g, b).to_ansi256());
+                [color.r, color.g, color.b]
+            }
+            Palette::Ansi16 => {
+                let color = Color::from_ansi256(nearest_basic(Color::rgb(r, g, b), true));
+                [color.r, color.g, color.b]
+            }
+            Palette::Custom(palette) => {
+                let color = palette.nearest(Color::rgb(r, g, b));
+                [color.r, color.g, color.b]
+            }
+        }
+    }
+}
+
+/// 按照像素模式的可用颜色对图片做抖动，透明像素保持不变
+pub(crate) fn dither(img: &mut RgbaImage, options: &RenderOptions) {
+    let Some(palette) = Palette::for_options(img, options) else {
+        return;
+    };
+    match options.dither {
+        // 其他调色板在转换时量化
+        Dither::Off if matches!(palette, Palette::Custom(_)) => quantize(img, palette),
+        Dither::Off => {}
+        Dither::FloydSteinberg => floyd_steinberg(img, palette),
+        Dither::Ordered(size) => ordered(img, palette, size),
+    }
+}
+
+/// 不抖动，直接取最接近的颜色
+fn quantize(img: &mut RgbaImage, palette: Palette) {
+    for pixel in img.pixels_mut().filter(|pixel| is_visible(pixel)) {
+        let wanted = [0, 1, 2].map(|c| pixel[c] as f32);
+        pixel.0[..3].copy_from_slice(&palette.nearest(wanted));
+    }
+}
+
+/// 边长为 `size` 的 Bayer 矩阵，元素为 0 到 `size * size - 1`
+fn bayer_matrix(size: usize) -> Vec<Vec<u32>> {
+    let mut matrix = vec![vec![0]];
+    while matrix.len() < size {
+        let n = matrix.len();
+        let mut next = vec![vec![0; n * 2]; n * 2];
+        for (y, row) in matrix.iter().enumerate() {
+            for (x, &value) in row.iter().enumerate() {
+                next[y][x] = 4 * value;
+                next[y][x + n] = 4 * value + 2;
+                next[y + n][x] = 4 * value + 3;
+                next[y + n][x + n] = 4 * value + 1;
+            }
+        }
+        matrix = next;
+    }
+    matrix
+}
+
+/// 有序抖动：按像素在 Bayer 矩阵中的位置加上固定的偏移后再取最接近的颜色，
+/// 形成规则的网点图案
+fn ordered(img: &mut RgbaImage, palette: Palette, size: BayerSize) {
+    let n = size.size();
+    let matrix = bayer_matrix(n);
+    let cells = (n * n) as f32;
+
+    for (x, y, pixel) in img.enumerate_pixels_mut() {
+        if !is_visible(pixel) {
+            continue;
+        }
+        let threshold = (matrix[y as usize % n][x as usize % n] as f32 + 0.5) / cells - 0.5;
+        let offset = threshold * palette.spread();
+        let wanted = [0, 1, 2].map(|c| pixel[c] as f32 + offset);
+        pixel.0[..3].copy_from_slice(&palette.nearest(wanted));
+    }
+}
+
+/// Floyd–Steinberg 误差扩散：把每个像素的量化误差按 7/16、3/16、5/16、1/16
+/// 分给右侧和下一行的相邻像素
+fn floyd_steinberg(img: &mut RgbaImage, palette: Palette) {
+    const WEIGHTS: [(i64, i64, f32); 4] = [
+        (1, 0, 7.0 / 16.0),
+        (-1, 1, 3.0 / 16.0),
+        (0, 1, 5.0 / 16.0),
+        (1, 1, 1.0 / 16.0),
+    ];
+    let (width, height) = (img.width() as i64, img.height() as i64);
+    let mut errors = vec![[0.0f32; 3]; (width * height) as usize];
+
+    for y in 0..height {
+        for x in 0..width {
+            let pixel = img.get_pixel_mut(x as u32, y as u32);
+            if !is_visible(pixel) {
+                continue;
+            }
+            let error = errors[(y * width + x) as usize];
+            let wanted = [0, 1, 2].map(|c| pixel[c] as f32 + error[c]);
+            let chosen = palette.nearest(wanted);
+            pixel.0[..3].copy_from_slice(&chosen);
+
+            for (dx, dy, weight) in WEIGHTS {
+                let (nx, ny) = (x + dx, y + dy);
+                if nx < 0 || nx >= width || ny >= height {
+                    continue;
+                }
+                let target = &mut errors[(ny * width + nx) as usize];
+                for c in 0..3 {
+                    target[c] += (wanted[c] - chosen[c] as f32) * weight;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn test_floyd_steinberg_keeps_gray_level() {
+        let mut img = RgbaImage::from_pixel(8, 8, Rgba([128, 128, 128, 255]));
+        let options = RenderOptions {
+            mode: PixelMode::Monochrome,
+            dither: Dither::FloydSteinberg,
+            ..RenderOptions::default()
+        };
+        dither(&mut img, &options);
+
+        // 只剩黑白两色，白色大约占一半
+        assert!(img.pixels().all(|p| p[0] == 0 || p[0] == 255));
+        let white = img.pixels().filter(|p| p[0] == 255).count();
+        assert!((24..=40).contains(&white), "{} white pixels", white);
+    }
+
+    #[test]
+    fn test_ordered_dither() {
+        assert_eq!(bayer_matrix(2), [[0, 2], [3, 1]]);
+        assert_eq!(bayer_matrix(4)[1], [12, 4, 14, 6]);
+
+        // 亮度为 25% 的灰色在 4x4 矩阵下恰好有 4 个白色像素
+        let mut img = RgbaImage::from_pixel(4, 4, Rgba([64, 64, 64, 255]));
+        let options = RenderOptions {
+            mode: PixelMode::Monochrome,
+            dither: Dither::Ordered(BayerSize::Four),
+            ..RenderOptions::default()
+        };
+        dither(&mut img, &options);
+        assert_eq!(img.pixels().filter(|p| p[0] == 255).count(), 4);
+    }
+
+    #[test]
+    fn test_custom_palette() {
+        let palette = ColorPalette::builtin("gameboy").unwrap();
+        let mut img = RgbaImage::from_fn(4, 4, |x, y| Rgba([x as u8 * 60, y as u8 * 60, 90, 255]));
+        img.put_pixel(0, 0, Rgba([255, 0, 0, 0]));
+        let options = RenderOptions {
+            palette: Some(palette.clone()),
+            ..RenderOptions::default()
+        };
+        dither(&mut img, &options);
+        // 透明像素不变，其余像素都是调色板中的颜色
+        assert_eq!(img.get_pixel(0, 0).0, [255, 0, 0, 0]);
+        assert!(img
+            .pixels()
+            .skip(1)
+            .all(|p| palette.colors().contains(&Color::rgb(p[0], p[1], p[2]))));
+    }
+}
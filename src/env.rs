@@ -0,0 +1,137 @@
+use crate::{PixelMode, RenderOptions, TerminalBackground};
+use std::fmt;
+use std::path::PathBuf;
+
+/// 像素转换模式，如 `truecolor`
+pub const MODE_VAR: &str = "PIXEL_SAYS_MODE";
+/// 默认的图片路径
+pub const IMAGE_VAR: &str = "PIXEL_SAYS_IMAGE";
+/// 消息框的默认宽度
+pub const WIDTH_VAR: &str = "PIXEL_SAYS_WIDTH";
+/// 终端主题，`dark` 或 `light`
+pub const THEME_VAR: &str = "PIXEL_SAYS_THEME";
+
+/// 从环境变量读取的默认配置
+///
+/// 未设置（或为空）的变量对应 `None`。
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct EnvDefaults {
+    /// `PIXEL_SAYS_MODE`
+    pub mode: Option<PixelMode>,
+    /// `PIXEL_SAYS_IMAGE`
+    pub image: Option<PathBuf>,
+    /// `PIXEL_SAYS_WIDTH`
+    pub width: Option<usize>,
+    /// `PIXEL_SAYS_THEME`
+    pub theme: Option<TerminalBackground>,
+}
+
+/// 环境变量的值无法解析
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidEnvVar {
+    /// 变量名
+    pub name: &'static str,
+    /// 变量的值
+    pub value: String,
+}
+
+impl fmt::Display for InvalidEnvVar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "环境变量 {} 的值无效: {}", self.name, self.value)
+    }
+}
+
+impl std::error::Error for InvalidEnvVar {}
+
+impl EnvDefaults {
+    /// 读取当前进程的环境变量
+    pub fn from_env() -> Result<Self, InvalidEnvVar> {
+        Self::from_lookup(|name| std::env::var(name).ok())
+    }
+
+    /// 通过 `lookup` 按变量名取值，便于测试或从其他来源读取
+    pub fn from_lookup<F>(lookup: F) -> Result<Self, InvalidEnvVar>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let get = |name: &'static str| {
+            lookup(name)
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+                .map(|value| (name, value))
+        };
+        let invalid = |(name, value): (&'static str, String)| InvalidEnvVar { name, value };
+
+        let mode = match get(MODE_VAR) {
+            Some(var) => Some(var.1.parse().map_err(|_| invalid(var))?),
+            None => None,
+        };
+        let width = match get(WIDTH_VAR) {
+            Some(var) => Some(var.1.parse().map_err(|_| invalid(var))?),
+            None => None,
+        };
+        let theme = match get(THEME_VAR) {
+            Some(var) => Some(match var.1.to_ascii_lowercase().as_str() {
+                "dark" => TerminalBackground::Dark,
+                "light" => TerminalBackground::Light,
+                _ => return Err(invalid(var)),
+            }),
+            None => None,
+        };
+
+        Ok(EnvDefaults {
+            mode,
+            image: get(IMAGE_VAR).map(|(_, value)| PathBuf::from(value)),
+            width,
+            theme,
+        })
+    }
+
+    /// 实际使用的像素转换模式
+    ///
+    /// 浅色主题下黑白模式会换成反色模式，与 [`TerminalBackground::monochrome_mode`] 一致。
+    pub fn effective_mode(&self) -> Option<PixelMode> {
+        match (&self.mode, self.theme) {
+            (Some(PixelMode::Monochrome), Some(theme)) => Some(theme.monochrome_mode()),
+            (mode, _) => mode.clone(),
+        }
+    }
+
+    /// 把设置了的项写入渲染选项
+    pub fn apply(&self, options: &mut RenderOptions) {
+        if let Some(mode) = self.effective_mode() {
+            options.mode = mode;
+        }
+        if let Some(width) = self.width {
+            options.max_width = width;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_defaults() {
+        let defaults = EnvDefaults::from_lookup(|name| match name {
+            MODE_VAR => Some("Monochrome".to_string()),
+            WIDTH_VAR => Some("24".to_string()),
+            THEME_VAR => Some("light".to_string()),
+            IMAGE_VAR => Some(String::new()),
+            _ => None,
+        })
+        .unwrap();
+
+        assert_eq!(defaults.image, None);
+        assert_eq!(defaults.effective_mode(), Some(PixelMode::Invert));
+        let mut options = RenderOptions::default();
+        defaults.apply(&mut options);
+        assert_eq!(options.mode, PixelMode::Invert);
+        assert_eq!(options.max_width, 24);
+
+        let err = EnvDefaults::from_lookup(|name| (name == WIDTH_VAR).then(|| "wide".to_string()))
+            .unwrap_err();
+        assert_eq!(err.name, WIDTH_VAR);
+    }
+}
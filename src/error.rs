@@ -0,0 +1,189 @@
+use crate::{DecodeLimits, OutputBackend, SheetLayout};
+use image::ImageError;
+use std::fmt;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+/// 库中各种失败的原因
+///
+/// 公开的函数仍然返回 `std::io::Result`：它们大多写入调用者提供的 `Write`，写入失败与
+/// 渲染失败需要一起处理，改为返回 `Result<_, PixelSaysError>` 会破坏现有的调用方式。
+/// 库自身产生的错误都以 `PixelSaysError` 的形式包装在 `std::io::Error` 中，
+/// 用 [`PixelSaysError::from_io`] 取回后即可按原因匹配，不需要解析错误信息；
+/// 也可以用 `PixelSaysError::from` 把任何 `std::io::Error` 转换为 `PixelSaysError`，
+/// 写入输出等普通的 I/O 错误成为 [`PixelSaysError::Io`]。
+///
+/// # Example
+///
+/// ```rust
+/// use pixel_says::{say_from_bytes, PixelMode, PixelSaysError};
+///
+/// let err = say_from_bytes(b"not an image", "hi", 40, PixelMode::TrueColor, Vec::new())
+///     .unwrap_err();
+/// assert!(matches!(
+///     PixelSaysError::from_io(&err),
+///     Some(PixelSaysError::ImageDecode(_))
+/// ));
+/// match PixelSaysError::from(err) {
+///     PixelSaysError::ImageDecode(_) => {}
+///     PixelSaysError::Io(err) => panic!("I/O error: {}", err),
+///     err => panic!("{}", err),
+/// }
+/// ```
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum PixelSaysError {
+    /// 读取图片或写入输出时的 I/O 错误
+    Io(std::io::Error),
+    /// 无法识别或解码图片
+    ImageDecode(ImageError),
+    /// 无法把图片编码为 PNG（iTerm2 输出或 `OutputBackend::Png`）
+    ImageEncode(ImageError),
+    /// 图片超出解码限制
+    ImageTooLarge(ImageTooLarge),
+    /// 无法解析或绘制 SVG 图片
+    Svg(String),
+    /// 需要的 Cargo 功能（如 `svg`）没有启用
+    FeatureDisabled(&'static str),
+    /// 这种输出方式不能用于所调用的函数，例如逐行输出不能生成二进制的 PNG
+    UnsupportedMode(OutputBackend),
+    /// 查询终端（背景色、图形协议）时没有得到完整的回复，附带原因
+    TerminalQuery(&'static str),
+    /// 无法下载网络图片
+    Download(String),
+    /// 目录中没有图片
+    NoImages(PathBuf),
+    /// 无法按给定的方式切分精灵图
+    InvalidSpriteSheet {
+        /// 切分方式
+        layout: SheetLayout,
+        /// 精灵图的宽度
+        width: u32,
+        /// 精灵图的高度
+        height: u32,
+    },
+    /// 消息中包含转义序列或控制字符，见 [`AnsiPolicy::Reject`](crate::AnsiPolicy::Reject)
+    ControlCharacters,
+    /// 使用 [`OutputProfile::Ascii`](crate::OutputProfile::Ascii) 时输出了非 ASCII 字节
+    NonAsciiOutput(u8),
+    /// 无效的 FIGlet 字体
+    InvalidFont(&'static str),
+    /// 无效的调色板文件
+    InvalidPalette(String),
+}
+
+impl PixelSaysError {
+    /// 取回包装在 `std::io::Error` 中的错误，其他 I/O 错误返回 `None`
+    pub fn from_io(err: &std::io::Error) -> Option<&PixelSaysError> {
+        err.get_ref()?.downcast_ref()
+    }
+
+    /// 包装为 `std::io::Error` 时使用的错误类型
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            PixelSaysError::Io(e) => e.kind(),
+            PixelSaysError::TerminalQuery(_) => ErrorKind::TimedOut,
+            PixelSaysError::ImageEncode(_)
+            | PixelSaysError::ImageTooLarge(_)
+            | PixelSaysError::NonAsciiOutput(_)
+            | PixelSaysError::InvalidFont(_)
+            | PixelSaysError::InvalidPalette(_) => ErrorKind::InvalidData,
+            PixelSaysError::FeatureDisabled(_) | PixelSaysError::UnsupportedMode(_) => {
+                ErrorKind::Unsupported
+            }
+            PixelSaysError::Download(_) => ErrorKind::Other,
+            PixelSaysError::NoImages(_) => ErrorKind::NotFound,
+            PixelSaysError::ImageDecode(_)
+            | PixelSaysError::Svg(_)
+            | PixelSaysError::InvalidSpriteSheet { .. }
+            | PixelSaysError::ControlCharacters => ErrorKind::InvalidInput,
+        }
+    }
+}
+
+impl fmt::Display for PixelSaysError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PixelSaysError::Io(e) => e.fmt(f),
+            PixelSaysError::ImageDecode(e) => write!(f, "无法加载图片: {}", e),
+            PixelSaysError::ImageEncode(e) => write!(f, "无法编码图片: {}", e),
+            PixelSaysError::ImageTooLarge(e) => e.fmt(f),
+            PixelSaysError::Svg(e) => write!(f, "无法加载 SVG 图片: {}", e),
+            PixelSaysError::FeatureDisabled(feature) => write!(f, "需要启用 {} 功能", feature),
+            PixelSaysError::UnsupportedMode(backend) => write!(f, "不能以 {:?} 方式输出", backend),
+            PixelSaysError::TerminalQuery(reason) => write!(f, "无法查询终端: {}", reason),
+            PixelSaysError::Download(e) => write!(f, "无法下载图片: {}", e),
+            PixelSaysError::NoImages(dir) => write!(f, "目录中没有图片: {}", dir.display()),
+            PixelSaysError::InvalidSpriteSheet {
+                layout,
+                width,
+                height,
+            } => write!(f, "无法按 {:?} 切分 {}x{} 的精灵图", layout, width, height),
+            PixelSaysError::ControlCharacters => write!(f, "消息中包含终端转义序列或控制字符"),
+            PixelSaysError::NonAsciiOutput(byte) => {
+                write!(f, "输出中包含非 ASCII 字符: 0x{:02x}", byte)
+            }
+            PixelSaysError::InvalidFont(reason) => write!(f, "无效的 FIGlet 字体: {}", reason),
+            PixelSaysError::InvalidPalette(reason) => write!(f, "无效的调色板: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for PixelSaysError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PixelSaysError::Io(e) => Some(e),
+            PixelSaysError::ImageDecode(e) | PixelSaysError::ImageEncode(e) => Some(e),
+            PixelSaysError::ImageTooLarge(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<PixelSaysError> for std::io::Error {
+    fn from(err: PixelSaysError) -> Self {
+        match err {
+            PixelSaysError::Io(err) => err,
+            err => std::io::Error::new(err.kind(), err),
+        }
+    }
+}
+
+/// 取回包装在其中的 `PixelSaysError`，其他 I/O 错误成为 [`PixelSaysError::Io`]
+impl From<std::io::Error> for PixelSaysError {
+    fn from(err: std::io::Error) -> Self {
+        err.downcast().unwrap_or_else(PixelSaysError::Io)
+    }
+}
+
+/// 图片尺寸或所需内存超出解码限制时返回的错误
+///
+/// 以 [`PixelSaysError::ImageTooLarge`] 的形式返回（`ErrorKind::InvalidData`）。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageTooLarge {
+    /// 图片的实际尺寸，解码前无法得知时为 `None`
+    pub dimensions: Option<(u32, u32)>,
+    /// 触发错误时生效的限制
+    pub limits: DecodeLimits,
+}
+
+impl fmt::Display for ImageTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.dimensions {
+            Some((width, height)) => write!(f, "图片过大: {}x{}", width, height)?,
+            None => write!(f, "图片过大")?,
+        }
+        if let (Some(width), Some(height)) = (self.limits.max_width, self.limits.max_height) {
+            write!(f, "（限制为 {}x{}）", width, height)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ImageTooLarge {}
+
+impl From<ImageTooLarge> for std::io::Error {
+    fn from(err: ImageTooLarge) -> Self {
+        PixelSaysError::ImageTooLarge(err).into()
+    }
+}
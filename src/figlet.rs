@@ -0,0 +1,205 @@
+use crate::PixelSaysError;
+use std::collections::HashMap;
+use std::io::{Error, Result};
+use std::sync::Arc;
+
+/// FIGlet 标准中必须包含的德文字符，位于 ASCII 字符之后
+const GERMAN_CHARS: [u32; 7] = [196, 214, 220, 228, 246, 252, 223];
+
+/// FIGlet 字体（`.flf` 文件），用于把消息绘制成由字符拼成的大字
+///
+/// 字形按完整宽度拼接，不做字距调整和重叠（smushing）。字体中没有的字符用 `?`
+/// 的字形代替。克隆的开销很小，所有副本共享同一份字形数据。
+#[derive(Debug, Clone)]
+pub struct FigletFont {
+    height: usize,
+    glyphs: Arc<HashMap<char, Vec<String>>>,
+}
+
+impl FigletFont {
+    /// 解析 `.flf` 字体文件的内容
+    pub fn parse(data: &str) -> Result<Self> {
+        let invalid = |reason| Error::from(PixelSaysError::InvalidFont(reason));
+        let mut lines = data.lines().map(|line| line.trim_end_matches('\r'));
+
+        let header = lines.next().unwrap_or_default();
+        let hardblank = header
+            .strip_prefix("flf2a")
+            .and_then(|rest| rest.chars().next())
+            .ok_or_else(|| invalid("缺少 flf2a 文件头"))?;
+        let fields: Vec<i64> = header
+            .split_whitespace()
+            .skip(1)
+            .map_while(|field| field.parse().ok())
+            .collect();
+        // 依次是高度、基线、最大行长、旧的排版方式、注释行数，之后的字段可以省略
+        let field = |i: usize| fields.get(i).and_then(|&value| usize::try_from(value).ok());
+        let height = field(0)
+            .filter(|&height| height > 0)
+            .ok_or_else(|| invalid("文件头无效"))?;
+        let comment_lines = field(4).unwrap_or(0);
+        let mut lines = lines.skip(comment_lines).peekable();
+
+        let read_glyph = |lines: &mut dyn Iterator<Item = &str>| -> Result<Vec<String>> {
+            (0..height)
+                .map(|_| {
+                    let line = lines.next().ok_or_else(|| invalid("字形不完整"))?;
+                    // 每行以结束符结尾，最后一行有两个
+                    let endmark = line.chars().last().unwrap_or(' ');
+                    let row = line.trim_end_matches(endmark);
+                    Ok(row.replace(hardblank, " "))
+                })
+                .collect()
+        };
+
+        let mut glyphs = HashMap::new();
+        // ASCII 字符和德文字符按顺序排列；不完整的字体可以在任何一个字形之后结束，
+        // 但只有包含全部字符时才能继续定义其他字符
+        for code in (32..=126).chain(GERMAN_CHARS) {
+            if lines.peek().is_none() {
+                break;
+            }
+            if let Some(c) = char::from_u32(code) {
+                glyphs.insert(c, read_glyph(&mut lines)?);
+            }
+        }
+        // 之后是以字符编码开头的字形
+        while let Some(tag) = lines.next() {
+            if tag.trim().is_empty() {
+                continue;
+            }
+            let glyph = read_glyph(&mut lines)?;
+            if let Some(c) = parse_code(tag).and_then(char::from_u32) {
+                glyphs.insert(c, glyph);
+            }
+        }
+        if glyphs.is_empty() {
+            return Err(invalid("没有字形"));
+        }
+
+        Ok(FigletFont {
+            height,
+            glyphs: Arc::new(glyphs),
+        })
+    }
+
+    /// 内置的 5 行高的字体，由 `#` 组成，大小写字母使用相同的字形
+    #[cfg(feature = "figlet")]
+    pub fn banner() -> Self {
+        FigletFont::parse(include_str!("../assets/fonts/banner.flf")).expect("内置的字体是有效的")
+    }
+
+    /// 每个字形的行数
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// 把一行文字绘制成 `height` 行大字，去掉每行末尾的空白
+    pub fn render(&self, text: &str) -> Vec<String> {
+        let mut rows = vec![String::new(); self.height];
+        let glyphs = text
+            .chars()
+            .filter_map(|c| self.glyphs.get(&c).or_else(|| self.glyphs.get(&'?')));
+        for glyph in glyphs {
+            for (row, line) in rows.iter_mut().zip(glyph) {
+                row.push_str(line);
+            }
+        }
+        for row in &mut rows {
+            row.truncate(row.trim_end().len());
+        }
+        rows
+    }
+
+    /// 把文字绘制成大字后的宽度
+    fn width(&self, text: &str) -> usize {
+        self.render(text)
+            .iter()
+            .map(|row| crate::text::display_width(row))
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// 字形前的字符编码，可以是十进制、`0x` 开头的十六进制或 `0` 开头的八进制
+fn parse_code(tag: &str) -> Option<u32> {
+    let code = tag.split_whitespace().next()?;
+    if let Some(hex) = code.strip_prefix("0x").or_else(|| code.strip_prefix("0X")) {
+        u32::from_str_radix(hex, 16).ok()
+    } else if code.len() > 1 && code.starts_with('0') {
+        u32::from_str_radix(&code[1..], 8).ok()
+    } else {
+        code.parse().ok()
+    }
+}
+
+/// 把消息绘制成大字：按单词分组，让每组的宽度不超过 `max_width`，
+/// 放不下的单个单词独占一组；组之间空一行
+pub(crate) fn banner_text(input: &str, font: &FigletFont, max_width: usize) -> String {
+    let mut groups: Vec<String> = Vec::new();
+    for line in input.split('\n') {
+        let mut current = String::new();
+        for word in line.split_whitespace() {
+            let candidate = if current.is_empty() {
+                word.to_string()
+            } else {
+                format!("{} {}", current, word)
+            };
+            if current.is_empty() || font.width(&candidate) <= max_width {
+                current = candidate;
+            } else {
+                groups.push(std::mem::replace(&mut current, word.to_string()));
+            }
+        }
+        groups.push(current);
+    }
+    groups
+        .iter()
+        .map(|group| font.render(group).join("\n"))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 每个字形都是 `#` 的两行字体，另外定义了 `☺`
+    fn test_font() -> String {
+        let mut font = String::from("flf2a$ 2 2 4 -1 1\ntiny test font\n");
+        for code in (32..=126).chain(GERMAN_CHARS) {
+            font.push_str(if code == 32 {
+                "$$@\n$$@@\n"
+            } else {
+                "#@\n#@@\n"
+            });
+        }
+        font.push_str("0x263A smiley\n:)@\n^^@@\n");
+        font
+    }
+
+    #[test]
+    fn test_parse_figlet_font() {
+        let font = FigletFont::parse(&test_font()).unwrap();
+        assert_eq!(font.height(), 2);
+        // 字体中没有的字符用 `?` 的字形代替
+        assert_eq!(font.render("! ☺€"), ["#  :)#", "#  ^^#"]);
+        assert!(FigletFont::parse("not a font").is_err());
+        assert!(FigletFont::parse("flf2a$ 2 2 4 -1 0\n$$@\n").is_err());
+    }
+
+    #[cfg(feature = "figlet")]
+    #[test]
+    fn test_banner_text() {
+        let font = FigletFont::banner();
+        assert_eq!(font.render("Hi"), font.render("HI"));
+        assert_eq!(
+            font.render("HI").join("\n"),
+            "#   # ###\n#   #  #\n#####  #\n#   #  #\n#   # ###"
+        );
+        // 放不下的单词换到下一组
+        let text = banner_text("HI HI", &font, 12);
+        assert_eq!(text.lines().count(), 11);
+        assert!(text.lines().all(|line| line.len() <= 12));
+    }
+}
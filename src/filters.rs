@@ -0,0 +1,701 @@
+use crate::color::{linear_to_srgb, luminance, srgb_to_linear};
+use crate::dither::dither;
+use crate::overlay::stamp_text;
+use crate::{
+    AutoLevels, Color, ColorBlindness, Dither, DropShadow, Luminance, RenderOptions, ShadeLevels,
+    Threshold, WhiteBalance,
+};
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+use std::borrow::Cow;
+
+/// 在转换为终端文本之前对（已缩放的）图片做预处理
+///
+/// 不需要任何处理时原样返回，不会复制图片
+pub(crate) fn preprocess<'a>(
+    img: Cow<'a, DynamicImage>,
+    options: &RenderOptions,
+) -> Cow<'a, DynamicImage> {
+    if options.auto_levels == AutoLevels::Off
+        && options.shadow.is_none()
+        && options.color_blindness.is_none()
+        && options.white_balance.is_none()
+        && options.brightness == 0.0
+        && options.contrast == 1.0
+        && options.saturation == 1.0
+        && options.hue_rotate == 0.0
+        && options.tint.is_none()
+        && options.posterize.is_none()
+        && options.overlay.is_none()
+        && options.dither == Dither::Off
+        && options.palette.is_none()
+        && options.alpha_blend.is_none()
+        && alpha_resolved(&img, options.alpha_threshold)
+    {
+        return img;
+    }
+
+    let mut img = img.into_owned().into_rgba8();
+    resolve_alpha(&mut img, options.alpha_threshold, options.alpha_blend);
+    match options.auto_levels {
+        AutoLevels::Off => {}
+        AutoLevels::Stretch => stretch_levels(&mut img),
+        AutoLevels::Equalize => equalize_histogram(&mut img),
+    }
+    if options.brightness != 0.0 || options.contrast != 1.0 || options.saturation != 1.0 {
+        adjust_colors(&mut img, options);
+    }
+    if options.hue_rotate != 0.0 {
+        rotate_hue(&mut img, options.hue_rotate);
+    }
+    if let Some(color) = options.tint {
+        tint(&mut img, color);
+    }
+    if let Some(balance) = options.white_balance {
+        adjust_white_balance(&mut img, balance);
+    }
+    if let Some(kind) = options.color_blindness {
+        simulate_color_blindness(&mut img, kind);
+    }
+    if let Some(shadow) = options.shadow {
+        img = drop_shadow(&img, shadow);
+    }
+    if let Some(overlay) = &options.overlay {
+        stamp_text(&mut img, overlay);
+    }
+    // 最后量化，之前的调整不会再产生不在色阶上的颜色
+    if let Some(levels) = options.posterize {
+        posterize(&mut img, levels);
+    }
+    dither(&mut img, options);
+    Cow::Owned(DynamicImage::ImageRgba8(img))
+}
+
+/// 透明度是否已经只有 0 和 255，按 `threshold` 解析不会改变任何像素
+///
+/// 阈值为 0 时完全透明的像素也会变为不透明，因此不算已解析
+fn alpha_resolved(img: &DynamicImage, threshold: u8) -> bool {
+    if !img.color().has_alpha() {
+        return true;
+    }
+    threshold > 0
+        && img
+            .pixels()
+            .all(|(_, _, pixel)| matches!(pixel[3], 0 | 255))
+}
+
+/// 把透明度低于 `threshold` 的像素变为完全透明，其余像素变为完全不透明；
+/// 设置了 `blend` 时半透明的像素先与它混合
+fn resolve_alpha(img: &mut RgbaImage, threshold: u8, blend: Option<Color>) {
+    for pixel in img.pixels_mut() {
+        let alpha = pixel[3];
+        if alpha < threshold {
+            pixel[3] = 0;
+            continue;
+        }
+        if let Some(background) = blend {
+            let mix = |c: u8, b: u8| {
+                ((c as u32 * alpha as u32 + b as u32 * (255 - alpha as u32) + 127) / 255) as u8
+            };
+            pixel[0] = mix(pixel[0], background.r);
+            pixel[1] = mix(pixel[1], background.g);
+            pixel[2] = mix(pixel[2], background.b);
+        }
+        pixel[3] = 255;
+    }
+}
+
+/// 模拟色觉缺陷（Machado 等人 2009 年的模型，严重程度为 1.0），在线性 RGB 空间中计算
+fn simulate_color_blindness(img: &mut RgbaImage, kind: ColorBlindness) {
+    let matrix: [[f32; 3]; 3] = match kind {
+        ColorBlindness::Protanopia => [
+            [0.152286, 1.052583, -0.204868],
+            [0.114503, 0.786281, 0.099216],
+            [-0.003882, -0.048116, 1.051998],
+        ],
+        ColorBlindness::Deuteranopia => [
+            [0.367322, 0.860646, -0.227968],
+            [0.280085, 0.672501, 0.047413],
+            [-0.011820, 0.042940, 0.968881],
+        ],
+        ColorBlindness::Tritanopia => [
+            [1.255528, -0.076749, -0.178779],
+            [-0.078411, 0.930809, 0.147602],
+            [0.004733, 0.691367, 0.303900],
+        ],
+    };
+
+    for pixel in img.pixels_mut() {
+        let rgb = [0, 1, 2].map(|i| srgb_to_linear(pixel[i]));
+        for (channel, row) in pixel.0[..3].iter_mut().zip(matrix) {
+            *channel = linear_to_srgb(row[0] * rgb[0] + row[1] * rgb[1] + row[2] * rgb[2]);
+        }
+    }
+}
+
+/// 依次调整饱和度、对比度和亮度，在 sRGB 编码的分量上计算
+fn adjust_colors(img: &mut RgbaImage, options: &RenderOptions) {
+    let brightness = options.brightness.clamp(-1.0, 1.0);
+    let contrast = options.contrast.max(0.0);
+    let saturation = options.saturation.max(0.0);
+    for pixel in img.pixels_mut() {
+        let rgb = [0, 1, 2].map(|i| pixel[i] as f32 / 255.0);
+        let gray = 0.2126 * rgb[0] + 0.7152 * rgb[1] + 0.0722 * rgb[2];
+        for (channel, value) in pixel.0[..3].iter_mut().zip(rgb) {
+            let value = gray + (value - gray) * saturation;
+            let value = (value - 0.5) * contrast + 0.5 + brightness;
+            *channel = (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+        }
+    }
+}
+
+/// 保持亮度不变地旋转色相（与 CSS 的 `hue-rotate()` 相同的矩阵）
+fn rotate_hue(img: &mut RgbaImage, degrees: f32) {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let matrix = [
+        [
+            0.213 + cos * 0.787 - sin * 0.213,
+            0.715 - cos * 0.715 - sin * 0.715,
+            0.072 - cos * 0.072 + sin * 0.928,
+        ],
+        [
+            0.213 - cos * 0.213 + sin * 0.143,
+            0.715 + cos * 0.285 + sin * 0.140,
+            0.072 - cos * 0.072 - sin * 0.283,
+        ],
+        [
+            0.213 - cos * 0.213 - sin * 0.787,
+            0.715 - cos * 0.715 + sin * 0.715,
+            0.072 + cos * 0.928 + sin * 0.072,
+        ],
+    ];
+    for pixel in img.pixels_mut() {
+        let rgb = [0, 1, 2].map(|i| pixel[i] as f32);
+        for (channel, row) in pixel.0[..3].iter_mut().zip(matrix) {
+            let value = row[0] * rgb[0] + row[1] * rgb[1] + row[2] * rgb[2];
+            *channel = value.round().clamp(0.0, 255.0) as u8;
+        }
+    }
+}
+
+/// 按亮度重新着色：黑色到 `color` 再到白色的渐变，亮度与 `color` 相同的像素正好变为它
+fn tint(img: &mut RgbaImage, color: Color) {
+    let target = [color.r, color.g, color.b].map(|c| c as f32 / 255.0);
+    let target_gray = luminance(color.r, color.g, color.b, Luminance::Fast) as f32 / 255.0;
+    for pixel in img.pixels_mut() {
+        let gray = luminance(pixel[0], pixel[1], pixel[2], Luminance::Fast) as f32 / 255.0;
+        for (channel, target) in pixel.0[..3].iter_mut().zip(target) {
+            let value = if gray <= target_gray {
+                target * gray / target_gray.max(f32::EPSILON)
+            } else {
+                target + (1.0 - target) * (gray - target_gray) / (1.0 - target_gray)
+            };
+            *channel = (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+        }
+    }
+}
+
+/// 把每个通道吸附到均匀分布的 `levels` 级上
+fn posterize(img: &mut RgbaImage, levels: u8) {
+    let steps = levels.max(2) as f32 - 1.0;
+    for pixel in img.pixels_mut() {
+        for channel in &mut pixel.0[..3] {
+            *channel = ((*channel as f32 * steps / 255.0).round() * 255.0 / steps).round() as u8;
+        }
+    }
+}
+
+/// 按色温和色调调整各通道的增益，在线性 RGB 空间中计算
+fn adjust_white_balance(img: &mut RgbaImage, balance: WhiteBalance) {
+    let target = blackbody(balance.temperature);
+    let neutral = blackbody(6500);
+    let tint = balance.tint.clamp(-1.0, 1.0);
+    // 品红是绿色的补色：正的偏移减弱绿色，负的偏移增强绿色
+    let green = 1.0 - 0.3 * tint;
+    let gains = [
+        target[0] / neutral[0],
+        target[1] / neutral[1] * green,
+        target[2] / neutral[2],
+    ];
+    // 保持整体亮度不变，只改变冷暖
+    let luma = 0.2126 * gains[0] + 0.7152 * gains[1] + 0.0722 * gains[2];
+    let gains = gains.map(|gain| gain / luma);
+
+    for pixel in img.pixels_mut() {
+        for (channel, gain) in pixel.0[..3].iter_mut().zip(gains) {
+            *channel = linear_to_srgb(srgb_to_linear(*channel) * gain);
+        }
+    }
+}
+
+/// 黑体辐射在某一色温下的线性 RGB 颜色（Tanner Helland 的近似公式）
+fn blackbody(kelvin: u32) -> [f32; 3] {
+    let t = kelvin.clamp(1000, 40000) as f32 / 100.0;
+    let red = if t <= 66.0 {
+        255.0
+    } else {
+        329.69873 * (t - 60.0).powf(-0.13320476)
+    };
+    let green = if t <= 66.0 {
+        99.4708 * t.ln() - 161.11957
+    } else {
+        288.12216 * (t - 60.0).powf(-0.07551485)
+    };
+    let blue = if t >= 66.0 {
+        255.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        138.51773 * (t - 10.0).ln() - 305.0448
+    };
+    // 公式给出的是 sRGB 编码值，避免除以零时保留一个很小的下限
+    [red, green, blue].map(|c| srgb_to_linear(c.clamp(1.0, 255.0) as u8))
+}
+
+/// 在图片下方合成偏移的阴影轮廓，画布会向偏移方向扩大
+fn drop_shadow(img: &RgbaImage, shadow: DropShadow) -> RgbaImage {
+    // 偏移超过图片的大小时阴影已经和图片分开，限制偏移以免画布过大
+    let clamp = |offset: i32, size: u32| {
+        let size = i32::try_from(size).unwrap_or(i32::MAX);
+        offset.clamp(-size, size)
+    };
+    let (dx, dy) = (
+        clamp(shadow.offset.0, img.width()),
+        clamp(shadow.offset.1, img.height()),
+    );
+    let width = img.width().saturating_add(dx.unsigned_abs());
+    let height = img.height().saturating_add(dy.unsigned_abs());
+    // 偏移为负时，原图需要向右（下）移动，为阴影腾出位置
+    let (sprite_x, sprite_y) = (
+        dx.saturating_neg().max(0) as u32,
+        dy.saturating_neg().max(0) as u32,
+    );
+    let (shadow_x, shadow_y) = (dx.max(0) as u32, dy.max(0) as u32);
+
+    let mut canvas = RgbaImage::new(width, height);
+    let shadow_pixel = Rgba([shadow.color.r, shadow.color.g, shadow.color.b, 255]);
+    for (x, y, pixel) in img.enumerate_pixels() {
+        if is_visible(pixel) {
+            canvas.put_pixel(x + shadow_x, y + shadow_y, shadow_pixel);
+        }
+    }
+    for (x, y, pixel) in img.enumerate_pixels() {
+        if is_visible(pixel) {
+            canvas.put_pixel(x + sprite_x, y + sprite_y, *pixel);
+        }
+    }
+    canvas
+}
+
+/// 像素是否可见（不透明）
+///
+/// `preprocess` 总会按 `alpha_threshold` 把透明度解析为 0 或 255，所以对预处理过的图片
+/// 这里的固定阈值与用户设置的阈值等价；所有“透明像素不显示”的判断都应该经过这里
+pub(crate) fn is_visible(pixel: &Rgba<u8>) -> bool {
+    pixel[3] >= 128
+}
+
+/// 不透明像素的亮度直方图
+fn histogram<I>(img: &I, model: Luminance) -> [u32; 256]
+where
+    I: GenericImageView<Pixel = Rgba<u8>>,
+{
+    let mut histogram = [0u32; 256];
+    for (_, _, pixel) in img.pixels().filter(|(_, _, p)| is_visible(p)) {
+        histogram[luminance(pixel[0], pixel[1], pixel[2], model) as usize] += 1;
+    }
+    histogram
+}
+
+/// 图片实际使用的亮度阈值，亮度高于它的像素算作亮
+pub(crate) fn resolve_threshold<I>(img: &I, threshold: Threshold, model: Luminance) -> u8
+where
+    I: GenericImageView<Pixel = Rgba<u8>>,
+{
+    match threshold {
+        Threshold::Absolute(value) => value,
+        Threshold::Percentile(percent) => {
+            let histogram = histogram(img, model);
+            let total: u32 = histogram.iter().sum();
+            // 亮度不超过阈值的像素数至少达到这个数目
+            let wanted = (total as u64 * percent.min(100) as u64).div_ceil(100) as u32;
+            let mut count = 0;
+            for (value, &n) in histogram.iter().enumerate() {
+                count += n;
+                if count >= wanted {
+                    return value as u8;
+                }
+            }
+            u8::MAX
+        }
+        Threshold::Auto => otsu_threshold(&histogram(img, model)),
+    }
+}
+
+/// 明暗模式中每个亮度（0–255）对应的级别（0–4）
+pub(crate) fn shade_table<I>(img: &I, levels: ShadeLevels, model: Luminance) -> [u8; 256]
+where
+    I: GenericImageView<Pixel = Rgba<u8>>,
+{
+    match levels {
+        ShadeLevels::Even => std::array::from_fn(|value| (value * 5 / 256) as u8),
+        ShadeLevels::Histogram => {
+            let histogram = histogram(img, model);
+            let total: u32 = histogram.iter().sum();
+            let mut below = 0;
+            std::array::from_fn(|value| {
+                // 按累积分布的中点分级，只有一种亮度的图片落在中间一级
+                let middle = below as f32 + histogram[value] as f32 / 2.0;
+                below += histogram[value];
+                (middle * 5.0 / total.max(1) as f32).min(4.0) as u8
+            })
+        }
+        ShadeLevels::Custom(bounds) => std::array::from_fn(|value| {
+            bounds
+                .iter()
+                .filter(|&&bound| value > bound as usize)
+                .count() as u8
+        }),
+    }
+}
+
+/// 大津法：选择让亮暗两类之间方差最大的阈值，阈值本身归入暗的一类
+fn otsu_threshold(histogram: &[u32; 256]) -> u8 {
+    let total: f64 = histogram.iter().map(|&n| n as f64).sum();
+    let sum: f64 = histogram
+        .iter()
+        .enumerate()
+        .map(|(value, &n)| value as f64 * n as f64)
+        .sum();
+
+    let (mut best, mut best_variance) = (0, -1.0);
+    let (mut dark_count, mut dark_sum) = (0.0, 0.0);
+    for (value, &n) in histogram.iter().enumerate() {
+        dark_count += n as f64;
+        dark_sum += value as f64 * n as f64;
+        let light_count = total - dark_count;
+        if dark_count == 0.0 || light_count == 0.0 {
+            continue;
+        }
+        let dark_mean = dark_sum / dark_count;
+        let light_mean = (sum - dark_sum) / light_count;
+        let variance = dark_count * light_count * (dark_mean - light_mean).powi(2);
+        if variance > best_variance {
+            (best, best_variance) = (value, variance);
+        }
+    }
+    best as u8
+}
+
+/// 把亮度范围线性拉伸到 0–255；按编码后的分量缩放，亮度也按编码后的值计算
+fn stretch_levels(img: &mut RgbaImage) {
+    let histogram = histogram(img, Luminance::Fast);
+    let low = histogram.iter().position(|&n| n > 0);
+    let high = histogram.iter().rposition(|&n| n > 0);
+    let (Some(low), Some(high)) = (low, high) else {
+        return;
+    };
+    if low >= high {
+        return;
+    }
+
+    let scale = 255.0 / (high - low) as f32;
+    for pixel in img.pixels_mut().filter(|p| is_visible(p)) {
+        for channel in &mut pixel.0[..3] {
+            *channel = ((*channel as f32 - low as f32) * scale).clamp(0.0, 255.0) as u8;
+        }
+    }
+}
+
+/// 直方图均衡化：按亮度的累积分布重新映射，并按比例缩放 RGB 以保留色相
+fn equalize_histogram(img: &mut RgbaImage) {
+    let histogram = histogram(img, Luminance::Fast);
+    let total: u32 = histogram.iter().sum();
+    if total == 0 {
+        return;
+    }
+
+    let mut mapping = [0u8; 256];
+    let mut cumulative = 0;
+    let first = histogram.iter().copied().find(|&n| n > 0).unwrap_or(0);
+    for (level, &count) in histogram.iter().enumerate() {
+        cumulative += count;
+        mapping[level] = if total == first {
+            level as u8
+        } else {
+            (cumulative.saturating_sub(first) as f32 * 255.0 / (total - first) as f32).round() as u8
+        };
+    }
+
+    for pixel in img.pixels_mut().filter(|p| is_visible(p)) {
+        let level = luminance(pixel[0], pixel[1], pixel[2], Luminance::Fast);
+        let target = mapping[level as usize];
+        if level == 0 {
+            pixel.0[..3].fill(target);
+            continue;
+        }
+        let scale = target as f32 / level as f32;
+        for channel in &mut pixel.0[..3] {
+            *channel = (*channel as f32 * scale).clamp(0.0, 255.0) as u8;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gray_ramp(levels: &[u8]) -> RgbaImage {
+        let mut img = RgbaImage::new(levels.len() as u32, 1);
+        for (x, &level) in levels.iter().enumerate() {
+            img.put_pixel(x as u32, 0, Rgba([level, level, level, 255]));
+        }
+        img
+    }
+
+    #[test]
+    fn test_auto_levels() {
+        let mut stretched = gray_ramp(&[100, 120, 140]);
+        stretch_levels(&mut stretched);
+        let levels: Vec<u8> = stretched.pixels().map(|p| p[0]).collect();
+        assert_eq!(levels, [0, 127, 255]);
+
+        let mut equalized = gray_ramp(&[100, 101, 102, 103]);
+        equalize_histogram(&mut equalized);
+        assert_eq!(equalized.get_pixel(0, 0)[0], 0);
+        assert!(equalized.get_pixel(3, 0)[0] >= 250);
+    }
+
+    #[test]
+    fn test_color_blindness_simulation() {
+        // 红色和绿色在红色盲模拟下变得难以区分
+        let mut img = RgbaImage::new(2, 1);
+        img.put_pixel(0, 0, Rgba([200, 60, 60, 255]));
+        img.put_pixel(1, 0, Rgba([90, 120, 40, 255]));
+        simulate_color_blindness(&mut img, ColorBlindness::Protanopia);
+
+        let (red, green) = (img.get_pixel(0, 0), img.get_pixel(1, 0));
+        let distance: i32 = (0..3)
+            .map(|i| (red[i] as i32 - green[i] as i32).abs())
+            .sum();
+        assert!(distance < 80, "{:?} vs {:?}", red, green);
+
+        // 灰色不受影响
+        let mut gray = RgbaImage::from_pixel(1, 1, Rgba([128, 128, 128, 255]));
+        simulate_color_blindness(&mut gray, ColorBlindness::Deuteranopia);
+        assert!(gray.get_pixel(0, 0).0[..3]
+            .iter()
+            .all(|&c| c.abs_diff(128) <= 1));
+    }
+
+    #[test]
+    fn test_white_balance() {
+        let gray = Rgba([128, 128, 128, 255]);
+        let adjust = |temperature| {
+            let mut img = RgbaImage::from_pixel(1, 1, gray);
+            let balance = WhiteBalance {
+                temperature,
+                tint: 0.0,
+            };
+            adjust_white_balance(&mut img, balance);
+            *img.get_pixel(0, 0)
+        };
+
+        let neutral = adjust(6500);
+        assert!(neutral.0[..3].iter().all(|&c| c.abs_diff(128) <= 1));
+        let warm = adjust(3000);
+        assert!(warm[0] > warm[2] + 20, "{:?}", warm);
+        let cool = adjust(10000);
+        assert!(cool[2] > cool[0], "{:?}", cool);
+    }
+
+    #[test]
+    fn test_alpha_blend() {
+        let img = RgbaImage::from_fn(3, 1, |x, _| Rgba([255, 0, 0, [0, 60, 128][x as usize]]));
+        let mut options = RenderOptions::default();
+        let resolved = |options: &RenderOptions| {
+            let img = preprocess(Cow::Owned(DynamicImage::ImageRgba8(img.clone())), options)
+                .into_owned()
+                .into_rgba8();
+            img.pixels().map(|pixel| pixel.0).collect::<Vec<_>>()
+        };
+
+        // 默认设置下半透明的像素同样会被解析
+        assert_eq!(
+            resolved(&options),
+            [[255, 0, 0, 0], [255, 0, 0, 0], [255, 0, 0, 255]]
+        );
+        options.alpha_threshold = 50;
+        assert_eq!(
+            resolved(&options),
+            [[255, 0, 0, 0], [255, 0, 0, 255], [255, 0, 0, 255]]
+        );
+        options.alpha_blend = Some(Color::rgb(0, 0, 255));
+        assert_eq!(
+            resolved(&options),
+            [[255, 0, 0, 0], [60, 0, 195, 255], [128, 0, 127, 255]]
+        );
+
+        // 透明度已经只有 0 和 255 时不需要复制图片
+        let opaque = DynamicImage::ImageRgba8(RgbaImage::from_pixel(2, 2, Rgba([1, 2, 3, 255])));
+        let options = RenderOptions::default();
+        assert!(matches!(
+            preprocess(Cow::Borrowed(&opaque), &options),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn test_adjust_colors() {
+        let mut img = RgbaImage::from_pixel(1, 1, Rgba([200, 100, 50, 255]));
+        let options = RenderOptions {
+            saturation: 0.0,
+            ..RenderOptions::default()
+        };
+        adjust_colors(&mut img, &options);
+        let [r, g, b, _] = img.get_pixel(0, 0).0;
+        assert!(r == g && g == b);
+
+        let mut img = gray_ramp(&[64, 128, 192]);
+        let options = RenderOptions {
+            contrast: 2.0,
+            brightness: 0.1,
+            ..RenderOptions::default()
+        };
+        adjust_colors(&mut img, &options);
+        let levels: Vec<u8> = img.pixels().map(|p| p[0]).collect();
+        assert_eq!(levels, [26, 154, 255]);
+    }
+
+    #[test]
+    fn test_hue_and_tint() {
+        let mut img = RgbaImage::from_pixel(1, 1, Rgba([255, 0, 0, 255]));
+        rotate_hue(&mut img, 360.0);
+        assert_eq!(img.get_pixel(0, 0).0, [255, 0, 0, 255]);
+        rotate_hue(&mut img, 120.0);
+        let [r, g, _, _] = img.get_pixel(0, 0).0;
+        assert!(g > r, "{:?}", img.get_pixel(0, 0));
+
+        // 黑白不变，中间调带上颜色
+        let mut img = gray_ramp(&[0, 100, 255]);
+        tint(&mut img, Color::rgb(200, 30, 30));
+        assert_eq!(img.get_pixel(0, 0).0, [0, 0, 0, 255]);
+        assert_eq!(img.get_pixel(2, 0).0, [255, 255, 255, 255]);
+        let [r, g, b, _] = img.get_pixel(1, 0).0;
+        assert!(r > g && g == b, "{:?}", img.get_pixel(1, 0));
+    }
+
+    #[test]
+    fn test_shade_levels() {
+        let img = RgbaImage::from_fn(10, 1, |x, _| {
+            let value = 100 + x as u8 * 5;
+            Rgba([value, value, value, 255])
+        });
+        let levels = |levels| {
+            let table = shade_table(&img, levels, Luminance::Linear);
+            [0, 100, 120, 145, 255].map(|value| table[value])
+        };
+        assert_eq!(levels(ShadeLevels::Even), [0, 1, 2, 2, 4]);
+        // 图片中的亮度平均分布在五级里
+        assert_eq!(levels(ShadeLevels::Histogram), [0, 0, 2, 4, 4]);
+        assert_eq!(
+            levels(ShadeLevels::Custom([99, 100, 200, 250])),
+            [0, 1, 2, 2, 4]
+        );
+
+        // 只有一种亮度时使用中间一级
+        let flat = RgbaImage::from_pixel(2, 2, Rgba([30, 30, 30, 255]));
+        assert_eq!(
+            shade_table(&flat, ShadeLevels::Histogram, Luminance::Linear)[30],
+            2
+        );
+    }
+
+    #[test]
+    fn test_posterize() {
+        let mut img = gray_ramp(&[0, 60, 100, 200, 255]);
+        posterize(&mut img, 3);
+        let levels: Vec<u8> = img.pixels().map(|p| p[0]).collect();
+        assert_eq!(levels, [0, 0, 128, 255, 255]);
+    }
+
+    #[test]
+    fn test_posterize_after_white_balance() {
+        let img = DynamicImage::ImageRgba8(gray_ramp(&[0, 60, 100, 200, 255]));
+        let options = RenderOptions {
+            posterize: Some(3),
+            white_balance: Some(WhiteBalance {
+                temperature: 3000,
+                tint: 0.5,
+            }),
+            ..RenderOptions::default()
+        };
+        let img = preprocess(Cow::Owned(img), &options)
+            .into_owned()
+            .into_rgba8();
+        for pixel in img.pixels() {
+            assert!(
+                pixel.0[..3].iter().all(|c| [0, 128, 255].contains(c)),
+                "{:?}",
+                pixel
+            );
+        }
+    }
+
+    #[test]
+    fn test_threshold() {
+        let img = RgbaImage::from_fn(10, 1, |x, _| {
+            let value = x as u8 * 10;
+            Rgba([value, value, value, 255])
+        });
+
+        assert_eq!(
+            resolve_threshold(&img, Threshold::Absolute(77), Luminance::Fast),
+            77
+        );
+        assert_eq!(
+            resolve_threshold(&img, Threshold::Percentile(50), Luminance::Linear),
+            40
+        );
+        assert_eq!(
+            resolve_threshold(&img, Threshold::Percentile(100), Luminance::Linear),
+            90
+        );
+        assert_eq!(
+            resolve_threshold(&img, Threshold::Percentile(0), Luminance::Linear),
+            0
+        );
+
+        // 两团亮度之间的阈值
+        let img = RgbaImage::from_fn(8, 1, |x, _| {
+            let value = [10, 20, 30, 40, 200, 210, 220, 230][x as usize];
+            Rgba([value, value, value, 255])
+        });
+        let threshold = resolve_threshold(&img, Threshold::Auto, Luminance::Linear);
+        assert!((40..200).contains(&threshold), "{}", threshold);
+    }
+
+    #[test]
+    fn test_drop_shadow() {
+        let sprite = RgbaImage::from_pixel(2, 2, Rgba([255, 255, 255, 255]));
+        let shadow = DropShadow {
+            offset: (1, 1),
+            color: crate::Color::rgb(10, 10, 10),
+        };
+        let result = drop_shadow(&sprite, shadow);
+
+        assert_eq!(result.dimensions(), (3, 3));
+        assert_eq!(result.get_pixel(0, 0), &Rgba([255, 255, 255, 255]));
+        assert_eq!(result.get_pixel(2, 2), &Rgba([10, 10, 10, 255]));
+        assert_eq!(result.get_pixel(2, 0)[3], 0);
+
+        // 过大的偏移被限制在图片的大小以内
+        let shadow = DropShadow {
+            offset: (i32::MIN, i32::MAX),
+            ..shadow
+        };
+        let result = drop_shadow(&sprite, shadow);
+        assert_eq!(result.dimensions(), (4, 4));
+        assert_eq!(result.get_pixel(0, 2), &Rgba([10, 10, 10, 255]));
+        assert_eq!(result.get_pixel(2, 0), &Rgba([255, 255, 255, 255]));
+    }
+}
@@ -0,0 +1,91 @@
+use crate::convert::{pixel_rows_per_line, pixels_in_columns};
+use crate::{terminal_size, BubblePosition, RenderOptions};
+
+/// 消息框在图片上方或下方时至少占用的行数：上下边框、一行文字和两行连接线，不包括内边距
+const MIN_BUBBLE_ROWS: usize = 5;
+
+/// 让输出适应终端大小的方式，见 [`FitStrategy::apply`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FitStrategy {
+    /// 只让消息文本的宽度适应终端的列数
+    Text,
+    /// 消息文本和图片的宽度都适应终端的列数，图片的高度仍由 `max_cells` 限制
+    Width,
+    /// 同时适应终端的列数和行数；消息框在图片上方或下方时，图片的高度为终端的行数减去
+    /// 只有一行文字的消息框和连接线占用的行数
+    Screen,
+}
+
+impl FitStrategy {
+    /// 按照终端大小 `(列数, 行数)` 修改 `max_width` 和 `max_cells`，行数为 0 表示未知
+    ///
+    /// 图片只会缩小，不会放大。左右并排时图片和消息框各自占满终端的宽度，仍然可能折行。
+    pub fn apply(self, options: &mut RenderOptions, (columns, rows): (usize, usize)) {
+        let columns = columns.saturating_sub(options.margin);
+        // 消息框两侧的边框和内边距
+        let border = 2 + 2 * options.padding.horizontal;
+        options.max_width = columns.saturating_sub(border).max(1);
+        if self == FitStrategy::Text {
+            return;
+        }
+
+        // 边框在图片两侧和上下各占一格
+        let frame = if options.frame.is_some() { 2 } else { 0 };
+        let max_width = pixels_in_columns(columns.saturating_sub(frame), options) as u32;
+        let max_height = match self {
+            FitStrategy::Screen if rows > 0 => {
+                let bubble = match options.position {
+                    BubblePosition::Above | BubblePosition::Below => {
+                        MIN_BUBBLE_ROWS + 2 * options.padding.vertical
+                    }
+                    BubblePosition::Left | BubblePosition::Right => 0,
+                };
+                let rows = rows.saturating_sub(frame + bubble);
+                rows as u32 * pixel_rows_per_line(options)
+            }
+            _ => options.max_cells.map_or(u32::MAX, |(_, height)| height),
+        };
+        options.max_cells = Some((max_width.max(1), max_height.max(1)));
+    }
+
+    /// 按照当前终端的大小修改选项，无法获取终端大小时保持不变
+    pub fn fit_terminal(self, options: &mut RenderOptions) {
+        if let Some(size) = terminal_size() {
+            self.apply(options, size);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PixelMode, PixelWidth};
+
+    #[test]
+    fn test_fit_strategy() {
+        let mut options = RenderOptions::default();
+        FitStrategy::Text.apply(&mut options, (100, 30));
+        assert_eq!(options.max_width, 96);
+        assert_eq!(options.max_cells, RenderOptions::default().max_cells);
+
+        FitStrategy::Width.apply(&mut options, (100, 30));
+        assert_eq!(options.max_cells, Some((50, 80)));
+
+        let mut options = RenderOptions {
+            mode: PixelMode::Braille,
+            pixel_width: PixelWidth::Single,
+            ..RenderOptions::default()
+        };
+        // 消息框和连接线至少占用 5 行
+        FitStrategy::Screen.apply(&mut options, (200, 30));
+        assert_eq!(options.max_cells, Some((400, 100)));
+        // 行数未知时保持原来的高度限制
+        FitStrategy::Screen.apply(&mut options, (200, 0));
+        assert_eq!(options.max_cells, Some((400, 100)));
+        // 左右并排时消息框不占用图片的高度
+        options.position = BubblePosition::Right;
+        FitStrategy::Screen.apply(&mut options, (200, 30));
+        assert_eq!(options.max_cells, Some((400, 120)));
+    }
+}
@@ -0,0 +1,230 @@
+use crate::text::{display_width, grapheme_width, text_width};
+use crate::AmbiguousWidth;
+use std::io::{Result, Write};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// 图片边框的样式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum FrameStyle {
+    /// `┌─┐│└┘`
+    #[default]
+    Single,
+    /// `╭─╮│╰╯`
+    Rounded,
+    /// `╔═╗║╚╝`
+    Double,
+    /// `+-+|++`
+    Ascii,
+}
+
+/// 围绕图片的装饰边框
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Frame {
+    /// 边框样式
+    pub style: FrameStyle,
+    /// 嵌在顶部边框中的标题，超出宽度的部分会被截断
+    pub title: Option<String>,
+}
+
+/// 边框使用的字符
+struct BorderChars {
+    top_left: char,
+    top_right: char,
+    bottom_left: char,
+    bottom_right: char,
+    horizontal: char,
+    vertical: char,
+    tee_left: char,
+    tee_right: char,
+}
+
+impl FrameStyle {
+    fn border(self) -> BorderChars {
+        let [top_left, top_right, bottom_left, bottom_right, horizontal, vertical, tee_left, tee_right] =
+            match self {
+                FrameStyle::Single => ['┌', '┐', '└', '┘', '─', '│', '├', '┤'],
+                FrameStyle::Rounded => ['╭', '╮', '╰', '╯', '─', '│', '├', '┤'],
+                FrameStyle::Double => ['╔', '╗', '╚', '╝', '═', '║', '╠', '╣'],
+                FrameStyle::Ascii => ['+', '+', '+', '+', '-', '|', '+', '+'],
+            };
+        BorderChars {
+            top_left,
+            top_right,
+            bottom_left,
+            bottom_right,
+            horizontal,
+            vertical,
+            tee_left,
+            tee_right,
+        }
+    }
+}
+
+/// 顶部边框，标题两侧各留一个空格
+fn top_border(frame: &Frame, width: usize) -> String {
+    let border = frame.style.border();
+    let mut top = String::new();
+    top.push(border.top_left);
+    let mut used = 0;
+    if let Some(title) = frame.title.as_deref().filter(|t| !t.is_empty()) {
+        if width >= 4 {
+            top.push(border.horizontal);
+            top.push(' ');
+            used = 2;
+            for grapheme in title.graphemes(true) {
+                let w = grapheme_width(grapheme, AmbiguousWidth::Narrow);
+                if used + w + 1 > width {
+                    break;
+                }
+                top.push_str(grapheme);
+                used += w;
+            }
+            top.push(' ');
+            used += 1;
+        }
+    }
+    top.extend(std::iter::repeat_n(border.horizontal, width - used));
+    top.push(border.top_right);
+    top
+}
+
+/// 水平边框线：`left`、`width` 个水平线字符、`right`
+fn rule(left: char, horizontal: char, right: char, width: usize) -> String {
+    let mut line = String::new();
+    line.push(left);
+    line.extend(std::iter::repeat_n(horizontal, width));
+    line.push(right);
+    line
+}
+
+/// 给已经转换好的图片文本加上边框
+///
+/// `art` 的每一行都是 `width` 列宽（转义序列不计入宽度）。
+pub(crate) fn write_framed<W>(art: &[u8], width: usize, frame: &Frame, mut writer: W) -> Result<()>
+where
+    W: Write,
+{
+    let border = frame.style.border();
+    writeln!(writer, "{}", top_border(frame, width))?;
+
+    for line in String::from_utf8_lossy(art).lines() {
+        let padding = width.saturating_sub(display_width(line));
+        writeln!(
+            writer,
+            "{}{}{:padding$}{}",
+            border.vertical, line, "", border.vertical
+        )?;
+    }
+
+    let bottom = rule(
+        border.bottom_left,
+        border.horizontal,
+        border.bottom_right,
+        width,
+    );
+    writeln!(writer, "{}", bottom)
+}
+
+/// 把图片和说明文字放进同一个边框，中间用横线隔开
+///
+/// 返回整个边框的宽度。
+pub(crate) fn write_enclosed<W>(
+    art: &[u8],
+    art_width: usize,
+    caption: &[&str],
+    ambiguous: AmbiguousWidth,
+    frame: &Frame,
+    mut writer: W,
+) -> Result<usize>
+where
+    W: Write,
+{
+    let border = frame.style.border();
+    let caption_width = caption
+        .iter()
+        .map(|line| text_width(line, ambiguous))
+        .max()
+        .unwrap_or(0);
+    // 内容两侧各留一个空格
+    let width = art_width.max(caption_width) + 2;
+
+    let content = |writer: &mut W, line: &str, line_width: usize| {
+        let padding = (width - 2).saturating_sub(line_width);
+        writeln!(
+            writer,
+            "{} {}{:padding$} {}",
+            border.vertical, line, "", border.vertical
+        )
+    };
+
+    writeln!(writer, "{}", top_border(frame, width))?;
+    for line in String::from_utf8_lossy(art).lines() {
+        content(&mut writer, line, display_width(line))?;
+    }
+    if !caption.is_empty() {
+        // 图片与说明文字之间的分隔线
+        let separator = rule(border.tee_left, border.horizontal, border.tee_right, width);
+        writeln!(writer, "{}", separator)?;
+        for line in caption {
+            content(&mut writer, line, text_width(line, ambiguous))?;
+        }
+    }
+    let bottom = rule(
+        border.bottom_left,
+        border.horizontal,
+        border.bottom_right,
+        width,
+    );
+    writeln!(writer, "{}", bottom)?;
+    Ok(width + 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_framed_art_with_title() {
+        let frame = Frame {
+            style: FrameStyle::Rounded,
+            title: Some("Ferris".to_string()),
+        };
+        let mut output = Vec::new();
+        write_framed(
+            "████████████\n██  ██  ██  \n".as_bytes(),
+            12,
+            &frame,
+            &mut output,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "╭─ Ferris ───╮\n│████████████│\n│██  ██  ██  │\n╰────────────╯\n"
+        );
+    }
+
+    #[test]
+    fn test_enclosed_art_with_caption() {
+        let mut output = Vec::new();
+        let width = write_enclosed(
+            "████\n".as_bytes(),
+            4,
+            &["hello!"],
+            AmbiguousWidth::Narrow,
+            &Frame::default(),
+            &mut output,
+        )
+        .unwrap();
+
+        assert_eq!(width, 10);
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "┌────────┐\n│ ████   │\n├────────┤\n│ hello! │\n└────────┘\n"
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use crate::text::ansi_regex;
+use crate::Color;
+use regex::Regex;
+
+/// 在消息框中突出显示的文本，如 `ERROR` 或版本号
+///
+/// 在折行之后逐行匹配，跨行的文本不会被匹配；消息中保留的转义序列不参与匹配。
+/// 使用 [`OutputProfile::Ascii`](crate::OutputProfile::Ascii) 或
+/// [`OutputProfile::Plain`](crate::OutputProfile::Plain) 时不生效。
+#[derive(Debug, Clone)]
+pub struct Highlight {
+    /// 要匹配的正则表达式
+    pub pattern: Regex,
+    /// 前景色，`None` 表示不改变颜色
+    pub color: Option<Color>,
+    /// 是否加粗
+    pub bold: bool,
+}
+
+impl Highlight {
+    /// 用指定的颜色突出显示匹配 `pattern` 的文本
+    pub fn new(pattern: &str, color: Color) -> Result<Self, regex::Error> {
+        Ok(Highlight {
+            pattern: Regex::new(pattern)?,
+            color: Some(color),
+            bold: false,
+        })
+    }
+
+    /// 开始突出显示的转义序列
+    fn escape(&self) -> String {
+        let mut escape = String::new();
+        if self.bold {
+            escape.push_str("\x1b[1m");
+        }
+        if let Some(color) = self.color {
+            escape.push_str(&color.fg_escape());
+        }
+        escape
+    }
+}
+
+/// 按顺序应用每一条规则，已经带有样式的文本（包括之前的规则突出显示的部分）不会再被匹配
+pub(crate) fn highlight_line(line: &str, highlights: &[Highlight]) -> String {
+    let mut line = line.to_string();
+    for highlight in highlights {
+        let escape = highlight.escape();
+        let mut result = String::with_capacity(line.len());
+        let mut last = 0;
+        let mut styled = false;
+        // 只在转义序列之间的纯文本中匹配
+        let escapes = ansi_regex()
+            .find_iter(&line)
+            .map(|m| (m.start(), m.end()))
+            .chain([(line.len(), line.len())]);
+        for (start, end) in escapes {
+            let text = &line[last..start];
+            if styled {
+                result.push_str(text);
+            } else {
+                let replaced = highlight
+                    .pattern
+                    .replace_all(text, |caps: &regex::Captures| {
+                        format!("{}{}\x1b[0m", escape, &caps[0])
+                    });
+                result.push_str(&replaced);
+            }
+            let sequence = &line[start..end];
+            if !sequence.is_empty() {
+                styled = sequence != "\x1b[0m" && sequence != "\x1b[m";
+            }
+            result.push_str(sequence);
+            last = end;
+        }
+        line = result;
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_highlight_line() {
+        let error = Highlight::new("ERROR", Color::rgb(255, 0, 0)).unwrap();
+        let version = Highlight {
+            bold: true,
+            color: None,
+            ..Highlight::new(r"v\d+", Color::rgb(0, 0, 0)).unwrap()
+        };
+
+        assert_eq!(
+            highlight_line("ERROR in v2", &[error.clone(), version]),
+            "\x1b[38;2;255;0;0mERROR\x1b[0m in \x1b[1mv2\x1b[0m"
+        );
+        // 已经突出显示的文本不会再被匹配
+        let letters = Highlight::new("R", Color::rgb(0, 0, 255)).unwrap();
+        assert_eq!(
+            highlight_line("ERROR", &[error.clone(), letters]),
+            "\x1b[38;2;255;0;0mERROR\x1b[0m"
+        );
+        // 转义序列中的数字不参与匹配
+        let digits = Highlight::new(r"\d", Color::rgb(0, 255, 0)).unwrap();
+        assert_eq!(
+            highlight_line("ERROR", &[error, digits]),
+            "\x1b[38;2;255;0;0mERROR\x1b[0m"
+        );
+    }
+}
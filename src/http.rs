@@ -0,0 +1,74 @@
+use crate::{
+    say_from_bytes_with_options, DecodeLimits, ImageTooLarge, PixelSaysError, RenderOptions,
+    RenderReport,
+};
+use std::io::{Read, Result, Write};
+use std::time::Duration;
+
+/// 下载图片的超时时间，包括连接和读取
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 字符串是否为 `http://` 或 `https://` 地址
+pub fn is_url(source: &str) -> bool {
+    let scheme = source.split_once("://").map(|(scheme, _)| scheme);
+    scheme.is_some_and(|scheme| {
+        scheme.eq_ignore_ascii_case("http") || scheme.eq_ignore_ascii_case("https")
+    })
+}
+
+/// 下载网络图片并创建像素说话效果
+///
+/// 下载的数据不能超过 `options.limits.max_alloc` 字节，否则返回
+/// [`ImageTooLarge`]；连接或读取超过 10 秒视为失败。
+pub fn say_from_url<W>(
+    url: &str,
+    message: &str,
+    options: &RenderOptions,
+    writer: W,
+) -> Result<RenderReport>
+where
+    W: Write,
+{
+    let bytes = download_image(url, options.limits)?;
+    say_from_bytes_with_options(&bytes, message, options, writer)
+}
+
+/// 下载网络图片的原始数据，限制与 [`say_from_url`] 相同
+///
+/// 便于只下载一次，再交给 [`crate::load_image_from_bytes`]、
+/// [`crate::FrameSequence::from_bytes`] 等按数据读取图片的函数。
+pub fn download_image(url: &str, limits: DecodeLimits) -> Result<Vec<u8>> {
+    let agent = ureq::AgentBuilder::new().timeout(TIMEOUT).build();
+    let response = agent
+        .get(url)
+        .call()
+        .map_err(|e| PixelSaysError::Download(e.to_string()))?;
+
+    let limit = limits.max_alloc;
+    let mut bytes = Vec::new();
+    response
+        .into_reader()
+        .take(limit.map_or(u64::MAX, |limit| limit.saturating_add(1)))
+        .read_to_end(&mut bytes)?;
+    if limit.is_some_and(|limit| bytes.len() as u64 > limit) {
+        return Err(ImageTooLarge {
+            dimensions: None,
+            limits,
+        }
+        .into());
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_url() {
+        assert!(is_url("https://example.com/ferris.png"));
+        assert!(is_url("HTTP://example.com/ferris.png"));
+        assert!(!is_url("ftp://example.com/ferris.png"));
+        assert!(!is_url("images/ferris.png"));
+    }
+}
@@ -0,0 +1,84 @@
+use crate::{PixelSaysError, RenderOptions};
+use image::{DynamicImage, ImageFormat};
+use std::io::{Cursor, Result, Write};
+
+/// 以 iTerm2 内联图片协议（OSC 1337 `File=`）输出图片，结尾换行
+///
+/// 没有缩放的 `source` 无损编码为 PNG，由终端自己缩放；通过 `width`、`height` 指定
+/// 占用的字符格数，与缩放后的 `img` 字符输出的大小相同，适用于 iTerm2 和 WezTerm。
+pub(crate) fn write_iterm2<W>(
+    img: &DynamicImage,
+    source: &DynamicImage,
+    options: &RenderOptions,
+    mut writer: W,
+) -> Result<()>
+where
+    W: Write,
+{
+    let mut png = Vec::new();
+    source
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .map_err(PixelSaysError::ImageEncode)?;
+
+    let columns = img.width() as usize * options.pixel_width.columns();
+    writeln!(
+        writer,
+        "\x1b]1337;File=inline=1;size={};width={};height={};preserveAspectRatio=0:{}\x07",
+        png.len(),
+        columns,
+        img.height(),
+        base64(&png)
+    )
+}
+
+/// 标准 Base64 编码（带 `=` 填充）
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut result = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                result.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                result.push('=');
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbaImage;
+
+    #[test]
+    fn test_iterm2_inline_image() {
+        assert_eq!(base64(b"Man"), "TWFu");
+        assert_eq!(base64(b"Ma"), "TWE=");
+        assert_eq!(base64(b"M"), "TQ==");
+
+        let img = DynamicImage::ImageRgba8(RgbaImage::new(3, 2));
+        let source = DynamicImage::ImageRgba8(RgbaImage::new(30, 20));
+        let mut output = Vec::new();
+        write_iterm2(&img, &source, &RenderOptions::default(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("\x1b]1337;File=inline=1;size="));
+        assert!(output.contains(";width=6;height=2;"));
+        assert!(output.ends_with("\x07\n"));
+
+        // 发送的是没有缩放的原图
+        let mut png = Vec::new();
+        source
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+        assert!(output.contains(&format!("size={};", png.len())));
+        assert!(output.contains(&base64(&png)));
+    }
+}
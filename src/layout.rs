@@ -0,0 +1,169 @@
+use crate::text::text_width;
+use crate::AmbiguousWidth;
+
+/// 一块按行排列的文本，所有行按同一宽度对齐
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Block {
+    pub(crate) lines: Vec<String>,
+    pub(crate) width: usize,
+    /// 计算宽度时歧义宽度字符的宽度
+    ambiguous: AmbiguousWidth,
+}
+
+impl Block {
+    /// 由多行文本创建，宽度取最长的一行（忽略转义序列）
+    pub(crate) fn from_text(text: &str) -> Self {
+        Self::from_text_with(text, AmbiguousWidth::Narrow)
+    }
+
+    /// 同 [`Block::from_text`]，按照 `ambiguous` 计算歧义宽度字符
+    pub(crate) fn from_text_with(text: &str, ambiguous: AmbiguousWidth) -> Self {
+        let lines: Vec<String> = text.lines().map(str::to_string).collect();
+        let width = lines
+            .iter()
+            .map(|l| text_width(l, ambiguous))
+            .max()
+            .unwrap_or(0);
+        Block {
+            lines,
+            width,
+            ambiguous,
+        }
+    }
+
+    pub(crate) fn height(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// 把另一块文本接在下方
+    pub(crate) fn push_below(&mut self, other: Block) {
+        self.width = self.width.max(other.width);
+        self.lines.extend(other.lines);
+    }
+
+    /// 第 `row` 行补齐到块宽度后的内容，超出高度的行为空白
+    fn padded_row(&self, row: Option<usize>) -> String {
+        let line = row
+            .and_then(|row| self.lines.get(row))
+            .map_or("", String::as_str);
+        let padding = self.width.saturating_sub(text_width(line, self.ambiguous));
+        format!("{}{:padding$}", line, "")
+    }
+}
+
+/// 把多个块左右并排，块之间间隔 `gap` 列
+///
+/// 较矮的块底部对齐；每行末尾的空白会被去掉。
+pub(crate) fn hstack(blocks: &[Block], gap: usize) -> String {
+    let height = blocks.iter().map(Block::height).max().unwrap_or(0);
+    let mut output = String::new();
+    for row in 0..height {
+        let mut line = String::new();
+        for (i, block) in blocks.iter().enumerate() {
+            if i > 0 {
+                line.push_str(&" ".repeat(gap));
+            }
+            let offset = height - block.height();
+            line.push_str(&block.padded_row(row.checked_sub(offset)));
+        }
+        output.push_str(line.trim_end_matches(' '));
+        output.push('\n');
+    }
+    output
+}
+
+/// 把图片和消息框左右并排，两者垂直居中对齐，`tail` 放在两者之间、
+/// 与消息框的第 `tail_row` 行对齐；`bubble_left` 为真时消息框在左侧
+pub(crate) fn beside(
+    art: Block,
+    bubble: Block,
+    tail_row: usize,
+    tail: &str,
+    bubble_left: bool,
+) -> String {
+    // hstack 按底部对齐，先把每一块补齐到相同的高度
+    let height = art.height().max(bubble.height());
+    let pad = |mut block: Block, top: usize| {
+        block
+            .lines
+            .splice(0..0, std::iter::repeat_n(String::new(), top));
+        block.lines.resize(height, String::new());
+        block
+    };
+    let offset = (height - bubble.height()) / 2;
+    let art_offset = (height - art.height()) / 2;
+    let connector = pad(Block::from_text(tail), offset + tail_row);
+    let (art, bubble) = (pad(art, art_offset), pad(bubble, offset));
+    if bubble_left {
+        hstack(&[bubble, connector, art], 1)
+    } else {
+        hstack(&[art, connector, bubble], 1)
+    }
+}
+
+/// 把多次渲染的结果按列排列，每行最多 `columns` 个，行与行之间空一行
+///
+/// 设置了 `max_width` 时，一行放不下就减少列数，至少保留一列。
+pub fn arrange_columns<S>(renders: &[S], columns: usize, max_width: Option<usize>) -> String
+where
+    S: AsRef<str>,
+{
+    const GAP: usize = 2;
+    let blocks: Vec<Block> = renders
+        .iter()
+        .map(|r| Block::from_text(r.as_ref()))
+        .collect();
+    let mut columns = columns.max(1);
+    while columns > 1 {
+        let fits = blocks.chunks(columns).all(|row| {
+            let width: usize = row.iter().map(|block| block.width).sum();
+            max_width.is_none_or(|max| width + GAP * (row.len() - 1) <= max)
+        });
+        if fits {
+            break;
+        }
+        columns -= 1;
+    }
+
+    blocks
+        .chunks(columns)
+        .map(|row| hstack(row, GAP))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hstack_alignment() {
+        let left = Block::from_text("ab\nc");
+        let right = Block::from_text("xyz");
+
+        assert_eq!(hstack(&[left, right], 1), "ab\nc  xyz\n");
+    }
+
+    #[test]
+    fn test_beside() {
+        let art = Block::from_text("##\n##\n##\n##");
+        let bubble = Block::from_text(" __\n< >\n --");
+        assert_eq!(
+            beside(art.clone(), bubble.clone(), 1, "<-", false),
+            "##     __\n## <- < >\n##     --\n##\n"
+        );
+        assert_eq!(
+            beside(art, bubble, 1, "->", true),
+            " __    ##\n< > -> ##\n --    ##\n       ##\n"
+        );
+    }
+
+    #[test]
+    fn test_arrange_columns() {
+        let renders = ["aa", "bb", "cc"];
+
+        assert_eq!(arrange_columns(&renders, 2, None), "aa  bb\n\ncc\n");
+        // 宽度不足时退回到一列
+        assert_eq!(arrange_columns(&renders, 3, Some(5)), "aa\n\nbb\n\ncc\n");
+    }
+}
@@ -1,22 +1,225 @@
-use image::{DynamicImage, GenericImageView};
-use regex::Regex;
+mod alt_text;
+mod animation;
+mod ans;
+mod bubble;
+mod builder;
+mod cells;
+mod color;
+mod compiled;
+mod conversation;
+mod convert;
+mod custom;
+mod dither;
+mod env;
+mod error;
+mod figlet;
+mod filters;
+mod fit;
+mod frame;
+mod highlight;
+#[cfg(feature = "http")]
+mod http;
+mod iterm;
+mod layout;
+mod lines;
+mod mascot;
+mod options;
+mod output;
+mod overlay;
+mod palette;
+#[cfg(feature = "png")]
+mod png;
+mod random;
+mod report;
+mod sixel;
+mod sprite;
+mod svg;
+mod template;
+mod terminal;
+mod text;
+
+pub use animation::{
+    say_from_animated_image, say_from_frames, say_from_frames_resizing, write_asciicast,
+    AnimationFrame, FrameSequence,
+};
+pub use bubble::{BubbleStyle, ParseBubbleStyleError};
+pub use builder::PixelSays;
+pub use cells::{render_cells, Cell, CellGrid};
+pub use color::{Color, ParseColorError};
+pub use compiled::CompiledSprite;
+pub use conversation::{Conversation, Speaker};
+pub use custom::{CustomRenderer, PixelRenderer};
+pub use env::{EnvDefaults, InvalidEnvVar, IMAGE_VAR, MODE_VAR, THEME_VAR, WIDTH_VAR};
+pub use error::{ImageTooLarge, PixelSaysError};
+pub use figlet::FigletFont;
+pub use fit::FitStrategy;
+pub use frame::{Frame, FrameStyle};
+pub use highlight::Highlight;
+#[cfg(feature = "http")]
+pub use http::{download_image, is_url, say_from_url};
+pub use layout::arrange_columns;
+pub use lines::{render_lines, RenderedLines};
+pub use mascot::{say_with_mascot, Mascot, ParseMascotError};
+pub use output::MultiWriter;
+pub use overlay::{Corner, TextOverlay};
+pub use palette::ColorPalette;
+pub use random::pick_random_image;
+pub use report::RenderReport;
+pub use sprite::{SheetLayout, SpriteSheet};
+pub use template::Template;
+pub use terminal::{
+    detect_background, detect_output_profile, detect_pixel_mode, terminal_size, ResizeWatcher,
+    TerminalBackground,
+};
+pub use options::{
+    AltText, AmbiguousWidth, AnsiPolicy, AspectCorrection, AspectFit, AutoLevels, BayerSize,
+    BubbleKind, BubblePadding, BubblePosition, ColorBlindness, DecodeLimits, Dither, DropShadow,
+    Facing, Layout, LineEnding, Luminance, OutputBackend, OutputProfile, PixelWidth, Rainbow, Rect,
+    RenderOptions, ShadeLevels, TailAnchor, TextStyle, Threshold, WhiteBalance, WrapMode,
+    DEFAULT_ALPHA_THRESHOLD, DEFAULT_MAX_ALLOC, DEFAULT_MAX_CELLS, DEFAULT_MAX_IMAGE_HEIGHT,
+    DEFAULT_MAX_IMAGE_WIDTH, DEFAULT_RAMP,
+};
+
+/// 供 `benches/` 调用的内部函数，不属于公开接口
+#[doc(hidden)]
+pub mod bench {
+    use crate::Luminance;
+
+    /// 像素的亮度，与转换图片时使用的查表实现相同
+    pub fn luminance(r: u8, g: u8, b: u8, model: Luminance) -> u8 {
+        crate::color::luminance(r, g, b, model)
+    }
+}
+
+use bubble::{bubble_width, tail_marks, write_bubble};
+use cells::{alpha_map, write_export};
+use convert::{
+    art_height, art_width, crop_image, graphics_source, is_export, pixel_column, render_art,
+    render_art_from, resize_image,
+};
+use filters::{is_visible, preprocess};
+use output::LineWriter;
+use report::CountingWriter;
+use image::{DynamicImage, GenericImageView, ImageError, ImageReader};
 use smallvec::*;
-use std::io::{Result, Write};
+use std::borrow::Cow;
+use std::io::{BufRead, Cursor, Result, Seek, Write};
 use std::path::Path;
-use textwrap::fill;
-use unicode_width::UnicodeWidthStr;
+use text::{longest_line, wrap_message};
 
-const BUFSIZE: usize = 8192;
+pub(crate) const BUFSIZE: usize = 8192;
 
 /// 像素转换模式
-#[derive(Debug, Clone, Copy)]
+///
+/// 因为 [`PixelMode::Custom`] 持有渲染器，这个类型只实现了 `Clone` 而不是 `Copy`。
+/// 启用 `serde` 功能后，`Custom` 以外的模式按小写名称序列化；序列化 `Custom`（包括持有它的
+/// [`RenderOptions`]）会在运行时返回错误，而不是在编译时被拒绝。
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum PixelMode {
     /// 真彩色模式，保持原有颜色
     TrueColor,
+    /// 256 色模式，把每个像素量化为 xterm 256 色调色板中最接近的颜色，
+    /// 适用于不支持 24 位颜色的终端和 CI 日志
+    Ansi256,
+    /// 16 色模式，把每个像素量化为终端基本 16 色中最接近的颜色，
+    /// 适用于 Linux 控制台等只支持 16 色的终端；实际显示的颜色取决于终端主题
+    Ansi16,
+    /// 灰度模式，与真彩色模式相同地输出 24 位颜色，但每个像素都换成按亮度计算的灰色，
+    /// 比黑白模式的明暗过渡更平滑
+    Grayscale,
     /// 黑白模式，转换为黑白格子
     Monochrome,
     /// 反色模式，黑白颠倒的黑白格子
     Invert,
+    /// 明暗模式，按亮度使用 ` ░▒▓█` 五级字符，不输出颜色
+    Shade,
+    /// 字符画模式，按亮度使用 `RenderOptions::ramp` 中的字符，不输出颜色
+    Ascii,
+    /// 象限模式，每个字符用象限方块（如 `▚`、`▐`）和前景、背景两种颜色表示 2x2 个像素，
+    /// 同样的宽度可以显示四倍的像素
+    Quadrant,
+    /// 盲文点阵模式，每个盲文字符表示 2x4 个像素，细节是黑白模式的数倍，不输出颜色
+    Braille,
+    /// 根据终端的能力自动选择，见 [`detect_pixel_mode`]
+    Auto,
+    /// 由调用者提供每个像素的字符和颜色，见 [`PixelRenderer`]；不能序列化
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Custom(CustomRenderer),
+}
+
+impl PixelMode {
+    /// 实际使用的模式，`Auto` 会被替换为检测到的模式
+    pub fn resolve(&self) -> PixelMode {
+        match self {
+            PixelMode::Auto => detect_pixel_mode(),
+            mode => mode.clone(),
+        }
+    }
+
+    /// 使用自定义的像素渲染方式
+    pub fn custom<R>(renderer: R) -> PixelMode
+    where
+        R: PixelRenderer + 'static,
+    {
+        PixelMode::Custom(CustomRenderer::new(renderer))
+    }
+}
+
+/// 无法识别的像素转换模式名称
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseModeError(String);
+
+impl std::fmt::Display for ParseModeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "未知的像素转换模式: {}", self.0)
+    }
+}
+
+impl std::error::Error for ParseModeError {}
+
+impl std::fmt::Display for PixelMode {
+    /// 输出可以被 `parse` 识别的名称
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            PixelMode::TrueColor => "truecolor",
+            PixelMode::Ansi256 => "ansi256",
+            PixelMode::Ansi16 => "ansi16",
+            PixelMode::Grayscale => "grayscale",
+            PixelMode::Monochrome => "monochrome",
+            PixelMode::Invert => "invert",
+            PixelMode::Shade => "shade",
+            PixelMode::Ascii => "ascii",
+            PixelMode::Quadrant => "quadrant",
+            PixelMode::Braille => "braille",
+            PixelMode::Auto => "auto",
+            PixelMode::Custom(_) => "custom",
+        };
+        f.write_str(name)
+    }
+}
+
+impl std::str::FromStr for PixelMode {
+    type Err = ParseModeError;
+
+    /// 模式名称不区分大小写，如 `truecolor`、`monochrome`
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "truecolor" => Ok(PixelMode::TrueColor),
+            "ansi256" | "256" => Ok(PixelMode::Ansi256),
+            "ansi16" | "16" => Ok(PixelMode::Ansi16),
+            "grayscale" | "greyscale" => Ok(PixelMode::Grayscale),
+            "monochrome" => Ok(PixelMode::Monochrome),
+            "invert" => Ok(PixelMode::Invert),
+            "shade" => Ok(PixelMode::Shade),
+            "ascii" => Ok(PixelMode::Ascii),
+            "quadrant" => Ok(PixelMode::Quadrant),
+            "braille" => Ok(PixelMode::Braille),
+            "auto" => Ok(PixelMode::Auto),
+            _ => Err(ParseModeError(s.to_string())),
+        }
+    }
 }
 
 /// 从图片文件创建像素说话效果
@@ -46,349 +249,859 @@ pub fn say_from_image<P, W>(
     max_width: usize,
     mode: PixelMode,
     writer: W,
-) -> Result<()>
+) -> Result<RenderReport>
 where
     P: AsRef<Path>,
     W: Write,
 {
-    // 加载图片
-    let img = image::open(image_path).map_err(|e| {
-        std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("无法加载图片: {}", e))
-    })?;
-
-    say_from_dynamic_image(img, message, max_width, mode, writer)
+    let options = RenderOptions {
+        mode,
+        max_width,
+        ..RenderOptions::default()
+    };
+    say_from_image_with_options(image_path, message, &options, writer)
 }
 
-/// 从 DynamicImage 创建像素说话效果
-pub fn say_from_dynamic_image<W>(
-    img: DynamicImage,
+/// 从图片文件创建像素思考效果：圆括号消息框和 `O`、`o` 气泡连接线
+///
+/// 参数与 [`say_from_image`] 相同。
+pub fn think_from_image<P, W>(
+    image_path: P,
     message: &str,
     max_width: usize,
     mode: PixelMode,
-    mut writer: W,
-) -> Result<()>
+    writer: W,
+) -> Result<RenderReport>
 where
+    P: AsRef<Path>,
     W: Write,
 {
-    let mut write_buffer = SmallVec::<[u8; BUFSIZE]>::new();
-
-    // 预处理消息文本
-    let input = merge_white_spaces(message);
-    let wrapped = fill(input.as_str(), max_width);
-    let lines: Vec<&str> = wrapped.lines().collect();
-    let line_count = lines.len();
-    let actual_width = longest_line(&lines);
+    let options = RenderOptions {
+        mode,
+        max_width,
+        bubble: BubbleKind::Think,
+        ..RenderOptions::default()
+    };
+    say_from_image_with_options(image_path, message, &options, writer)
+}
 
-    // 绘制消息框顶部
-    write_buffer.push(b' ');
-    for _ in 0..(actual_width + 2) {
-        write_buffer.push(b'_');
+/// 使用渲染选项从图片文件创建像素说话效果
+///
+/// 图片在解码时会受到 `options.limits` 的限制，超出限制时返回
+/// 携带 [`ImageTooLarge`] 的错误，而不会尝试分配大量内存。
+/// 启用 `svg` 功能后，`.svg` 文件会按终端宽度光栅化；启用 `http` 功能后，
+/// `http(s)://` 地址会通过 [`say_from_url`] 下载。
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use pixel_says::{say_from_image_with_options, DecodeLimits, RenderOptions};
+/// use std::io::stdout;
+///
+/// let options = RenderOptions {
+///     limits: DecodeLimits {
+///         max_width: Some(512),
+///         max_height: Some(512),
+///         ..DecodeLimits::default()
+///     },
+///     ..RenderOptions::default()
+/// };
+/// say_from_image_with_options("test.png", "Hello!", &options, stdout()).unwrap();
+/// ```
+pub fn say_from_image_with_options<P, W>(
+    image_path: P,
+    message: &str,
+    options: &RenderOptions,
+    writer: W,
+) -> Result<RenderReport>
+where
+    P: AsRef<Path>,
+    W: Write,
+{
+    let image_path = image_path.as_ref();
+    #[cfg(feature = "http")]
+    if let Some(url) = image_path.to_str().filter(|path| http::is_url(path)) {
+        return say_from_url(url, message, options, writer);
     }
-    write_buffer.push(b'\n');
+    let img = if svg::is_svg_path(image_path) {
+        svg::rasterize(&std::fs::read(image_path)?, options)?
+    } else {
+        load_image(image_path, options.limits)?
+    };
+    let name = image_path.file_name().map(|name| name.to_string_lossy());
 
-    // 绘制消息内容
-    for (i, line) in lines.into_iter().enumerate() {
-        if line_count == 1 {
-            write_buffer.extend_from_slice(b"< ");
-        } else if i == 0 {
-            write_buffer.extend_from_slice(b"/ ");
-        } else if i == line_count - 1 {
-            write_buffer.extend_from_slice(b"\\ ");
-        } else {
-            write_buffer.extend_from_slice(b"| ");
-        }
+    say_named(img, name.as_deref(), message, options, writer)
+}
 
-        let line_len = UnicodeWidthStr::width(line);
-        write_buffer.extend_from_slice(line.as_bytes());
-        for _ in line_len..actual_width {
-            write_buffer.push(b' ');
-        }
+/// 在解码限制下读取图片文件，便于多次渲染同一张图片时只解码一次
+pub fn load_image<P>(image_path: P, limits: DecodeLimits) -> Result<DynamicImage>
+where
+    P: AsRef<Path>,
+{
+    let image_path = image_path.as_ref();
+    decode_with_limits(|| ImageReader::open(image_path), limits)
+}
+
+/// 在解码限制下解码内存中的图片，格式根据数据内容判断
+pub fn load_image_from_bytes(bytes: &[u8], limits: DecodeLimits) -> Result<DynamicImage> {
+    decode_with_limits(|| Ok(ImageReader::new(Cursor::new(bytes))), limits)
+}
+
+/// 从内存中的图片数据创建像素说话效果
+///
+/// 图片格式根据数据内容判断，适合配合 `include_bytes!` 把图片嵌入程序。
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use pixel_says::{say_from_bytes, PixelMode};
+/// use std::io::stdout;
+///
+/// let sprite = std::fs::read("test.png").unwrap();
+/// say_from_bytes(&sprite, "Hello!", 24, PixelMode::TrueColor, stdout()).unwrap();
+/// ```
+pub fn say_from_bytes<W>(
+    bytes: &[u8],
+    message: &str,
+    max_width: usize,
+    mode: PixelMode,
+    writer: W,
+) -> Result<RenderReport>
+where
+    W: Write,
+{
+    let options = RenderOptions {
+        mode,
+        max_width,
+        ..RenderOptions::default()
+    };
+    say_from_bytes_with_options(bytes, message, &options, writer)
+}
 
-        if line_count == 1 {
-            write_buffer.extend_from_slice(b" >\n");
-        } else if i == 0 {
-            write_buffer.extend_from_slice(b" \\\n");
-        } else if i == line_count - 1 {
-            write_buffer.extend_from_slice(b" /\n");
-        } else {
-            write_buffer.extend_from_slice(b" |\n");
+/// 使用渲染选项从内存中的图片数据创建像素说话效果
+///
+/// 图片格式根据数据内容判断，解码同样受到 `options.limits` 的限制。
+/// 启用 `svg` 功能后也可以是 SVG 文档。
+pub fn say_from_bytes_with_options<W>(
+    bytes: &[u8],
+    message: &str,
+    options: &RenderOptions,
+    writer: W,
+) -> Result<RenderReport>
+where
+    W: Write,
+{
+    let img = if svg::is_svg_data(bytes) {
+        svg::rasterize(bytes, options)?
+    } else {
+        load_image_from_bytes(bytes, options.limits)?
+    };
+
+    say_from_dynamic_image_with_options(img, message, options, writer)
+}
+
+/// 数据是否以已知图片格式（PNG、JPEG、GIF、WebP）的文件头开始
+pub fn is_image_data(bytes: &[u8]) -> bool {
+    bytes.starts_with(b"\x89PNG\r\n\x1a\n")
+        || bytes.starts_with(b"\xff\xd8\xff")
+        || bytes.starts_with(b"GIF87a")
+        || bytes.starts_with(b"GIF89a")
+        || (bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WEBP"))
+}
+
+/// 在解码限制下读取图片
+///
+/// `open` 会被调用两次：第一次只读取图片头部以检查尺寸，第二次才真正解码。
+pub(crate) fn decode_with_limits<R, F>(open: F, limits: DecodeLimits) -> Result<DynamicImage>
+where
+    R: BufRead + Seek,
+    F: Fn() -> Result<ImageReader<R>>,
+{
+    let (width, height) = open()?
+        .with_guessed_format()?
+        .into_dimensions()
+        .map_err(image_error)?;
+    if !limits.allows(width, height) {
+        return Err(ImageTooLarge {
+            dimensions: Some((width, height)),
+            limits,
         }
+        .into());
     }
 
-    // 绘制消息框底部
-    write_buffer.push(b' ');
-    for _ in 0..(actual_width + 2) {
-        write_buffer.push(b'-');
-    }
-    write_buffer.push(b'\n');
+    let mut reader = open()?.with_guessed_format()?;
+    reader.limits(limits.into());
+    reader.decode().map_err(|e| match e {
+        ImageError::Limits(_) => ImageTooLarge {
+            dimensions: Some((width, height)),
+            limits,
+        }
+        .into(),
+        e => image_error(e),
+    })
+}
 
-    // 添加连接线
-    write_buffer.extend_from_slice(b"        \\\n");
-    write_buffer.extend_from_slice(b"         \\\n");
+fn image_error(e: ImageError) -> std::io::Error {
+    PixelSaysError::ImageDecode(e).into()
+}
 
-    // 输出缓冲区内容
-    writer.write_all(&write_buffer)?;
+/// 从 DynamicImage 创建像素说话效果，更多的选项见 [`PixelSays`]
+pub fn say_from_dynamic_image<W>(
+    img: DynamicImage,
+    message: &str,
+    max_width: usize,
+    mode: PixelMode,
+    writer: W,
+) -> Result<RenderReport>
+where
+    W: Write,
+{
+    PixelSays::new(img)
+        .message(message)
+        .mode(mode)
+        .width(max_width)
+        .render(writer)
+}
 
-    // 转换并输出图片
-    convert_image_to_text(img, mode, writer)?;
+/// 使用渲染选项从 DynamicImage 创建像素说话效果
+pub fn say_from_dynamic_image_with_options<W>(
+    img: DynamicImage,
+    message: &str,
+    options: &RenderOptions,
+    writer: W,
+) -> Result<RenderReport>
+where
+    W: Write,
+{
+    say_named(img, None, message, options, writer)
+}
 
-    Ok(())
+/// 与 [`say_from_dynamic_image_with_options`] 相同，但只借用图片
+///
+/// 图片不需要裁剪、缩放或预处理时直接从原图转换，不会复制像素数据，适合反复输出同一张
+/// 图片的场合。同一张图片配合不同的消息时，[`CompiledSprite`] 还能省去每次的转换。
+///
+/// # Example
+///
+/// ```rust
+/// use image::{DynamicImage, RgbaImage};
+/// use pixel_says::{say_from_image_ref, RenderOptions};
+///
+/// let sprite = DynamicImage::ImageRgba8(RgbaImage::new(4, 4));
+/// for message in ["Hello!", "Goodbye!"] {
+///     say_from_image_ref(&sprite, message, &RenderOptions::default(), Vec::new()).unwrap();
+/// }
+/// ```
+pub fn say_from_image_ref<W>(
+    img: &DynamicImage,
+    message: &str,
+    options: &RenderOptions,
+    writer: W,
+) -> Result<RenderReport>
+where
+    W: Write,
+{
+    CompiledSprite::compile(Cow::Borrowed(img), None, options)?.say(message, writer)
 }
 
-/// 将图片转换为终端文本
-fn convert_image_to_text<W>(img: DynamicImage, mode: PixelMode, writer: W) -> Result<()>
+/// 只把图片转换为终端文本，不绘制消息框和连接线
+///
+/// 便于把像素图嵌入其他终端界面。
+///
+/// # Example
+///
+/// ```rust
+/// use image::{DynamicImage, RgbaImage};
+/// use pixel_says::{render_image_to_text, PixelMode};
+///
+/// let sprite = DynamicImage::ImageRgba8(RgbaImage::new(4, 4));
+/// let mut output = Vec::new();
+/// render_image_to_text(sprite, PixelMode::TrueColor, &mut output).unwrap();
+/// ```
+pub fn render_image_to_text<W>(
+    img: DynamicImage,
+    mode: PixelMode,
+    writer: W,
+) -> Result<RenderReport>
 where
     W: Write,
 {
-    let (width, height) = img.dimensions();
-    
-    // 限制图片大小，避免输出过大
-    let max_size = 80;
-    let (new_width, new_height) = if width > max_size || height > max_size {
-        let ratio = max_size as f32 / width.max(height) as f32;
-        ((width as f32 * ratio) as u32, (height as f32 * ratio) as u32)
-    } else {
-        (width, height)
+    let options = RenderOptions {
+        mode,
+        ..RenderOptions::default()
     };
-
-    let resized_img = img.resize(new_width, new_height, image::imageops::FilterType::Nearest);
-
-    match mode {
-        PixelMode::TrueColor => convert_to_truecolor(&resized_img, writer),
-        PixelMode::Monochrome => convert_to_monochrome(&resized_img, writer),
-        PixelMode::Invert => convert_to_invert(&resized_img, writer),
-    }
+    render_image_to_text_with_options(img, &options, writer)
 }
 
-/// 转换为真彩色输出
-fn convert_to_truecolor<W>(img: &DynamicImage, mut writer: W) -> Result<()>
+/// 使用渲染选项只把图片转换为终端文本，与消息框相关的选项不起作用
+pub fn render_image_to_text_with_options<W>(
+    img: DynamicImage,
+    options: &RenderOptions,
+    writer: W,
+) -> Result<RenderReport>
 where
     W: Write,
 {
-    let (width, height) = img.dimensions();
-    
-    for y in 0..height {
-        for x in 0..width {
-            let pixel = img.get_pixel(x, y);
-            match pixel {
-                image::Rgba([r, g, b, a]) => {
-                    // 如果像素是透明的，输出空格
-                    if a < 128 {
-                        write!(writer, "  ")?;
-                    } else {
-                        // 使用 ANSI 真彩色转义序列 - 前景色
-                        write!(writer, "\x1b[38;2;{};{};{}m██\x1b[0m", r, g, b)?;
-                    }
-                },
-            };
+    let img = crop_image(Cow::Owned(img), options);
+    let source = graphics_source(&img, options);
+    let img = preprocess(resize_image(img, options), options);
+    let frame = if options.frame.is_some() { 2 } else { 0 };
+    let report = RenderReport {
+        art_width: art_width(img.width(), options) + frame,
+        art_height: art_height(img.height(), options) + frame,
+        colors_used: count_colors(&img),
+        ..RenderReport::default()
+    };
+
+    if is_export(options) {
+        let mut art = Vec::new();
+        render_art(&img, options, &mut art)?;
+        let mut text = Vec::new();
+        LineWriter::new(&mut text, options).write_all(&art)?;
+        let mut grid = CellGrid::from_ansi(&String::from_utf8_lossy(&text));
+        if options.profile != OutputProfile::Ascii {
+            let mut map = Vec::new();
+            let alpha = alpha_map(&art, &img, frame / 2, options);
+            LineWriter::new(&mut map, options).write_all(&alpha)?;
+            grid.apply_alpha(&CellGrid::from_ansi(&String::from_utf8_lossy(&map)));
         }
-        writeln!(writer)?;
+        return write_export(&grid, options, report, writer);
     }
-    
-    Ok(())
+    let mut counter = CountingWriter::new(writer);
+    let writer = LineWriter::new(&mut counter, options);
+    render_art_from(&img, source.as_ref().unwrap_or(&img), options, writer)?;
+    Ok(RenderReport {
+        lines: counter.lines,
+        bytes_written: counter.bytes,
+        ..report
+    })
 }
 
-/// 转换为黑白模式输出
-fn convert_to_monochrome<W>(img: &DynamicImage, mut writer: W) -> Result<()>
+/// 渲染并按照 `options.alt_text` 追加描述，`name` 为描述中的图片名称
+fn say_named<W>(
+    img: DynamicImage,
+    name: Option<&str>,
+    message: &str,
+    options: &RenderOptions,
+    writer: W,
+) -> Result<RenderReport>
 where
     W: Write,
 {
-    let (width, height) = img.dimensions();
-    
-    for y in 0..height {
-        for x in 0..width {
-            let pixel = img.get_pixel(x, y);
-            let char = match pixel {
-                image::Rgba([r, g, b, a]) => {
-                    // 如果像素是透明的，输出空格
-                    if a < 128 {
-                        "  "
-                    } else {
-                        // 计算亮度 (ITU-R BT.709)
-                        let luminance = (0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32) as u8;
-                        // 根据亮度选择字符
-                        if luminance > 128 { "██" } else { "  " }
-                    }
-                },
-            };
-            write!(writer, "{}", char)?;
+    CompiledSprite::compile(Cow::Owned(img), name, options)?.say(message, writer)
+}
+
+/// 绘制左侧空出 `margin` 列的消息框，以换行结束
+pub(crate) fn write_indented_bubble(
+    write_buffer: &mut SmallVec<[u8; BUFSIZE]>,
+    lines: &[&str],
+    actual_width: usize,
+    margin: usize,
+    options: &RenderOptions,
+) {
+    let mut bubble = SmallVec::<[u8; BUFSIZE]>::new();
+    write_bubble(&mut bubble, lines, actual_width, options);
+    bubble.push(b'\n');
+    for line in bubble.split_inclusive(|&b| b == b'\n') {
+        write_buffer.extend(std::iter::repeat_n(b' ', margin));
+        write_buffer.extend_from_slice(line);
+    }
+}
+
+/// 缩放、预处理图片并让它朝向消息框，返回处理后的图片和连接线第一行的缩进
+pub(crate) fn place_art<'a>(
+    img: Cow<'a, DynamicImage>,
+    bubble_width: usize,
+    options: &RenderOptions,
+) -> (Cow<'a, DynamicImage>, usize) {
+    // 先缩放图片，连接线的位置取决于图片的宽度
+    let (source_width, _) = img.dimensions();
+    let resized_img = preprocess(resize_image(img, options), options);
+    let (flip, indent) = orient_art(source_width, resized_img.width(), bubble_width, options);
+    if flip {
+        (Cow::Owned(resized_img.fliph()), indent)
+    } else {
+        (resized_img, indent)
+    }
+}
+
+/// 计算连接线第一行的缩进，以及图片是否需要水平翻转才能朝向消息框
+///
+/// `source_width` 与 `width` 分别是缩放前后图片的像素宽度。
+pub(crate) fn orient_art(
+    source_width: u32,
+    width: u32,
+    bubble_width: usize,
+    options: &RenderOptions,
+) -> (bool, usize) {
+    let image_width = art_width(width, options);
+    // 一个像素占用的终端列数，盲文模式下两个像素共用一列
+    let columns = pixel_column(1, options).max(1);
+
+    let mut indent = tail_indent(options.tail, bubble_width, image_width, |column| {
+        let x = column as usize * width as usize / source_width.max(1) as usize;
+        pixel_column(x, options)
+    });
+
+    // 让图片朝向消息框
+    let mut flip = false;
+    if let Some(facing) = options.face_bubble {
+        if bubble_side(indent, image_width).is_some_and(|side| side != facing) {
+            flip = true;
+            // 锚定在图片某一列时，连接线跟随这一列一起翻转
+            if options.tail.image_column().is_some() {
+                indent = image_width.saturating_sub(columns + indent);
+            }
         }
-        writeln!(writer)?;
     }
-    
-    Ok(())
+    // 边框占用图片左侧的一列
+    if options.frame.is_some() && options.tail.image_column().is_some() {
+        indent += 1;
+    }
+    (flip, indent)
 }
 
-/// 转换为反色模式输出
-fn convert_to_invert<W>(img: &DynamicImage, mut writer: W) -> Result<()>
+/// 添加连接线；`upward` 为真时消息框在下方，连接线上下翻转
+pub(crate) fn write_tail(
+    write_buffer: &mut SmallVec<[u8; BUFSIZE]>,
+    indent: usize,
+    kind: BubbleKind,
+    upward: bool,
+) {
+    let mut rows: Vec<(usize, &[u8])> = tail_marks(kind).into_iter().enumerate().collect();
+    if upward {
+        rows.reverse();
+    }
+    for (offset, mark) in rows {
+        write_buffer.extend(std::iter::repeat_n(b' ', indent + offset));
+        // 上下翻转后 `\` 变成 `/`
+        write_buffer.extend_from_slice(if upward && mark == b"\\" { b"/" } else { mark });
+        write_buffer.push(b'\n');
+    }
+}
+
+/// 图片中可见像素的不同颜色数
+pub(crate) fn count_colors(img: &DynamicImage) -> usize {
+    visible_colors(img).len()
+}
+
+/// 图片中可见像素的颜色，用于统计多张图片（如动画的每一帧）共用的颜色数
+pub(crate) fn visible_colors(img: &DynamicImage) -> std::collections::HashSet<[u8; 3]> {
+    img.to_rgba8()
+        .pixels()
+        .filter(|pixel| is_visible(pixel))
+        .map(|pixel| [pixel[0], pixel[1], pixel[2]])
+        .collect()
+}
+
+/// 计算连接线第一行的缩进
+///
+/// `bubble_width` 与 `image_width` 都以终端列为单位；`image_column` 把原图中的像素列
+/// 换算为缩放后图片中的终端列。
+fn tail_indent<F>(
+    anchor: TailAnchor,
+    bubble_width: usize,
+    image_width: usize,
+    image_column: F,
+) -> usize
 where
-    W: Write,
+    F: Fn(u32) -> usize,
 {
-    let (width, height) = img.dimensions();
-    
-    for y in 0..height {
-        for x in 0..width {
-            let pixel = img.get_pixel(x, y);
-            let char = match pixel {
-                image::Rgba([r, g, b, a]) => {
-                    // 如果像素是透明的，输出空格
-                    if a < 128 {
-                        "  "
-                    } else {
-                        // 计算亮度 (ITU-R BT.709)
-                        let luminance = (0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32) as u8;
-                        // 反色：根据亮度选择字符，与monochrome相反
-                        if luminance > 128 { "  " } else { "██" }
-                    }
-                },
-            };
-            write!(writer, "{}", char)?;
-        }
-        writeln!(writer)?;
+    match anchor {
+        TailAnchor::Fixed(indent) => indent,
+        TailAnchor::BubbleCenter => bubble_width / 2,
+        // 两行连接线分列中心两侧
+        TailAnchor::ImageCenter => (image_width / 2).saturating_sub(1),
+        // 指向消息框与图片重叠部分的中间，确保连接线两端都有内容
+        TailAnchor::Auto => bubble_width.min(image_width) / 2,
+        TailAnchor::ImageColumn(column) | TailAnchor::Point(column, _) => image_column(column),
+    }
+}
+
+/// 根据连接线的位置判断消息框位于图片的哪一侧，正好在中间时返回 `None`
+fn bubble_side(indent: usize, image_width: usize) -> Option<Facing> {
+    let center = image_width / 2;
+    if indent + 1 < center {
+        Some(Facing::Left)
+    } else if indent > center + 1 {
+        Some(Facing::Right)
+    } else {
+        None
     }
-    
-    Ok(())
 }
 
 /// 兼容原有的 say 函数，使用默认的 Ferris 图案
-pub fn say<W>(input: &str, max_width: usize, mut writer: W) -> Result<()>
+pub fn say<W>(input: &str, max_width: usize, writer: W) -> Result<RenderReport>
+where
+    W: Write,
+{
+    let options = RenderOptions {
+        max_width,
+        ..RenderOptions::default()
+    };
+    say_with_options(input, &options, writer)
+}
+
+/// 使用渲染选项绘制默认的 Ferris 图案，只有与消息框相关的选项会生效
+pub fn say_with_options<W>(
+    input: &str,
+    options: &RenderOptions,
+    writer: W,
+) -> Result<RenderReport>
 where
     W: Write,
 {
-    const MASCOT: &[u8] = br#"
-        \
-         \
-            _~^~^~_
+    let mut counter = CountingWriter::new(writer);
+    let mut writer = LineWriter::new(&mut counter, options);
+    const MASCOT: &[u8] = br#"            _~^~^~_
         \) /  o o  \ (/
           '_   -   _'
           / '-----' \
 "#;
 
     let mut write_buffer = SmallVec::<[u8; BUFSIZE]>::new();
-    let input = merge_white_spaces(input);
-    let wrapped = fill(input.as_str(), max_width);
+    let wrapped = wrap_message(input, options)?;
     let lines: Vec<&str> = wrapped.lines().collect();
-    let line_count = lines.len();
-    let actual_width = longest_line(&lines);
+    let actual_width = longest_line(&lines, options.ambiguous_width);
 
     // 绘制消息框
-    write_buffer.push(b' ');
-    for _ in 0..(actual_width + 2) {
-        write_buffer.push(b'_');
-    }
+    write_bubble(&mut write_buffer, &lines, actual_width, options);
     write_buffer.push(b'\n');
+    write_tail(&mut write_buffer, 8, options.bubble, false);
 
-    for (i, line) in lines.into_iter().enumerate() {
-        if line_count == 1 {
-            write_buffer.extend_from_slice(b"< ");
-        } else if i == 0 {
-            write_buffer.extend_from_slice(b"/ ");
-        } else if i == line_count - 1 {
-            write_buffer.extend_from_slice(b"\\ ");
-        } else {
-            write_buffer.extend_from_slice(b"| ");
-        }
-
-        let line_len = UnicodeWidthStr::width(line);
-        write_buffer.extend_from_slice(line.as_bytes());
-        for _ in line_len..actual_width {
-            write_buffer.push(b' ');
-        }
-
-        if line_count == 1 {
-            write_buffer.extend_from_slice(b" >\n");
-        } else if i == 0 {
-            write_buffer.extend_from_slice(b" \\\n");
-        } else if i == line_count - 1 {
-            write_buffer.extend_from_slice(b" /\n");
-        } else {
-            write_buffer.extend_from_slice(b" |\n");
-        }
-    }
+    write_buffer.extend_from_slice(MASCOT);
+    writer.write_all(&write_buffer)?;
 
-    write_buffer.push(b' ');
-    for _ in 0..(actual_width + 2) {
-        write_buffer.push(b'-');
-    }
+    let mascot = std::str::from_utf8(MASCOT).unwrap_or_default();
+    let art: Vec<&str> = mascot.lines().collect();
+    Ok(RenderReport {
+        art_width: art.iter().map(|line| line.len()).max().unwrap_or(0),
+        art_height: art.len(),
+        bubble_width: bubble_width(actual_width, options),
+        lines: counter.lines,
+        bytes_written: counter.bytes,
+        colors_used: 0,
+    })
+}
 
-    write_buffer.extend_from_slice(MASCOT);
-    writer.write_all(&write_buffer)
+/// 把渲染结果收集为字符串，适合测试、GUI 和日志等不方便提供 `Write` 的场合
+///
+/// # Example
+///
+/// ```rust
+/// use pixel_says::{say_to_string, RenderOptions};
+///
+/// let output = say_to_string("Hello!", &RenderOptions::default()).unwrap();
+/// assert!(output.contains("< Hello! >"));
+/// ```
+pub fn say_to_string(input: &str, options: &RenderOptions) -> Result<String> {
+    collect_string(|writer| say_with_options(input, options, writer))
 }
 
-fn longest_line(lines: &[&str]) -> usize {
-    lines
-        .iter()
-        .map(|line| UnicodeWidthStr::width(*line))
-        .max()
-        .unwrap_or(0)
+/// 把图片和消息的渲染结果收集为字符串，见 [`say_from_dynamic_image_with_options`]
+pub fn render_to_string(
+    img: DynamicImage,
+    message: &str,
+    options: &RenderOptions,
+) -> Result<String> {
+    collect_string(|writer| say_from_dynamic_image_with_options(img, message, options, writer))
 }
 
-fn merge_white_spaces(input: &str) -> String {
-    let re = Regex::new(r"([^\S\r\n])+").unwrap();
-    re.replace_all(input, " ").to_string()
+/// 渲染到内存中并转换为字符串，输出总是 UTF-8
+pub(crate) fn collect_string<F>(render: F) -> Result<String>
+where
+    F: FnOnce(&mut Vec<u8>) -> Result<RenderReport>,
+{
+    let mut output = Vec::new();
+    render(&mut output)?;
+    String::from_utf8(output)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use image::{Rgba, RgbaImage};
+    use image::RgbaImage;
+
+    #[test]
+    fn test_decode_limits_reject_large_image() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::new(16, 8));
+        let mut png = Vec::new();
+        img.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+
+        let limits = DecodeLimits {
+            max_width: Some(8),
+            ..DecodeLimits::default()
+        };
+        let open = || Ok(ImageReader::new(std::io::Cursor::new(&png)));
+
+        let err = decode_with_limits(open, limits).unwrap_err();
+        let Some(PixelSaysError::ImageTooLarge(too_large)) = PixelSaysError::from_io(&err) else {
+            panic!("unexpected error: {}", err);
+        };
+        assert_eq!(too_large.dimensions, Some((16, 8)));
+
+        let decoded = decode_with_limits(open, DecodeLimits::default()).unwrap();
+        assert_eq!(decoded.dimensions(), (16, 8));
+    }
+
+    #[test]
+    fn test_error_conversion() {
+        let err = PixelSaysError::from(std::io::Error::from(std::io::ErrorKind::BrokenPipe));
+        assert!(matches!(err, PixelSaysError::Io(_)));
+        // 普通的 I/O 错误转换回去时不会再包装一层
+        let err = std::io::Error::from(err);
+        assert_eq!(err.kind(), std::io::ErrorKind::BrokenPipe);
+        assert!(PixelSaysError::from_io(&err).is_none());
+
+        let err = std::io::Error::from(PixelSaysError::TerminalQuery("终端没有及时回复"));
+        assert!(matches!(
+            PixelSaysError::from(err),
+            PixelSaysError::TerminalQuery(_)
+        ));
+    }
+
+    #[test]
+    fn test_tail_indent() {
+        let column = |c: u32| c as usize * 2;
+        assert_eq!(tail_indent(TailAnchor::Fixed(8), 30, 40, column), 8);
+        assert_eq!(tail_indent(TailAnchor::BubbleCenter, 30, 40, column), 15);
+        assert_eq!(tail_indent(TailAnchor::ImageCenter, 30, 40, column), 19);
+        assert_eq!(tail_indent(TailAnchor::Auto, 30, 10, column), 5);
+        assert_eq!(tail_indent(TailAnchor::ImageColumn(3), 30, 40, column), 6);
+        assert_eq!(tail_indent(TailAnchor::Point(3, 7), 30, 40, column), 6);
+    }
+
+    #[test]
+    fn test_face_bubble_flips_image() {
+        // 左半边白色、右半边黑色的图片，面朝左
+        let mut img = RgbaImage::from_pixel(8, 1, image::Rgba([0, 0, 0, 255]));
+        for x in 0..4 {
+            img.put_pixel(x, 0, image::Rgba([255, 255, 255, 255]));
+        }
+        let render = |tail| {
+            let options = RenderOptions {
+                mode: PixelMode::Monochrome,
+                face_bubble: Some(Facing::Left),
+                tail,
+                ..RenderOptions::default()
+            };
+            let mut output = Vec::new();
+            say_from_dynamic_image_with_options(
+                DynamicImage::ImageRgba8(img.clone()),
+                "hi",
+                &options,
+                &mut output,
+            )
+            .unwrap();
+            String::from_utf8(output).unwrap()
+        };
+
+        assert!(render(TailAnchor::Fixed(0)).ends_with("████████        \n"));
+        assert!(render(TailAnchor::Fixed(14)).ends_with("        ████████\n"));
+    }
+
+    #[test]
+    fn test_render_report() {
+        let img = RgbaImage::from_fn(3, 2, |x, _| image::Rgba([x as u8 * 100, 0, 0, 255]));
+        let mut output = Vec::new();
+        let report = say_from_dynamic_image(
+            DynamicImage::ImageRgba8(img),
+            "hello",
+            40,
+            PixelMode::TrueColor,
+            &mut output,
+        )
+        .unwrap();
+
+        assert_eq!(report.art_width, 6);
+        assert_eq!(report.art_height, 2);
+        assert_eq!(report.bubble_width, 9);
+        assert_eq!(report.lines, 7);
+        assert_eq!(report.bytes_written, output.len());
+        assert_eq!(report.colors_used, 3);
+    }
 
     #[test]
-    fn test_transparent_pixels_in_monochrome() {
-        // 创建一个 2x2 的测试图片，包含透明和不透明像素
-        let mut img = RgbaImage::new(2, 2);
-        img.put_pixel(0, 0, Rgba([255, 255, 255, 255])); // 白色，不透明
-        img.put_pixel(1, 0, Rgba([0, 0, 0, 255]));       // 黑色，不透明
-        img.put_pixel(0, 1, Rgba([255, 255, 255, 0]));   // 白色，透明
-        img.put_pixel(1, 1, Rgba([0, 0, 0, 0]));         // 黑色，透明
-        
-        let dynamic_img = DynamicImage::ImageRgba8(img);
+    fn test_image_data_from_bytes() {
+        let mut png = Vec::new();
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(2, 2, image::Rgba([255, 0, 0, 255])))
+            .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+
+        assert!(is_image_data(&png));
+        assert!(!is_image_data(b"hello"));
+        assert!(is_image_data(b"RIFF\0\0\0\0WEBPVP8 "));
+
         let mut output = Vec::new();
-        
-        convert_to_monochrome(&dynamic_img, &mut output).unwrap();
-        let result = String::from_utf8(output).unwrap();
-        
-        // 期望：第一行是"██  "（白色块+黑色空格），第二行是"    "（两个透明像素都是空格）
-        assert_eq!(result, "██  \n    \n");
+        let report =
+            say_from_bytes_with_options(&png, "hi", &RenderOptions::default(), &mut output)
+                .unwrap();
+        assert_eq!(report.art_width, 4);
+
+        let mut plain = Vec::new();
+        say_from_bytes(&png, "hi", 40, PixelMode::TrueColor, &mut plain).unwrap();
+        assert_eq!(plain, output);
     }
 
     #[test]
-    fn test_transparent_pixels_in_invert() {
-        // 创建一个 2x2 的测试图片，包含透明和不透明像素
-        let mut img = RgbaImage::new(2, 2);
-        img.put_pixel(0, 0, Rgba([255, 255, 255, 255])); // 白色，不透明
-        img.put_pixel(1, 0, Rgba([0, 0, 0, 255]));       // 黑色，不透明
-        img.put_pixel(0, 1, Rgba([255, 255, 255, 0]));   // 白色，透明
-        img.put_pixel(1, 1, Rgba([0, 0, 0, 0]));         // 黑色，透明
-        
-        let dynamic_img = DynamicImage::ImageRgba8(img);
+    fn test_think_bubble() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::new(2, 2));
+        let options = RenderOptions {
+            bubble: BubbleKind::Think,
+            tail: TailAnchor::Fixed(2),
+            ..RenderOptions::default()
+        };
         let mut output = Vec::new();
-        
-        convert_to_invert(&dynamic_img, &mut output).unwrap();
-        let result = String::from_utf8(output).unwrap();
-        
-        // 期望：第一行是"  ██"（白色空格+黑色块），第二行是"    "（两个透明像素都是空格）
-        assert_eq!(result, "  ██\n    \n");
+        say_from_dynamic_image_with_options(img, "one\ntwo", &options, &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with(" _____\n( one )\n( two )\n -----\n  O\n   o\n"));
     }
 
     #[test]
-    fn test_transparent_pixels_in_truecolor() {
-        // 创建一个 2x2 的测试图片，包含透明和不透明像素
-        let mut img = RgbaImage::new(2, 2);
-        img.put_pixel(0, 0, Rgba([255, 0, 0, 255]));     // 红色，不透明
-        img.put_pixel(1, 0, Rgba([0, 255, 0, 255]));     // 绿色，不透明
-        img.put_pixel(0, 1, Rgba([0, 0, 255, 0]));       // 蓝色，透明
-        img.put_pixel(1, 1, Rgba([255, 255, 255, 50]));  // 白色，半透明
-        
-        let dynamic_img = DynamicImage::ImageRgba8(img);
+    fn test_render_image_without_bubble() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(2, 1, image::Rgba([255; 4])));
         let mut output = Vec::new();
-        
-        convert_to_truecolor(&dynamic_img, &mut output).unwrap();
-        let result = String::from_utf8(output).unwrap();
-        
-        // 期望：第一行有彩色块，第二行两个透明像素都是空格
-        assert!(result.contains("\x1b[38;2;255;0;0m██\x1b[0m")); // 红色块
-        assert!(result.contains("\x1b[38;2;0;255;0m██\x1b[0m")); // 绿色块
-        assert!(result.ends_with("    \n")); // 第二行全是空格
+        let report = render_image_to_text(img, PixelMode::Monochrome, &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "████\n");
+        assert_eq!((report.art_width, report.bubble_width, report.lines), (4, 0, 1));
+    }
+
+    #[test]
+    fn test_render_to_string() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(2, 1, image::Rgba([255; 4])));
+        let options = RenderOptions {
+            mode: PixelMode::Monochrome,
+            tail: TailAnchor::Fixed(0),
+            ..RenderOptions::default()
+        };
+        let output = render_to_string(img.clone(), "hi", &options).unwrap();
+        assert_eq!(output, " ____\n< hi >\n ----\n\\\n \\\n████\n");
+        let builder = PixelSays::new(img.clone()).message("hi").options(options.clone());
+        assert_eq!(builder.render_to_string().unwrap(), output);
+        let mut borrowed = Vec::new();
+        say_from_image_ref(&img, "hi", &options, &mut borrowed).unwrap();
+        assert_eq!(borrowed, output.as_bytes());
+
+        let mut expected = Vec::new();
+        say("hi", 40, &mut expected).unwrap();
+        let output = say_to_string("hi", &RenderOptions::default()).unwrap();
+        assert_eq!(output.as_bytes(), expected);
+    }
+
+    #[test]
+    fn test_plain_profile() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(2, 1, image::Rgba([255; 4])));
+        let options = RenderOptions {
+            mode: PixelMode::TrueColor,
+            profile: OutputProfile::Plain,
+            ansi: AnsiPolicy::PassThrough,
+            text_style: TextStyle {
+                bold: true,
+                ..TextStyle::default()
+            },
+            highlights: vec![Highlight::new("hi", Color::rgb(255, 0, 0)).unwrap()],
+            background: Some(Color::rgb(0, 0, 64)),
+            clear_to_eol: true,
+            tail: TailAnchor::Fixed(0),
+            ..RenderOptions::default()
+        };
+        let output = render_to_string(img, "\x1b[31mhi\x1b[0m", &options).unwrap();
+        assert_eq!(output, " ____\n< hi >\n ----\n\\\n \\\n████\n");
+    }
+
+    #[test]
+    fn test_bubble_positions() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 3, image::Rgba([255; 4])));
+        let render = |position| {
+            let options = RenderOptions {
+                mode: PixelMode::Monochrome,
+                tail: TailAnchor::Fixed(0),
+                position,
+                ..RenderOptions::default()
+            };
+            let mut output = Vec::new();
+            say_from_dynamic_image_with_options(img.clone(), "hi", &options, &mut output).unwrap();
+            String::from_utf8(output).unwrap()
+        };
+
+        assert_eq!(
+            render(BubblePosition::Right),
+            "██      ____\n██ <-- < hi >\n██      ----\n"
+        );
+        assert_eq!(
+            render(BubblePosition::Left),
+            " ____      ██\n< hi > --> ██\n ----      ██\n"
+        );
+        assert_eq!(
+            render(BubblePosition::Below),
+            "██\n██\n██\n /\n/\n ____\n< hi >\n ----\n"
+        );
+    }
+
+    #[test]
+    fn test_center_bubble_over_image() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(6, 1, image::Rgba([255; 4])));
+        let options = RenderOptions {
+            mode: PixelMode::Monochrome,
+            tail: TailAnchor::ImageCenter,
+            center_bubble: true,
+            ..RenderOptions::default()
+        };
+        let mut output = Vec::new();
+        say_from_dynamic_image_with_options(img, "hi", &options, &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "    ____\n   < hi >\n    ----\n     \\\n      \\\n████████████\n"
+        );
+    }
+
+    #[test]
+    fn test_alt_text_only() {
+        let img = RgbaImage::from_pixel(3, 2, image::Rgba([0, 255, 0, 255]));
+        let options = RenderOptions {
+            alt_text: AltText::Only,
+            ..RenderOptions::default()
+        };
+        let mut output = Vec::new();
+        let report = say_from_dynamic_image_with_options(
+            DynamicImage::ImageRgba8(img),
+            "hi",
+            &options,
+            &mut output,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Image: 3x2 pixels, dominant colors #00ff00. Message: hi\n"
+        );
+        assert_eq!(report.lines, 1);
+    }
+
+    #[test]
+    fn test_enclosed_layout() {
+        let img = RgbaImage::from_pixel(2, 1, image::Rgba([255, 255, 255, 255]));
+        let options = RenderOptions {
+            mode: PixelMode::Monochrome,
+            layout: Layout::Enclosed,
+            ..RenderOptions::default()
+        };
+        let mut output = Vec::new();
+        let report = say_from_dynamic_image_with_options(
+            DynamicImage::ImageRgba8(img),
+            "hi",
+            &options,
+            &mut output,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "┌──────┐\n│ ████ │\n├──────┤\n│ hi   │\n└──────┘\n"
+        );
+        assert_eq!(report.bubble_width, 8);
+        assert_eq!(report.lines, 5);
     }
 }
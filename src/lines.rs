@@ -0,0 +1,319 @@
+use crate::alt_text::describe;
+use crate::bubble::bubble_width;
+use crate::convert::{
+    art_width, convert_image_to_text, crop_image, is_export, is_graphics, pixel_rows_per_line,
+};
+use crate::filters::{resolve_threshold, shade_table};
+use crate::output::LineWriter;
+use crate::text::{expand_template, longest_line, wrap_message};
+use crate::{
+    place_art, write_indented_bubble, write_tail, AltText, BubblePosition, CompiledSprite, Layout,
+    LineEnding, OutputBackend, PixelSaysError, RenderOptions, ShadeLevels, Threshold, BUFSIZE,
+};
+use image::DynamicImage;
+use smallvec::SmallVec;
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::io::{Result, Write};
+
+/// 逐行产生渲染结果的迭代器，每一项是不带行尾的一行
+///
+/// 消息框在创建时就已经绘制好，图片则在迭代到时才逐行转换，适合分页显示很大的图片
+/// 或限速输出。带边框、左右并排、`Layout::Enclosed` 以及图形输出无法逐行转换，
+/// 这些情况下会在创建时一次渲染完毕。
+///
+/// # Example
+///
+/// ```rust
+/// use image::{DynamicImage, RgbaImage};
+/// use pixel_says::{render_lines, RenderOptions};
+///
+/// let sprite = DynamicImage::ImageRgba8(RgbaImage::new(4, 40));
+/// let lines = render_lines(sprite, "Hello!", &RenderOptions::default()).unwrap();
+/// for line in lines.take(10) {
+///     println!("{}", line.unwrap());
+/// }
+/// ```
+#[derive(Debug)]
+pub struct RenderedLines {
+    options: RenderOptions,
+    before: VecDeque<String>,
+    art: Option<ArtRows>,
+    after: VecDeque<String>,
+}
+
+/// 尚未转换的图片和下一行对应的像素行
+#[derive(Debug)]
+struct ArtRows {
+    img: DynamicImage,
+    row: u32,
+}
+
+/// 以迭代器的形式渲染消息框和图片，拼接各行的结果与 [`crate::say_from_dynamic_image_with_options`]
+/// 的输出相同（行尾总是 `\n`）
+///
+/// 二进制的 `OutputBackend::Png` 和 CP437 编码的 `OutputBackend::Ans` 无法拆分为文本行，
+/// 返回 [`PixelSaysError::UnsupportedMode`]。
+pub fn render_lines(
+    img: DynamicImage,
+    message: &str,
+    options: &RenderOptions,
+) -> Result<RenderedLines> {
+    named_lines(img, None, message, options)
+}
+
+/// 与 [`render_lines`] 相同，`name` 为文字描述中的图片名称
+pub(crate) fn named_lines(
+    img: DynamicImage,
+    name: Option<&str>,
+    message: &str,
+    options: &RenderOptions,
+) -> Result<RenderedLines> {
+    if matches!(options.backend, OutputBackend::Png | OutputBackend::Ans) {
+        return Err(PixelSaysError::UnsupportedMode(options.backend).into());
+    }
+    let mut lines = RenderedLines {
+        // 行尾由调用者决定
+        options: RenderOptions {
+            line_ending: LineEnding::Lf,
+            ..options.clone()
+        },
+        before: VecDeque::new(),
+        art: None,
+        after: VecDeque::new(),
+    };
+    let img = crop_image(Cow::Owned(img), options);
+    let alt = (options.alt_text != AltText::Off)
+        .then(|| describe(name, &img, &expand_template(message, options)));
+
+    if options.alt_text == AltText::Only {
+        // 只输出描述
+    } else if is_streamable(options) {
+        let wrapped = wrap_message(message, options)?;
+        let text: Vec<&str> = wrapped.lines().collect();
+        let actual_width = longest_line(&text, options.ambiguous_width);
+        let bubble_columns = bubble_width(actual_width, options);
+        let (img, indent) = place_art(img, bubble_columns, options);
+        let margin = if options.center_bubble {
+            art_width(img.width(), options).saturating_sub(bubble_columns) / 2
+        } else {
+            0
+        };
+
+        let mut bubble = SmallVec::<[u8; BUFSIZE]>::new();
+        if options.position == BubblePosition::Below {
+            write_tail(&mut bubble, indent, options.bubble, true);
+            write_indented_bubble(&mut bubble, &text, actual_width, margin, options);
+            lines.after = lines.split(&bubble)?;
+        } else {
+            write_indented_bubble(&mut bubble, &text, actual_width, margin, options);
+            write_tail(&mut bubble, indent, options.bubble, false);
+            lines.before = lines.split(&bubble)?;
+        }
+        if lines.fix_levels(&img) {
+            lines.art = Some(ArtRows {
+                img: img.into_owned(),
+                row: 0,
+            });
+        } else {
+            // 分级无法固定时一次转换整张图片
+            let mut art = Vec::new();
+            convert_image_to_text(&img, &lines.options, &mut art)?;
+            let art = lines.split(&art)?;
+            lines.before.extend(art);
+        }
+    } else {
+        // 图片已经裁剪过了
+        let options = RenderOptions {
+            alt_text: AltText::Off,
+            crop: None,
+            ..lines.options.clone()
+        };
+        let mut output = Vec::new();
+        CompiledSprite::compile(img, None, &options)?.say(message, &mut output)?;
+        lines.before = String::from_utf8_lossy(&output)
+            .lines()
+            .map(String::from)
+            .collect();
+    }
+
+    if let Some(alt) = alt {
+        let alt = lines.split(alt.as_bytes())?;
+        lines.after.extend(alt);
+    }
+    Ok(lines)
+}
+
+/// 图片能否逐行转换
+fn is_streamable(options: &RenderOptions) -> bool {
+    options.layout == Layout::Bubble
+        && matches!(
+            options.position,
+            BubblePosition::Above | BubblePosition::Below
+        )
+        && options.frame.is_none()
+        && !is_export(options)
+        && !is_graphics(options)
+}
+
+impl RenderedLines {
+    /// 按照选项处理行尾和左边距，然后拆分为行
+    fn split(&self, text: &[u8]) -> Result<VecDeque<String>> {
+        let mut output = Vec::new();
+        LineWriter::new(&mut output, &self.options).write_all(text)?;
+        Ok(String::from_utf8_lossy(&output)
+            .lines()
+            .map(String::from)
+            .collect())
+    }
+
+    /// 按整张图片计算阈值和明暗分级并写入选项，逐行转换时不会按每一行重新计算；
+    /// 明暗分级无法用 [`ShadeLevels::Custom`] 表示时返回 `false`
+    fn fix_levels(&mut self, img: &DynamicImage) -> bool {
+        let options = &mut self.options;
+        if matches!(
+            options.threshold,
+            Threshold::Percentile(_) | Threshold::Auto
+        ) {
+            let threshold = resolve_threshold(img, options.threshold, options.luminance);
+            options.threshold = Threshold::Absolute(threshold);
+        }
+        if options.shade_levels != ShadeLevels::Histogram {
+            return true;
+        }
+        let table = shade_table(img, options.shade_levels, options.luminance);
+        // 第 n 个分界是级别不超过 n 的最大亮度；亮度 0 已经高于第一级时无法表示
+        let mut bounds = [0; 4];
+        for (level, bound) in bounds.iter_mut().enumerate() {
+            match table.iter().rposition(|&value| value as usize <= level) {
+                Some(value) => *bound = value as u8,
+                None => return false,
+            }
+        }
+        let custom = ShadeLevels::Custom(bounds);
+        options.shade_levels = custom;
+        shade_table(img, custom, options.luminance) == table
+    }
+
+    /// 转换图片中对应下一行的像素，图片已经转换完时返回 `false`
+    fn convert_next_row(&mut self) -> Result<bool> {
+        let Some(art) = &mut self.art else {
+            return Ok(false);
+        };
+        let (width, height) = (art.img.width(), art.img.height());
+        if art.row >= height {
+            self.art = None;
+            return Ok(false);
+        }
+        let rows = pixel_rows_per_line(&self.options).min(height - art.row);
+        let strip = art.img.crop_imm(0, art.row, width, rows);
+        art.row += rows;
+
+        let mut output = Vec::new();
+        convert_image_to_text(&strip, &self.options, &mut output)?;
+        let lines = self.split(&output)?;
+        self.before.extend(lines);
+        Ok(true)
+    }
+}
+
+impl Iterator for RenderedLines {
+    type Item = Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(line) = self.before.pop_front() {
+                return Some(Ok(line));
+            }
+            match self.convert_next_row() {
+                Ok(true) => continue,
+                Ok(false) => return self.after.pop_front().map(Ok),
+                Err(e) => {
+                    // 出错后不再继续转换
+                    self.art = None;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{say_from_dynamic_image_with_options, PixelMode, Rect};
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn test_lines_match_output() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(5, 7, |x, y| {
+            Rgba([
+                x as u8 * 50,
+                y as u8 * 30,
+                100,
+                if (x + y) % 3 == 0 { 0 } else { 255 },
+            ])
+        }));
+        let variants = [
+            RenderOptions::default(),
+            RenderOptions {
+                mode: PixelMode::Braille,
+                position: BubblePosition::Below,
+                margin: 2,
+                ..RenderOptions::default()
+            },
+            RenderOptions {
+                mode: PixelMode::Ansi256,
+                position: BubblePosition::Left,
+                alt_text: AltText::Append,
+                crop: Some(Rect::new(1, 1, 3, 4)),
+                ..RenderOptions::default()
+            },
+            RenderOptions {
+                mode: PixelMode::Monochrome,
+                threshold: Threshold::Auto,
+                ..RenderOptions::default()
+            },
+            RenderOptions {
+                mode: PixelMode::Braille,
+                threshold: Threshold::Percentile(30),
+                ..RenderOptions::default()
+            },
+            RenderOptions {
+                mode: PixelMode::Shade,
+                shade_levels: ShadeLevels::Histogram,
+                ..RenderOptions::default()
+            },
+        ];
+        // 上暗下亮的两色图片：按每一行计算的阈值和分级会与整张图片不同
+        let two_tone = DynamicImage::ImageRgba8(RgbaImage::from_fn(6, 6, |_, y| {
+            Rgba(if y < 3 {
+                [40, 40, 40, 255]
+            } else {
+                [200, 200, 200, 255]
+            })
+        }));
+        for (img, options) in [img, two_tone]
+            .iter()
+            .flat_map(|img| variants.iter().map(move |options| (img, options)))
+        {
+            let mut expected = Vec::new();
+            say_from_dynamic_image_with_options(img.clone(), "hello there", options, &mut expected)
+                .unwrap();
+            let lines = render_lines(img.clone(), "hello there", options).unwrap();
+            let output: String = lines.map(|line| line.unwrap() + "\n").collect();
+            assert_eq!(output, String::from_utf8(expected).unwrap());
+        }
+
+        let options = RenderOptions {
+            backend: OutputBackend::Png,
+            ..RenderOptions::default()
+        };
+        let img = DynamicImage::ImageRgba8(RgbaImage::new(2, 2));
+        let err = render_lines(img, "hi", &options).unwrap_err();
+        assert!(matches!(
+            PixelSaysError::from(err),
+            PixelSaysError::UnsupportedMode(OutputBackend::Png)
+        ));
+    }
+}
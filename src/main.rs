@@ -1,4 +1,4 @@
-use pixel_says::{say_from_image, PixelMode};
+use pixel_says::{detect_background, say_from_image, PixelMode, TerminalBackground};
 use std::env;
 use std::io::{stdout, BufWriter};
 
@@ -9,8 +9,15 @@ fn main() {
         eprintln!("用法: {} <图片路径> <消息> [选项]", args[0]);
         eprintln!("例子: {} test_pixel.png \"Hello from pixels!\"", args[0]);
         eprintln!("选项:");
+        eprintln!("  --ansi256    使用 256 色模式");
+        eprintln!("  --grayscale  使用真彩色灰度模式");
         eprintln!("  --monochrome 使用黑白模式");
         eprintln!("  --invert     使用反色模式（黑白颠倒）");
+        eprintln!("  --shade      使用明暗字符模式（░▒▓█）");
+        eprintln!("  --ascii      使用 ASCII 字符画模式");
+        eprintln!("  --quadrant   使用象限方块模式");
+        eprintln!("  --braille    使用盲文点阵模式");
+        eprintln!("  --adaptive   根据终端背景的明暗自动选择黑白或反色模式");
         return;
     }
     
@@ -19,8 +26,17 @@ fn main() {
     
     let mode = if args.len() > 3 {
         match args[3].as_str() {
+            "--ansi256" => PixelMode::Ansi256,
+            "--grayscale" => PixelMode::Grayscale,
             "--monochrome" => PixelMode::Monochrome,
             "--invert" => PixelMode::Invert,
+            "--shade" => PixelMode::Shade,
+            "--ascii" => PixelMode::Ascii,
+            "--quadrant" => PixelMode::Quadrant,
+            "--braille" => PixelMode::Braille,
+            "--adaptive" => detect_background()
+                .unwrap_or(TerminalBackground::Dark)
+                .monochrome_mode(),
             _ => PixelMode::TrueColor,
         }
     } else {
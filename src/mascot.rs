@@ -0,0 +1,142 @@
+use crate::random::random_index;
+use crate::{say_named, RenderOptions, RenderReport};
+use image::DynamicImage;
+use std::io::{Result, Write};
+
+/// 内置的像素吉祥物，不需要额外的图片文件
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Mascot {
+    /// Rust 的吉祥物螃蟹 Ferris
+    Ferris,
+    /// 眯着眼笑的 Ferris
+    FerrisHappy,
+    /// 戴墨镜的 Ferris
+    FerrisCool,
+    /// 猫
+    Cat,
+    /// 狗
+    Dog,
+    /// 幽灵
+    Ghost,
+}
+
+impl Mascot {
+    /// 所有内置的吉祥物
+    pub const ALL: [Mascot; 6] = [
+        Mascot::Ferris,
+        Mascot::FerrisHappy,
+        Mascot::FerrisCool,
+        Mascot::Cat,
+        Mascot::Dog,
+        Mascot::Ghost,
+    ];
+
+    /// 随机选择一个吉祥物
+    pub fn random() -> Mascot {
+        Mascot::ALL[random_index(Mascot::ALL.len())]
+    }
+
+    /// 可以被 `parse` 识别的名称
+    pub fn name(self) -> &'static str {
+        match self {
+            Mascot::Ferris => "ferris",
+            Mascot::FerrisHappy => "ferris-happy",
+            Mascot::FerrisCool => "ferris-cool",
+            Mascot::Cat => "cat",
+            Mascot::Dog => "dog",
+            Mascot::Ghost => "ghost",
+        }
+    }
+
+    /// 嵌入程序中的 PNG 数据
+    pub fn bytes(self) -> &'static [u8] {
+        match self {
+            Mascot::Ferris => include_bytes!("../assets/mascots/ferris.png"),
+            Mascot::FerrisHappy => include_bytes!("../assets/mascots/ferris-happy.png"),
+            Mascot::FerrisCool => include_bytes!("../assets/mascots/ferris-cool.png"),
+            Mascot::Cat => include_bytes!("../assets/mascots/cat.png"),
+            Mascot::Dog => include_bytes!("../assets/mascots/dog.png"),
+            Mascot::Ghost => include_bytes!("../assets/mascots/ghost.png"),
+        }
+    }
+
+    /// 解码后的图片
+    pub fn image(self) -> DynamicImage {
+        image::load_from_memory(self.bytes()).expect("内置的吉祥物图片是有效的 PNG")
+    }
+}
+
+/// 无法识别的吉祥物名称
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseMascotError(String);
+
+impl std::fmt::Display for ParseMascotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "未知的吉祥物: {}", self.0)
+    }
+}
+
+impl std::error::Error for ParseMascotError {}
+
+impl std::fmt::Display for Mascot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl std::str::FromStr for Mascot {
+    type Err = ParseMascotError;
+
+    /// 名称不区分大小写，如 `ferris`、`ferris-cool`
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let name = s.trim().to_ascii_lowercase();
+        Mascot::ALL
+            .into_iter()
+            .find(|mascot| mascot.name() == name)
+            .ok_or_else(|| ParseMascotError(s.to_string()))
+    }
+}
+
+/// 使用内置的吉祥物创建像素说话效果
+///
+/// # Example
+///
+/// ```rust
+/// use pixel_says::{say_with_mascot, Mascot, RenderOptions};
+///
+/// let mut output = Vec::new();
+/// say_with_mascot(Mascot::Cat, "Meow!", &RenderOptions::default(), &mut output).unwrap();
+/// ```
+pub fn say_with_mascot<W>(
+    mascot: Mascot,
+    message: &str,
+    options: &RenderOptions,
+    writer: W,
+) -> Result<RenderReport>
+where
+    W: Write,
+{
+    say_named(
+        mascot.image(),
+        Some(mascot.name()),
+        message,
+        options,
+        writer,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_mascots() {
+        for mascot in Mascot::ALL {
+            assert_eq!(mascot.name().parse(), Ok(mascot));
+            let mut output = Vec::new();
+            let report = say_with_mascot(mascot, "hi", &RenderOptions::default(), &mut output);
+            assert!(report.unwrap().art_height > 0);
+        }
+        assert!("unicorn".parse::<Mascot>().is_err());
+    }
+}
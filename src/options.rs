@@ -0,0 +1,839 @@
+use crate::{
+    BubbleStyle, Color, ColorPalette, FigletFont, FitStrategy, Frame, Highlight, PixelMode,
+    Template, TextOverlay,
+};
+
+/// 默认允许解码的最大图片宽度（像素）
+pub const DEFAULT_MAX_IMAGE_WIDTH: u32 = 4096;
+/// 默认允许解码的最大图片高度（像素）
+pub const DEFAULT_MAX_IMAGE_HEIGHT: u32 = 4096;
+/// 默认允许解码器分配的最大内存（字节）
+pub const DEFAULT_MAX_ALLOC: u64 = 64 * 1024 * 1024;
+/// 默认缩放后图片的最大宽度和高度（像素），见 [`RenderOptions::max_cells`]
+pub const DEFAULT_MAX_CELLS: (u32, u32) = (80, 80);
+/// 默认的透明度阈值，透明度低于它的像素不显示，见 [`RenderOptions::alpha_threshold`]
+pub const DEFAULT_ALPHA_THRESHOLD: u8 = 128;
+/// 默认的明暗字符，由暗到亮
+pub const DEFAULT_RAMP: &str = " .:-=+*#%@";
+
+/// 图片解码限制
+///
+/// 对应 `image::Limits`，在解码前检查图片尺寸并限制解码器的内存分配，
+/// 避免恶意或过大的图片占用数 GB 内存。`None` 表示不限制。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct DecodeLimits {
+    /// 最大图片宽度
+    pub max_width: Option<u32>,
+    /// 最大图片高度
+    pub max_height: Option<u32>,
+    /// 解码器最多可分配的字节数
+    pub max_alloc: Option<u64>,
+}
+
+impl DecodeLimits {
+    /// 不做任何限制
+    pub fn none() -> Self {
+        DecodeLimits {
+            max_width: None,
+            max_height: None,
+            max_alloc: None,
+        }
+    }
+
+    /// 检查给定的图片尺寸是否在限制之内
+    pub fn allows(&self, width: u32, height: u32) -> bool {
+        self.max_width.is_none_or(|max| width <= max)
+            && self.max_height.is_none_or(|max| height <= max)
+    }
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        DecodeLimits {
+            max_width: Some(DEFAULT_MAX_IMAGE_WIDTH),
+            max_height: Some(DEFAULT_MAX_IMAGE_HEIGHT),
+            max_alloc: Some(DEFAULT_MAX_ALLOC),
+        }
+    }
+}
+
+impl From<DecodeLimits> for image::Limits {
+    fn from(limits: DecodeLimits) -> Self {
+        let mut result = image::Limits::no_limits();
+        result.max_image_width = limits.max_width;
+        result.max_image_height = limits.max_height;
+        result.max_alloc = limits.max_alloc;
+        result
+    }
+}
+
+/// 缩放后某一边不足一个像素时（极端宽高比的图片）的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum AspectFit {
+    /// 把图片直接拉伸到目标尺寸，每个方向至少保留 1 个像素
+    #[default]
+    Squash,
+    /// 保持原始宽高比，用透明像素填充到目标尺寸
+    Pad,
+}
+
+/// 图片中的矩形区域，单位为像素
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rect {
+    /// 左上角的横坐标
+    pub x: u32,
+    /// 左上角的纵坐标
+    pub y: u32,
+    /// 宽度
+    pub width: u32,
+    /// 高度
+    pub height: u32,
+}
+
+impl Rect {
+    /// 以 (`x`, `y`) 为左上角、`width`x`height` 大小的区域
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
+/// 黑白、反色和盲文模式区分亮暗像素的阈值
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Threshold {
+    /// 亮度（0–255）高于这个值的像素算作亮
+    Absolute(u8),
+    /// 按图片中可见像素的亮度分布选择阈值，让大约这个百分比（0–100）的像素算作暗；
+    /// 整体偏暗的图片也能显示出轮廓
+    Percentile(u8),
+    /// 用大津法（Otsu）从亮度直方图中选择让亮暗两类区分最明显的阈值，
+    /// 亮色和暗色的图片都不需要手动调整
+    Auto,
+}
+
+impl Default for Threshold {
+    fn default() -> Self {
+        Threshold::Absolute(128)
+    }
+}
+
+/// 明暗模式中相邻两级字符之间的亮度分界
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum ShadeLevels {
+    /// 把 0–255 的亮度平均分为五级
+    #[default]
+    Even,
+    /// 按图片中可见像素的亮度分布分级，每一级的像素数大致相同；对比度低的图片也有层次
+    Histogram,
+    /// 由暗到亮的四个分界，亮度高于第 n 个分界的像素至少使用第 n + 1 级字符
+    Custom([u8; 4]),
+}
+
+/// 计算像素亮度的方式，影响黑白、反色、盲文、明暗和字符梯度模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Luminance {
+    /// 先把 sRGB 分量转换为线性光强，再按 ITU-R BT.709 加权；鲜艳颜色的中间调不会偏暗
+    #[default]
+    Linear,
+    /// 直接对 sRGB 编码的分量加权，计算更快，与早期版本的输出相同
+    Fast,
+}
+
+/// 缩放时对字符格宽高比（约为 1:2）的修正，让像素在终端中显示为正方形
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum AspectCorrection {
+    /// 按像素模式修正：`██` 和盲文模式的像素本来就接近正方形，不需要修正；
+    /// 每个像素只占一列（`PixelWidth::Single`）或象限模式时把图片高度减半
+    #[default]
+    Auto,
+    /// 不修正，每个像素占一个（或按模式划分的一部分）字符格
+    Off,
+    /// 把图片高度乘以这个系数，无效的值按 1 处理
+    Factor(f32),
+}
+
+/// 消息中终端转义序列（ANSI）的处理策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum AnsiPolicy {
+    /// 去除转义序列和其他控制字符
+    #[default]
+    Strip,
+    /// 保留设置颜色和样式的 SGR 序列，计算宽度时忽略它们，并在每行末尾重置样式；
+    /// 其他转义序列（如修改窗口标题的 OSC）仍然会被去除
+    PassThrough,
+    /// 消息中出现转义序列或控制字符时返回错误
+    Reject,
+}
+
+/// 消息文本的折行方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum WrapMode {
+    /// 逐行折行，保留输入中的每一个换行
+    #[default]
+    Lines,
+    /// 按段落重新排版：段落内的换行被合并，段落之间保留一个空行
+    Paragraphs,
+    /// 逐行折行，同时保留行首缩进、连续的空格和所有空行，适合诗歌和 fortune；
+    /// 折行产生的后续行沿用原行的缩进
+    PreserveNewlines,
+}
+
+/// 消息框与图片之间连接线的位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum TailAnchor {
+    /// 指向消息框与图片重叠部分的中间
+    #[default]
+    Auto,
+    /// 固定缩进的列数（旧版本固定为 8）
+    Fixed(usize),
+    /// 指向消息框的水平中心
+    BubbleCenter,
+    /// 指向缩放后图片的水平中心，与图片的宽窄无关
+    ImageCenter,
+    /// 指向原图中的某一像素列，随图片一起缩放
+    ImageColumn(u32),
+    /// 指向原图中的某个像素（如角色的嘴巴），坐标为 `(x, y)`，随图片一起缩放
+    ///
+    /// 消息框在图片上方时，连接线停在图片顶部，只用到横坐标。
+    Point(u32, u32),
+}
+
+impl TailAnchor {
+    /// 锚定在原图中的哪一像素列
+    pub fn image_column(self) -> Option<u32> {
+        match self {
+            TailAnchor::ImageColumn(column) | TailAnchor::Point(column, _) => Some(column),
+            _ => None,
+        }
+    }
+}
+
+/// 每个像素在终端中占用的宽度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum PixelWidth {
+    /// 每个像素两个字符（`██`），终端中接近正方形
+    #[default]
+    Double,
+    /// 每个像素一个字符（`█`），同样的宽度可以显示两倍的像素，但图片会被横向压扁
+    Single,
+}
+
+impl PixelWidth {
+    /// 每个像素占用的终端列数
+    pub fn columns(self) -> usize {
+        match self {
+            PixelWidth::Double => 2,
+            PixelWidth::Single => 1,
+        }
+    }
+
+    /// 实心像素与空白像素对应的字符
+    pub(crate) fn cells(self) -> (&'static str, &'static str) {
+        match self {
+            PixelWidth::Double => ("██", "  "),
+            PixelWidth::Single => ("█", " "),
+        }
+    }
+}
+
+/// 输出字符集
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum OutputProfile {
+    /// 使用 Unicode 方块字符和 ANSI 转义序列
+    #[default]
+    Unicode,
+    /// 保证输出只包含可打印的 7 位 ASCII 字符和换行：图片使用 ASCII 明暗字符，
+    /// 消息中的非 ASCII 字符替换为 `?`，并在输出时逐字节检查，适用于串口终端、
+    /// 嵌入式设备和古老的邮件系统
+    Ascii,
+    /// 保证输出不包含任何转义序列：彩色的像素模式改用黑白模式，消息不加颜色和样式，
+    /// 仍然可以使用 Unicode 字符，适用于管道、日志文件和设置了 `NO_COLOR` 的环境
+    Plain,
+}
+
+impl OutputProfile {
+    /// 是否允许输出转义序列
+    pub(crate) fn allows_escapes(self) -> bool {
+        self == OutputProfile::Unicode
+    }
+}
+
+/// 图片的输出方式
+///
+/// 图形输出会忽略 `mode` 和 `frame`；`Enclosed` 布局、对话和 ASCII 输出时仍然使用字符。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum OutputBackend {
+    /// 用字符和 ANSI 颜色绘制，由 `mode` 决定具体的字符
+    #[default]
+    Text,
+    /// 用 Sixel 图形绘制真正的位图，适用于 xterm、mlterm、foot 等终端
+    Sixel,
+    /// 用 iTerm2 内联图片协议无损地显示图片，适用于 iTerm2 和 WezTerm
+    Iterm2,
+    /// 图片仍由 `mode` 决定的字符绘制，但整个输出（包括消息框）写成 HTML 的 `<pre>` 片段，
+    /// 颜色用 `<span style>` 表示，可以嵌入网页或发送给聊天机器人
+    Html,
+    /// 与 `Html` 相同，但写成可以任意缩放的 SVG 文档：消息框是文字，图片是彩色矩形，
+    /// 适合 README 徽章和社交网站的预览图
+    Svg,
+    /// 与 `Html` 相同，但用内置的点阵字体绘制成 PNG 图片，可以分享到不显示 ANSI 颜色的地方；
+    /// 需要启用 `png` 功能
+    Png,
+    /// 与 `Html` 相同，但写成按行排列的字符格的 JSON，便于网页前端等其他程序自行绘制，
+    /// 见 [`CellGrid::to_json`](crate::CellGrid::to_json)
+    Json,
+    /// 按照经典 ANSI 画（`.ans`）的惯例输出 ANSI 文本：颜色量化为 16 色（亮色前景使用粗体，
+    /// 背景只有 8 色），字符编码为 CP437，换行为 CRLF，末尾附加 SAUCE 记录，可以用 PabloDraw
+    /// 等查看器打开；CP437 中没有的字符（如盲文和象限方块）替换为 `?`
+    Ans,
+}
+
+/// 东亚“歧义宽度”字符（如 `°`、`§`、`±`、`①`）的显示宽度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum AmbiguousWidth {
+    /// 按一列计算，与大多数西文终端一致
+    #[default]
+    Narrow,
+    /// 按两列计算，适用于设置了歧义字符全角显示的中日韩终端
+    Wide,
+}
+
+/// 输出的换行符
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum LineEnding {
+    /// `\n`
+    #[default]
+    Lf,
+    /// `\r\n`，适用于交给 Windows 工具或放在 SMB 共享上的文件
+    CrLf,
+}
+
+impl LineEnding {
+    /// 换行符的字节
+    pub fn as_bytes(self) -> &'static [u8] {
+        match self {
+            LineEnding::Lf => b"\n",
+            LineEnding::CrLf => b"\r\n",
+        }
+    }
+}
+
+/// 供屏幕阅读器使用的文字描述
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum AltText {
+    /// 不输出描述
+    #[default]
+    Off,
+    /// 在图片之后追加一行描述
+    Append,
+    /// 只输出描述，不输出消息框和图片
+    Only,
+}
+
+/// 图片中角色的朝向
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Facing {
+    /// 朝左
+    Left,
+    /// 朝右
+    Right,
+}
+
+/// 自动调整色阶，改善低对比度图片
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum AutoLevels {
+    /// 不调整
+    #[default]
+    Off,
+    /// 把亮度范围线性拉伸到 0–255
+    Stretch,
+    /// 亮度直方图均衡化
+    Equalize,
+}
+
+/// 在映射为字符之前对颜色有限的模式（黑白、反色、盲文、256 色和 16 色）做抖动，保留渐变
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Dither {
+    /// 不抖动，直接取最接近的颜色
+    #[default]
+    Off,
+    /// Floyd–Steinberg 误差扩散
+    FloydSteinberg,
+    /// 使用 Bayer 矩阵的有序抖动，呈现规则的复古网点效果
+    Ordered(BayerSize),
+}
+
+/// 有序抖动使用的 Bayer 矩阵的大小
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum BayerSize {
+    /// 2x2，网点最粗，只有 5 级灰度
+    Two,
+    /// 4x4，17 级灰度
+    #[default]
+    Four,
+    /// 8x8，65 级灰度
+    Eight,
+}
+
+impl BayerSize {
+    /// 矩阵的边长
+    pub fn size(self) -> usize {
+        match self {
+            BayerSize::Two => 2,
+            BayerSize::Four => 4,
+            BayerSize::Eight => 8,
+        }
+    }
+}
+
+/// 图片的投影效果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct DropShadow {
+    /// 阴影相对图片的偏移（像素），正值表示向右、向下
+    pub offset: (i32, i32),
+    /// 阴影的颜色
+    pub color: Color,
+}
+
+impl Default for DropShadow {
+    fn default() -> Self {
+        DropShadow {
+            offset: (1, 1),
+            color: Color::rgb(40, 40, 40),
+        }
+    }
+}
+
+/// 白平衡调整，让图片的冷暖与终端配色相协调
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct WhiteBalance {
+    /// 目标色温（开尔文）：6500 为不调整，越低越暖，越高越冷
+    pub temperature: u32,
+    /// 绿色与品红之间的偏移，范围 -1.0（偏绿）到 1.0（偏品红），0 为不调整
+    pub tint: f32,
+}
+
+impl Default for WhiteBalance {
+    fn default() -> Self {
+        WhiteBalance {
+            temperature: 6500,
+            tint: 0.0,
+        }
+    }
+}
+
+/// 色觉缺陷模拟，用于检查调色板在终端中是否仍然容易区分
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum ColorBlindness {
+    /// 红色盲
+    Protanopia,
+    /// 绿色盲
+    Deuteranopia,
+    /// 蓝色盲
+    Tritanopia,
+}
+
+/// 消息与图片的排列方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Layout {
+    /// 消息框在上，通过连接线指向下方的图片
+    #[default]
+    Bubble,
+    /// 图片放在边框里，消息作为说明文字放在同一个边框的底部，
+    /// 边框样式取自 `frame`（未设置时使用默认样式）
+    Enclosed,
+}
+
+/// `Layout::Bubble` 时消息框相对于图片的位置，连接线总是指向图片
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum BubblePosition {
+    /// 消息框在图片上方
+    #[default]
+    Above,
+    /// 消息框在图片下方
+    Below,
+    /// 消息框在图片左侧，通过水平的 `-->` 连接线指向图片
+    Left,
+    /// 消息框在图片右侧，通过水平的 `<--` 连接线指向图片
+    Right,
+}
+
+/// 消息框的种类
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum BubbleKind {
+    /// 说话：`<`、`/`、`\` 边框，连接线是 `\`
+    #[default]
+    Say,
+    /// 思考：圆括号边框，连接线是由大到小的 `O`、`o` 气泡
+    Think,
+}
+
+/// 消息框内文字与边框之间的空白
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct BubblePadding {
+    /// 文字左右两侧各空出的列数
+    pub horizontal: usize,
+    /// 文字上下各空出的行数
+    pub vertical: usize,
+}
+
+impl Default for BubblePadding {
+    fn default() -> Self {
+        BubblePadding {
+            horizontal: 1,
+            vertical: 0,
+        }
+    }
+}
+
+/// 消息框中文字的样式，使用 [`OutputProfile::Ascii`] 或 [`OutputProfile::Plain`] 时不生效
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct TextStyle {
+    /// 前景色，`None` 表示使用终端的默认颜色
+    pub color: Option<Color>,
+    /// 是否加粗
+    pub bold: bool,
+    /// 是否斜体
+    pub italic: bool,
+}
+
+impl TextStyle {
+    /// 是否不改变文字的样式
+    pub fn is_plain(&self) -> bool {
+        *self == TextStyle::default()
+    }
+
+    /// 设置样式的转义序列
+    pub(crate) fn escape(&self) -> String {
+        let mut escape = String::new();
+        if self.bold {
+            escape.push_str("\x1b[1m");
+        }
+        if self.italic {
+            escape.push_str("\x1b[3m");
+        }
+        if let Some(color) = self.color {
+            escape.push_str(&color.fg_escape());
+        }
+        escape
+    }
+}
+
+/// 彩虹渐变的消息文字，类似 `lolcat`
+///
+/// 每个字符的色相为 `start_hue + frequency * (列 + 行)`，颜色沿对角线变化。
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Rainbow {
+    /// 每一列（以及每一行）色相变化的度数
+    pub frequency: f32,
+    /// 第一个字符的色相，单位为度，0 为红色
+    pub start_hue: f32,
+}
+
+impl Default for Rainbow {
+    fn default() -> Self {
+        Rainbow {
+            frequency: 10.0,
+            start_hue: 0.0,
+        }
+    }
+}
+
+/// 渲染选项
+///
+/// 汇总了渲染时可调整的参数，配合 `say_from_image_with_options` 等函数使用。
+///
+/// 启用 `serde` 功能后可以序列化为 JSON、TOML 等格式保存预设，缺少的字段使用默认值；
+/// 模板、突出显示和 FIGlet 字体不会被保存。`mode` 为 [`PixelMode::Custom`] 时无法序列化，
+/// 这要到运行时才会以序列化器的错误报告出来，保存预设前需要先换成其他模式。
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct RenderOptions {
+    /// 像素转换模式
+    pub mode: PixelMode,
+    /// 文本的最大宽度
+    pub max_width: usize,
+    /// 图片解码限制
+    pub limits: DecodeLimits,
+    /// 只绘制原图中的这个区域；超出图片的部分会被忽略，至少保留 1 个像素
+    pub crop: Option<Rect>,
+    /// 极端宽高比图片的缩放方式
+    pub aspect_fit: AspectFit,
+    /// 转换前把图片等比缩小到不超过这个宽度和高度（像素），`None` 表示保持原始尺寸；
+    /// 每个像素占用的字符格取决于 `mode` 和 `pixel_width`
+    pub max_cells: Option<(u32, u32)>,
+    /// 字符格宽高比的修正
+    pub aspect_correction: AspectCorrection,
+    /// 黑白、反色和盲文模式区分亮暗像素的阈值
+    pub threshold: Threshold,
+    /// 计算亮度的方式
+    pub luminance: Luminance,
+    /// 明暗模式的分级方式
+    pub shade_levels: ShadeLevels,
+    /// 透明度（alpha）低于这个值的像素按透明处理，不显示
+    pub alpha_threshold: u8,
+    /// 设置后，其余半透明的像素先与这个颜色混合再显示，而不是当作完全不透明；
+    /// 配合较低的 `alpha_threshold` 可以保留抗锯齿的边缘
+    pub alpha_blend: Option<Color>,
+    /// 消息模板中占位符的取值，`None` 表示不替换占位符
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub template: Option<Template>,
+    /// 消息中转义序列的处理策略
+    pub ansi: AnsiPolicy,
+    /// 消息文本的折行方式
+    pub wrap: WrapMode,
+    /// 消息中歧义宽度字符的宽度，影响折行和消息框的对齐
+    pub ambiguous_width: AmbiguousWidth,
+    /// 在消息框中突出显示的文本
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub highlights: Vec<Highlight>,
+    /// 连接线的位置
+    pub tail: TailAnchor,
+    /// [`PixelMode::Ascii`] 使用的字符，由暗到亮排列
+    pub ramp: String,
+    /// 每个像素的宽度
+    pub pixel_width: PixelWidth,
+    /// 图片的输出方式
+    pub backend: OutputBackend,
+    /// 用背景色（`\x1b[48;...m` 加空格）而不是前景色的 `██` 绘制像素，
+    /// 在方块字符之间有缝隙的字体中也能显示为完整的色块；只影响真彩色、256 色和 16 色模式
+    pub background_cells: bool,
+    /// 在每个换行符之前输出 `\x1b[K` 清除行尾，覆盖已有内容或通过
+    /// `less -R` 分页时避免残留的颜色
+    pub clear_to_eol: bool,
+    /// 输出的换行符；输出总是不带 BOM 的 UTF-8，消息开头的 BOM 会被去掉
+    pub line_ending: LineEnding,
+    /// 输出字符集
+    pub profile: OutputProfile,
+    /// 原图中角色的朝向；设置后，如果消息框位于另一侧，图片会被水平翻转，
+    /// 让角色面向自己说的话
+    pub face_bubble: Option<Facing>,
+    /// 在量化为字符或颜色之前自动调整色阶
+    pub auto_levels: AutoLevels,
+    /// 颜色有限的模式使用的抖动算法
+    pub dither: Dither,
+    /// 设置后，真彩色和象限模式只使用调色板中的颜色，可以配合 `dither` 使用
+    pub palette: Option<ColorPalette>,
+    /// 在整个图片区域（包括透明像素）下面铺上的背景色，让图片在杂乱的终端内容上也清晰可见
+    pub background: Option<Color>,
+    /// 在图片下方合成的投影
+    pub shadow: Option<DropShadow>,
+    /// 围绕图片的装饰边框
+    pub frame: Option<Frame>,
+    /// 色觉缺陷模拟
+    pub color_blindness: Option<ColorBlindness>,
+    /// 白平衡调整
+    pub white_balance: Option<WhiteBalance>,
+    /// 亮度偏移，范围 -1.0（全黑）到 1.0（全白），0 为不调整
+    pub brightness: f32,
+    /// 对比度系数，以中灰为中心拉开亮暗，1.0 为不调整；深色背景上常常需要调高
+    pub contrast: f32,
+    /// 饱和度系数，0 为灰度，1.0 为不调整
+    pub saturation: f32,
+    /// 色相旋转的角度（度），0 为不调整
+    pub hue_rotate: f32,
+    /// 设置后把每个通道量化为这么多级（至少 2 级），颜色更少、风格更复古，
+    /// 输出的转义序列也更少。在其他颜色调整之后、抖动之前进行
+    pub posterize: Option<u8>,
+    /// 设置后按亮度把图片重新着色为这个颜色，黑色和白色保持不变；
+    /// 同一个角色可以在脚本里按状态显示不同的颜色
+    pub tint: Option<Color>,
+    /// 消息与图片的排列方式
+    pub layout: Layout,
+    /// 消息框相对于图片的位置
+    pub position: BubblePosition,
+    /// 消息框比图片窄时，让消息框在图片上方或下方水平居中
+    pub center_bubble: bool,
+    /// 说话还是思考
+    pub bubble: BubbleKind,
+    /// 消息框边框的字符
+    pub bubble_style: BubbleStyle,
+    /// 消息文字的样式
+    pub text_style: TextStyle,
+    /// 彩虹渐变的消息文字，设置后代替 `text_style` 中的颜色；突出显示的文本保持原样
+    pub rainbow: Option<Rainbow>,
+    /// 用 FIGlet 字体把消息绘制成大字，消息框随大字的尺寸变化；设置后不再折行和突出显示
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub banner: Option<FigletFont>,
+    /// 嵌在消息框顶部边框中的说话者名字，如 `┌─ Ferris ───┐`
+    pub speaker: Option<String>,
+    /// 消息框的内边距
+    pub padding: BubblePadding,
+    /// 整个输出左侧空出的列数
+    pub margin: usize,
+    /// 印在图片上的文字
+    pub overlay: Option<TextOverlay>,
+    /// 供屏幕阅读器使用的文字描述，包括图片名称、尺寸、主要颜色和纯文本消息
+    pub alt_text: AltText,
+    /// 播放动画时终端大小变化后按这种方式重新适应终端，`None` 表示保持原来的大小
+    pub fit: Option<FitStrategy>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            mode: PixelMode::TrueColor,
+            max_width: 40,
+            limits: DecodeLimits::default(),
+            crop: None,
+            aspect_fit: AspectFit::default(),
+            max_cells: Some(DEFAULT_MAX_CELLS),
+            aspect_correction: AspectCorrection::default(),
+            threshold: Threshold::default(),
+            luminance: Luminance::default(),
+            shade_levels: ShadeLevels::default(),
+            alpha_threshold: DEFAULT_ALPHA_THRESHOLD,
+            alpha_blend: None,
+            template: None,
+            ansi: AnsiPolicy::default(),
+            wrap: WrapMode::default(),
+            ambiguous_width: AmbiguousWidth::default(),
+            highlights: Vec::new(),
+            tail: TailAnchor::default(),
+            ramp: DEFAULT_RAMP.to_string(),
+            pixel_width: PixelWidth::default(),
+            backend: OutputBackend::default(),
+            background_cells: false,
+            clear_to_eol: false,
+            line_ending: LineEnding::default(),
+            profile: OutputProfile::default(),
+            face_bubble: None,
+            auto_levels: AutoLevels::default(),
+            dither: Dither::default(),
+            palette: None,
+            background: None,
+            shadow: None,
+            frame: None,
+            color_blindness: None,
+            white_balance: None,
+            brightness: 0.0,
+            contrast: 1.0,
+            saturation: 1.0,
+            hue_rotate: 0.0,
+            tint: None,
+            posterize: None,
+            layout: Layout::default(),
+            position: BubblePosition::default(),
+            center_bubble: false,
+            bubble: BubbleKind::default(),
+            bubble_style: BubbleStyle::default(),
+            text_style: TextStyle::default(),
+            rainbow: None,
+            banner: None,
+            speaker: None,
+            padding: BubblePadding::default(),
+            margin: 0,
+            overlay: None,
+            alt_text: AltText::default(),
+            fit: None,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serde_presets() {
+        let preset = r##"{
+            "mode": "ansi256",
+            "max_width": 30,
+            "dither": { "ordered": "four" },
+            "bubble_style": { "top": ["+", "-", "+"] },
+            "text_style": { "color": "#ff8000", "bold": true }
+        }"##;
+        let options: RenderOptions = serde_json::from_str(preset).unwrap();
+        assert_eq!(options.mode, PixelMode::Ansi256);
+        assert_eq!(options.max_width, 30);
+        assert_eq!(options.dither, Dither::Ordered(BayerSize::Four));
+        assert_eq!(options.bubble_style.top, ['+', '-', '+']);
+        assert_eq!(options.bubble_style.left, BubbleStyle::ASCII.left);
+        assert_eq!(options.text_style.color, Some(Color::rgb(255, 128, 0)));
+        assert_eq!(options.layout, Layout::Bubble);
+
+        let json = serde_json::to_string(&options).unwrap();
+        let restored: RenderOptions = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.text_style, options.text_style);
+        assert!(json.contains(r##""color":"#ff8000""##));
+    }
+
+    #[test]
+    fn test_serde_custom_mode_fails() {
+        struct Blank;
+        impl crate::PixelRenderer for Blank {
+            fn cell(&self, _: image::Rgba<u8>, _: u32, _: u32) -> std::borrow::Cow<'_, str> {
+                "  ".into()
+            }
+        }
+
+        let options = RenderOptions {
+            mode: PixelMode::custom(Blank),
+            ..RenderOptions::default()
+        };
+        let err = serde_json::to_string(&options).unwrap_err();
+        assert!(err.to_string().contains("Custom"), "{}", err);
+    }
+}
@@ -0,0 +1,192 @@
+use crate::{LineEnding, OutputProfile, PixelSaysError, RenderOptions};
+use std::io::{Result, Write};
+
+/// 清除光标到行尾的内容
+const CLEAR_TO_EOL: &[u8] = b"\x1b[K";
+
+/// 在每个换行符之前插入行尾处理的输出包装
+///
+/// 渲染代码统一只写 `\n`，由这里根据选项决定行尾的具体形式。
+/// 使用 [`OutputProfile::Ascii`] 时还会检查输出是否只包含可打印的 7 位 ASCII 字符。
+pub(crate) struct LineWriter<W> {
+    inner: W,
+    clear_to_eol: bool,
+    line_ending: LineEnding,
+    ascii_only: bool,
+    margin: usize,
+    at_line_start: bool,
+}
+
+impl<W: Write> LineWriter<W> {
+    pub(crate) fn new(inner: W, options: &RenderOptions) -> Self {
+        let ascii_only = options.profile == OutputProfile::Ascii;
+        LineWriter {
+            inner,
+            clear_to_eol: options.clear_to_eol && options.profile.allows_escapes(),
+            line_ending: options.line_ending,
+            ascii_only,
+            margin: options.margin,
+            at_line_start: true,
+        }
+    }
+
+    fn is_passthrough(&self) -> bool {
+        !self.clear_to_eol && self.line_ending == LineEnding::Lf && self.margin == 0
+    }
+
+    /// 在一行的第一段内容之前输出左边距，空行不加，避免行尾空白
+    fn write_line_part(&mut self, part: &[u8]) -> Result<()> {
+        if part.is_empty() {
+            return Ok(());
+        }
+        if self.at_line_start {
+            self.inner.write_all(&b" ".repeat(self.margin))?;
+            self.at_line_start = false;
+        }
+        self.inner.write_all(part)
+    }
+}
+
+impl<W: Write> Write for LineWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        if self.ascii_only {
+            if let Some(byte) = buf
+                .iter()
+                .find(|&&b| b != b'\n' && !(b' '..=b'~').contains(&b))
+            {
+                return Err(PixelSaysError::NonAsciiOutput(*byte).into());
+            }
+        }
+        if self.is_passthrough() {
+            return self.inner.write(buf);
+        }
+
+        let mut rest = buf;
+        while let Some(pos) = rest.iter().position(|&b| b == b'\n') {
+            self.write_line_part(&rest[..pos])?;
+            if self.clear_to_eol {
+                self.inner.write_all(CLEAR_TO_EOL)?;
+            }
+            self.inner.write_all(self.line_ending.as_bytes())?;
+            self.at_line_start = true;
+            rest = &rest[pos + 1..];
+        }
+        self.write_line_part(rest)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// 把同一份输出同时写到多个目标，如终端和日志文件，只需渲染一次
+///
+/// 每次写入都会完整地写到所有目标；任何一个目标出错时立即返回该错误。
+#[derive(Default)]
+pub struct MultiWriter<'a> {
+    writers: Vec<&'a mut dyn Write>,
+}
+
+impl<'a> MultiWriter<'a> {
+    /// 没有任何目标的输出
+    pub fn new() -> Self {
+        MultiWriter::default()
+    }
+
+    /// 添加一个输出目标
+    pub fn push(&mut self, writer: &'a mut dyn Write) -> &mut Self {
+        self.writers.push(writer);
+        self
+    }
+}
+
+impl Write for MultiWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        for writer in &mut self.writers {
+            writer.write_all(buf)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        for writer in &mut self.writers {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clear_to_eol_before_newlines() {
+        let options = RenderOptions {
+            clear_to_eol: true,
+            ..RenderOptions::default()
+        };
+        let mut output = Vec::new();
+        let mut writer = LineWriter::new(&mut output, &options);
+        writer.write_all(b"one\ntwo\n").unwrap();
+
+        assert_eq!(output, b"one\x1b[K\ntwo\x1b[K\n");
+    }
+
+    #[test]
+    fn test_crlf_line_endings() {
+        let options = RenderOptions {
+            line_ending: LineEnding::CrLf,
+            clear_to_eol: true,
+            ..RenderOptions::default()
+        };
+        let mut output = Vec::new();
+        let mut writer = LineWriter::new(&mut output, &options);
+        writer.write_all(b"one\ntwo\n").unwrap();
+
+        assert_eq!(output, b"one\x1b[K\r\ntwo\x1b[K\r\n");
+    }
+
+    #[test]
+    fn test_left_margin() {
+        let options = RenderOptions {
+            margin: 2,
+            ..RenderOptions::default()
+        };
+        let mut output = Vec::new();
+        let mut writer = LineWriter::new(&mut output, &options);
+        writer.write_all(b"one\n\ntw").unwrap();
+        writer.write_all(b"o\n").unwrap();
+
+        assert_eq!(output, b"  one\n\n  two\n");
+    }
+
+    #[test]
+    fn test_ascii_profile_rejects_escapes() {
+        let options = RenderOptions {
+            profile: OutputProfile::Ascii,
+            clear_to_eol: true,
+            ..RenderOptions::default()
+        };
+        let mut output = Vec::new();
+        let mut writer = LineWriter::new(&mut output, &options);
+        writer.write_all(b"plain\n").unwrap();
+        assert!(writer.write_all(b"\x1b[31mred\n").is_err());
+        assert!(writer.write_all("██\n".as_bytes()).is_err());
+
+        assert_eq!(output, b"plain\n");
+    }
+
+    #[test]
+    fn test_multi_writer() {
+        let (mut first, mut second) = (Vec::new(), Vec::new());
+        let mut writer = MultiWriter::new();
+        writer.push(&mut first).push(&mut second);
+        writer.write_all(b"hello\n").unwrap();
+        writer.flush().unwrap();
+
+        assert_eq!(first, b"hello\n");
+        assert_eq!(second, b"hello\n");
+    }
+}
@@ -0,0 +1,158 @@
+use crate::Color;
+use image::{Rgba, RgbaImage};
+
+/// 字形的宽度和高度（像素）
+const GLYPH_WIDTH: u32 = 3;
+const GLYPH_HEIGHT: u32 = 5;
+
+/// 文字所在的角落
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Corner {
+    /// 左上角
+    TopLeft,
+    /// 右上角
+    TopRight,
+    /// 左下角
+    #[default]
+    BottomLeft,
+    /// 右下角
+    BottomRight,
+}
+
+/// 在转换前印在图片上的一小段文字，如帧号、名字或水印
+///
+/// 使用内置的 3x5 像素字体，只支持数字、英文字母（不区分大小写）和少量标点，
+/// 其他字符显示为 `?`。文字画在缩放之后的图片上，超出图片的部分会被裁掉。
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TextOverlay {
+    /// 文字内容
+    pub text: String,
+    /// 文字颜色
+    pub color: Color,
+    /// 文字所在的角落
+    pub corner: Corner,
+}
+
+impl TextOverlay {
+    /// 白色文字，位于左下角
+    pub fn new(text: impl Into<String>) -> Self {
+        TextOverlay {
+            text: text.into(),
+            color: Color::rgb(255, 255, 255),
+            corner: Corner::default(),
+        }
+    }
+}
+
+/// 字形的 5 行，每行低 3 位从左到右表示像素
+pub(crate) fn glyph(c: char) -> [u8; 5] {
+    match c.to_ascii_uppercase() {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        ' ' => [0b000; 5],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '!' => [0b010, 0b010, 0b010, 0b000, 0b010],
+        '#' => [0b101, 0b111, 0b101, 0b111, 0b101],
+        _ => [0b111, 0b001, 0b010, 0b000, 0b010],
+    }
+}
+
+/// 把文字印在图片上，字符之间空一列像素
+pub(crate) fn stamp_text(img: &mut RgbaImage, overlay: &TextOverlay) {
+    let count = overlay.text.chars().count() as u32;
+    if count == 0 {
+        return;
+    }
+    let text_width = count * (GLYPH_WIDTH + 1) - 1;
+    let left = match overlay.corner {
+        Corner::TopLeft | Corner::BottomLeft => 0,
+        Corner::TopRight | Corner::BottomRight => img.width().saturating_sub(text_width),
+    };
+    let top = match overlay.corner {
+        Corner::TopLeft | Corner::TopRight => 0,
+        Corner::BottomLeft | Corner::BottomRight => img.height().saturating_sub(GLYPH_HEIGHT),
+    };
+
+    let color = overlay.color;
+    let pixel = Rgba([color.r, color.g, color.b, 255]);
+    for (i, c) in overlay.text.chars().enumerate() {
+        let x0 = left + i as u32 * (GLYPH_WIDTH + 1);
+        for (dy, row) in glyph(c).into_iter().enumerate() {
+            for dx in 0..GLYPH_WIDTH {
+                let (x, y) = (x0 + dx, top + dy as u32);
+                if row & (0b100 >> dx) != 0 && x < img.width() && y < img.height() {
+                    img.put_pixel(x, y, pixel);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stamp_text_in_corner() {
+        let mut img = RgbaImage::new(8, 6);
+        let overlay = TextOverlay {
+            corner: Corner::BottomRight,
+            ..TextOverlay::new("1")
+        };
+        stamp_text(&mut img, &overlay);
+
+        let rows: Vec<String> = (0..6)
+            .map(|y| {
+                (0..8)
+                    .map(|x| if img.get_pixel(x, y)[3] > 0 { '#' } else { '.' })
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            ["........", "......#.", ".....##.", "......#.", "......#.", ".....###"]
+        );
+    }
+}
@@ -0,0 +1,158 @@
+use crate::{Color, PixelSaysError};
+use std::io::{Error, Result};
+
+/// 由十六进制数写成的颜色，如 `0xff8000`
+fn hex(value: u32) -> Color {
+    Color::rgb((value >> 16) as u8, (value >> 8) as u8, value as u8)
+}
+
+/// 初代 Game Boy 的四级绿色
+const GAME_BOY: [u32; 4] = [0x0f380f, 0x306230, 0x8bac0f, 0x9bbc0f];
+
+/// NES（2C02）调色板，去掉了重复的黑色和白色
+const NES: [u32; 54] = [
+    0x747474, 0x24188c, 0x0000a8, 0x44009c, 0x8c0074, 0xa80010, 0xa40000, 0x7c0800, 0x402c00,
+    0x004400, 0x005000, 0x003c14, 0x183c5c, 0x000000, 0xbcbcbc, 0x0070ec, 0x2038ec, 0x8000f0,
+    0xbc00bc, 0xe40058, 0xd82800, 0xc84c0c, 0x887000, 0x009400, 0x00a800, 0x009038, 0x008088,
+    0xfcfcfc, 0x3cbcfc, 0x5c94fc, 0xcc88fc, 0xf478fc, 0xfc74b4, 0xfc7460, 0xfc9838, 0xf0bc3c,
+    0x80d010, 0x4cdc48, 0x58f898, 0x00e8d8, 0x787878, 0xa8e4fc, 0xc4d4fc, 0xd4c8fc, 0xfcc4fc,
+    0xfcc4d8, 0xfcbcb0, 0xfcd8a8, 0xfce4a0, 0xe0fca0, 0xa8f0bc, 0xb0fccc, 0x9cfcf0, 0xc4c4c4,
+];
+
+/// PICO-8 的 16 色调色板
+const PICO8: [u32; 16] = [
+    0x000000, 0x1d2b53, 0x7e2553, 0x008751, 0xab5236, 0x5f574f, 0xc2c3c7, 0xfff1e8, 0xff004d,
+    0xffa300, 0xffec27, 0x00e436, 0x29adff, 0x83769c, 0xff77a8, 0xffccaa,
+];
+
+/// 量化图片时使用的一组颜色，见 `RenderOptions::palette`
+///
+/// 可以从 GIMP 调色板（`.gpl`）或每行一个十六进制颜色的文本中读取，
+/// 也可以使用内置的复古调色板。
+///
+/// # Example
+///
+/// ```rust
+/// use pixel_says::{Color, ColorPalette};
+///
+/// let palette = ColorPalette::parse("#000000\n#ffffff\n").unwrap();
+/// assert_eq!(palette.nearest(Color::rgb(200, 180, 190)), Color::rgb(255, 255, 255));
+/// assert_eq!(ColorPalette::builtin("pico8").unwrap().colors().len(), 16);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct ColorPalette {
+    colors: Vec<Color>,
+}
+
+impl ColorPalette {
+    /// 内置调色板的名称
+    pub const BUILTIN: [&'static str; 3] = ["gameboy", "nes", "pico8"];
+
+    /// 由颜色列表创建调色板
+    pub fn new(colors: Vec<Color>) -> Self {
+        ColorPalette { colors }
+    }
+
+    /// 按名称（不区分大小写）取得内置调色板，见 [`ColorPalette::BUILTIN`]
+    pub fn builtin(name: &str) -> Option<Self> {
+        let colors: &[u32] = match name.trim().to_ascii_lowercase().as_str() {
+            "gameboy" => &GAME_BOY,
+            "nes" => &NES,
+            "pico8" => &PICO8,
+            _ => return None,
+        };
+        Some(ColorPalette::new(
+            colors.iter().map(|&value| hex(value)).collect(),
+        ))
+    }
+
+    /// 解析 GIMP 调色板（`.gpl`），或每行一个 `#rrggbb`（`#` 可以省略）的颜色列表
+    ///
+    /// 颜色列表中无法解析为颜色的 `#` 开头的行和 `;` 开头的行是注释。
+    pub fn parse(data: &str) -> Result<Self> {
+        let invalid = |reason: String| Error::from(PixelSaysError::InvalidPalette(reason));
+        let mut lines = data.lines().map(str::trim).peekable();
+        let gimp = lines.next_if(|line| *line == "GIMP Palette").is_some();
+
+        let mut colors = Vec::new();
+        for line in lines {
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+            if gimp {
+                if line.starts_with('#')
+                    || line.starts_with("Name:")
+                    || line.starts_with("Columns:")
+                {
+                    continue;
+                }
+                // 每行是三个十进制分量，之后可以跟颜色的名称
+                let channels: Vec<u8> = line
+                    .split_whitespace()
+                    .take(3)
+                    .map_while(|field| field.parse().ok())
+                    .collect();
+                match channels[..] {
+                    [r, g, b] => colors.push(Color::rgb(r, g, b)),
+                    _ => return Err(invalid(format!("无法解析的行: {}", line))),
+                }
+            } else {
+                let field = line.split_whitespace().next().unwrap_or_default();
+                match field.parse() {
+                    Ok(color) => colors.push(color),
+                    Err(_) if line.starts_with('#') => continue,
+                    Err(_) => return Err(invalid(format!("无法解析的行: {}", line))),
+                }
+            }
+        }
+
+        if colors.is_empty() {
+            return Err(invalid("调色板中没有颜色".to_string()));
+        }
+        Ok(ColorPalette::new(colors))
+    }
+
+    /// 调色板中的颜色
+    pub fn colors(&self) -> &[Color] {
+        &self.colors
+    }
+
+    /// 调色板中与 `color` 最接近的颜色，调色板为空时返回 `color` 本身
+    pub fn nearest(&self, color: Color) -> Color {
+        self.colors
+            .iter()
+            .copied()
+            .min_by_key(|candidate| candidate.distance(color))
+            .unwrap_or(color)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_palette() {
+        let gimp = "GIMP Palette\nName: Test\nColumns: 2\n# comment\n  0   0   0\tBlack\n\
+                    255 128 0 Orange\n";
+        assert_eq!(
+            ColorPalette::parse(gimp).unwrap().colors(),
+            [Color::rgb(0, 0, 0), Color::rgb(255, 128, 0)]
+        );
+
+        let list = "; paint.net style\n# hex list\n#102030\nff8000\n";
+        assert_eq!(
+            ColorPalette::parse(list).unwrap().colors(),
+            [Color::rgb(16, 32, 48), Color::rgb(255, 128, 0)]
+        );
+
+        assert!(ColorPalette::parse("GIMP Palette\n1 2\n").is_err());
+        assert!(ColorPalette::parse("not a color\n").is_err());
+        assert!(ColorPalette::parse("# only comments\n").is_err());
+        for name in ColorPalette::BUILTIN {
+            assert!(ColorPalette::builtin(name).is_some());
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use crate::cells::{draw_shapes, fill_rect, DEFAULT_FG};
+use crate::{Cell, CellGrid, Color, PixelSaysError};
+use image::{ImageFormat, Rgba, RgbaImage};
+use std::collections::HashMap;
+use std::io::{Cursor, Result};
+use std::sync::OnceLock;
+
+/// 点阵字体中一个字符格的宽度和高度（像素）
+const CELL_WIDTH: u32 = 8;
+const CELL_HEIGHT: u32 = 16;
+
+/// 没有颜色的背景按黑色绘制
+const DEFAULT_BG: Color = Color::rgb(0, 0, 0);
+
+/// 内置的点阵字体，每个字形 16 行，每行一个字节，最高位在左
+fn font() -> &'static HashMap<char, [u8; 16]> {
+    static FONT: OnceLock<HashMap<char, [u8; 16]>> = OnceLock::new();
+    FONT.get_or_init(|| parse_hex(include_str!("../assets/fonts/mono8x16.hex")))
+}
+
+/// 解析 GNU Unifont 的 `.hex` 格式（`码位:32 个十六进制数字`），`#` 开头的行是注释
+fn parse_hex(data: &str) -> HashMap<char, [u8; 16]> {
+    let mut glyphs = HashMap::new();
+    for line in data.lines().filter(|line| !line.starts_with('#')) {
+        let Some((code, bits)) = line.split_once(':') else {
+            continue;
+        };
+        let Some(ch) = u32::from_str_radix(code, 16).ok().and_then(char::from_u32) else {
+            continue;
+        };
+        let mut rows = [0; 16];
+        for (row, byte) in rows.iter_mut().zip(bits.as_bytes().chunks(2)) {
+            *row = std::str::from_utf8(byte)
+                .ok()
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .unwrap_or(0);
+        }
+        glyphs.insert(ch, rows);
+    }
+    glyphs
+}
+
+impl CellGrid {
+    /// 用内置的 8x16 点阵字体把字符格绘制为一张不透明的图片
+    ///
+    /// 方块和明暗字符按形状绘制，盲文字符按点绘制，
+    /// 字体中没有的字符显示为 `?`。没有颜色的前景和背景按白字黑底绘制。
+    pub fn to_bitmap(&self) -> RgbaImage {
+        let mut img = RgbaImage::new(
+            self.width() as u32 * CELL_WIDTH,
+            self.height() as u32 * CELL_HEIGHT,
+        );
+        for (y, row) in self.rows().enumerate() {
+            for (x, cell) in row.iter().enumerate() {
+                let cell = Cell {
+                    bg: cell.bg.or(Some(DEFAULT_BG)),
+                    ..*cell
+                };
+                let origin = (x as u32 * CELL_WIDTH, y as u32 * CELL_HEIGHT);
+                if let Some(ch) = draw_shapes(&mut img, &cell, origin, (CELL_WIDTH, CELL_HEIGHT)) {
+                    let fg = cell.fg.unwrap_or(DEFAULT_FG);
+                    draw_glyph(&mut img, ch, origin, Rgba([fg.r, fg.g, fg.b, 255]));
+                }
+            }
+        }
+        img
+    }
+}
+
+/// 在字符格中绘制一个字符
+fn draw_glyph(img: &mut RgbaImage, ch: char, (left, top): (u32, u32), pixel: Rgba<u8>) {
+    if let Some(dots) = braille_dots(ch) {
+        // 盲文的 2x4 个点，每个点 2x2 像素
+        for (bit, (column, row)) in BRAILLE_DOTS.into_iter().enumerate() {
+            if dots & 1 << bit != 0 {
+                let (x, y) = (left + 1 + column * 4, top + 1 + row * 4);
+                fill_rect(img, (x, y), (2, 2), pixel);
+            }
+        }
+        return;
+    }
+    let glyphs = font();
+    let Some(rows) = glyphs.get(&ch).or_else(|| glyphs.get(&'?')) else {
+        return;
+    };
+    for (dy, row) in rows.iter().enumerate() {
+        for dx in 0..CELL_WIDTH {
+            if row & (0x80 >> dx) != 0 {
+                img.put_pixel(left + dx, top + dy as u32, pixel);
+            }
+        }
+    }
+}
+
+/// 盲文字符中每一位对应的点（列, 行）
+const BRAILLE_DOTS: [(u32, u32); 8] = [
+    (0, 0),
+    (0, 1),
+    (0, 2),
+    (1, 0),
+    (1, 1),
+    (1, 2),
+    (0, 3),
+    (1, 3),
+];
+
+/// 盲文字符亮起的点，不是盲文字符时返回 `None`
+fn braille_dots(ch: char) -> Option<u8> {
+    (ch as u32)
+        .checked_sub(0x2800)
+        .filter(|&dots| dots < 0x100)
+        .map(|dots| dots as u8)
+}
+
+/// 把字符格绘制为 PNG 图片
+pub(crate) fn encode_png(grid: &CellGrid) -> Result<Vec<u8>> {
+    let mut png = Vec::new();
+    grid.to_bitmap()
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .map_err(PixelSaysError::ImageEncode)?;
+    Ok(png)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grid_to_bitmap() {
+        let grid = CellGrid::from_ansi("A\x1b[38;2;255;0;0m▀\x1b[0m⠁\n");
+        let img = grid.to_bitmap();
+        assert_eq!(img.dimensions(), (24, 16));
+        // 没有颜色的背景是黑色，文字是白色
+        assert_eq!(img.get_pixel(0, 0), &Rgba([0, 0, 0, 255]));
+        assert!(img
+            .pixels()
+            .any(|pixel| pixel == &Rgba([255, 255, 255, 255])));
+        assert_eq!(img.get_pixel(8, 0), &Rgba([255, 0, 0, 255]));
+        assert_eq!(img.get_pixel(8, 15), &Rgba([0, 0, 0, 255]));
+        // 盲文的第一个点
+        assert_eq!(img.get_pixel(17, 1), &Rgba([255, 255, 255, 255]));
+        assert_eq!(img.get_pixel(21, 1), &Rgba([0, 0, 0, 255]));
+
+        let png = encode_png(&grid).unwrap();
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+    }
+}
@@ -0,0 +1,87 @@
+use crate::PixelSaysError;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io::Result;
+use std::path::{Path, PathBuf};
+
+/// 可以被识别为图片的扩展名
+#[cfg(feature = "svg")]
+const IMAGE_EXTENSIONS: [&str; 9] = [
+    "png", "jpg", "jpeg", "gif", "webp", "bmp", "ico", "svg", "svgz",
+];
+/// 可以被识别为图片的扩展名，没有启用 `svg` 功能时无法读取 SVG
+#[cfg(not(feature = "svg"))]
+const IMAGE_EXTENSIONS: [&str; 7] = ["png", "jpg", "jpeg", "gif", "webp", "bmp", "ico"];
+
+/// `0..len` 中的随机数，`len` 不能为 0
+///
+/// 只用于挑选图片，不需要密码学意义上的随机：标准库为每个 `RandomState`
+/// 生成了随机的种子，足够每次运行得到不同的结果。
+pub(crate) fn random_index(len: usize) -> usize {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_usize(len);
+    (hasher.finish() % len as u64) as usize
+}
+
+/// 从目录中随机选择一张图片（按扩展名判断，不包括子目录）
+///
+/// 目录中没有图片时返回 [`PixelSaysError::NoImages`]（`ErrorKind::NotFound`）。
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use pixel_says::{pick_random_image, say_from_image_with_options, RenderOptions};
+/// use std::io::stdout;
+///
+/// let image = pick_random_image("/usr/share/pixel-says").unwrap();
+/// say_from_image_with_options(image, "Welcome back!", &RenderOptions::default(), stdout())
+///     .unwrap();
+/// ```
+pub fn pick_random_image<P>(dir: P) -> Result<PathBuf>
+where
+    P: AsRef<Path>,
+{
+    let dir = dir.as_ref();
+    let mut images = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && is_image_path(&path) {
+            images.push(path);
+        }
+    }
+    if images.is_empty() {
+        return Err(PixelSaysError::NoImages(dir.to_path_buf()).into());
+    }
+    let index = random_index(images.len());
+    Ok(images.swap_remove(index))
+}
+
+fn is_image_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            IMAGE_EXTENSIONS
+                .iter()
+                .any(|known| ext.eq_ignore_ascii_case(known))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pick_random_image() {
+        let dir = std::env::temp_dir().join(format!("pixel-says-random-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("notes.txt"), "not an image").unwrap();
+        assert!(pick_random_image(&dir).is_err());
+
+        std::fs::write(dir.join("sprite.PNG"), []).unwrap();
+        assert_eq!(pick_random_image(&dir).unwrap(), dir.join("sprite.PNG"));
+        assert_eq!(is_image_path(Path::new("logo.svg")), cfg!(feature = "svg"));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!((0..100).all(|_| random_index(3) < 3));
+    }
+}
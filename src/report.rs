@@ -0,0 +1,50 @@
+use std::io::{Result, Write};
+
+/// 一次渲染的结果统计
+///
+/// 宽度和高度都以终端字符格为单位，调用者可以据此定位后续输出或记录指标。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RenderReport {
+    /// 图片（或字符画）部分的宽度
+    pub art_width: usize,
+    /// 图片（或字符画）部分的高度
+    pub art_height: usize,
+    /// 消息框的宽度（包括边框）
+    pub bubble_width: usize,
+    /// 输出的总行数
+    pub lines: usize,
+    /// 写入的字节数
+    pub bytes_written: usize,
+    /// 图片中可见像素的不同颜色数
+    pub colors_used: usize,
+}
+
+/// 统计写入的字节数和行数
+pub(crate) struct CountingWriter<W> {
+    inner: W,
+    pub(crate) bytes: usize,
+    pub(crate) lines: usize,
+}
+
+impl<W: Write> CountingWriter<W> {
+    pub(crate) fn new(inner: W) -> Self {
+        CountingWriter {
+            inner,
+            bytes: 0,
+            lines: 0,
+        }
+    }
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.bytes += n;
+        self.lines += buf[..n].iter().filter(|&&b| b == b'\n').count();
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.inner.flush()
+    }
+}
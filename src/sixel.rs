@@ -0,0 +1,212 @@
+use crate::filters::is_visible;
+use crate::{Color, ImageTooLarge, RenderOptions};
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView};
+use std::collections::HashMap;
+use std::io::{Result, Write};
+
+/// 假定的终端字符格大小（像素），Sixel 图片按照它缩放，占用与字符输出相同的行列
+const CELL_WIDTH: u32 = 10;
+const CELL_HEIGHT: u32 = 20;
+
+/// Sixel 最多支持的颜色寄存器数量
+const MAX_COLORS: usize = 256;
+
+/// 以 Sixel 图形输出图片，结尾换行
+///
+/// 每个像素放大为与字符输出相同大小的色块，透明像素不绘制（有背景色时使用背景色）。
+/// 颜色超过 256 种时量化为 xterm 256 色调色板中的颜色。放大后的尺寸或所需内存超出
+/// [`RenderOptions::limits`] 时返回 [`ImageTooLarge`]。
+pub(crate) fn write_sixel<W>(
+    img: &DynamicImage,
+    options: &RenderOptions,
+    mut writer: W,
+) -> Result<()>
+where
+    W: Write,
+{
+    let (width, height) = img.dimensions();
+    let columns = options.pixel_width.columns() as u64;
+    let scaled_width = width as u64 * CELL_WIDTH as u64 * columns;
+    let scaled_height = height as u64 * CELL_HEIGHT as u64;
+    // 与解码时一样检查尺寸和所需内存，`max_cells` 为 `None` 时放大后的图片可能极大
+    let limits = options.limits;
+    let fits = |size: u64| u32::try_from(size).ok();
+    let (width, height) = match (fits(scaled_width), fits(scaled_height)) {
+        (Some(width), Some(height))
+            if limits.allows(width, height)
+                && limits
+                    .max_alloc
+                    .is_none_or(|max| scaled_width * scaled_height * 4 <= max) =>
+        {
+            (width, height)
+        }
+        _ => {
+            let clamp = |size: u64| u32::try_from(size).unwrap_or(u32::MAX);
+            return Err(ImageTooLarge {
+                dimensions: Some((clamp(scaled_width), clamp(scaled_height))),
+                limits,
+            }
+            .into());
+        }
+    };
+    let scaled = img
+        .resize_exact(width, height, FilterType::Nearest)
+        .into_rgba8();
+
+    // 每个像素对应的颜色寄存器，`None` 表示透明
+    let pixel_color = |x: u32, y: u32| {
+        let pixel = scaled.get_pixel(x, y);
+        let image::Rgba([r, g, b, _]) = *pixel;
+        if is_visible(pixel) {
+            Some(Color::rgb(r, g, b))
+        } else {
+            options.background
+        }
+    };
+    let mut colors: Vec<Color> = Vec::new();
+    let mut registers: HashMap<Color, usize> = HashMap::new();
+    let mut pixels = Vec::with_capacity((width * height) as usize);
+    let distinct = count_distinct(width, height, &pixel_color);
+    for y in 0..height {
+        for x in 0..width {
+            let color = pixel_color(x, y).map(|color| {
+                if distinct > MAX_COLORS {
+                    Color::from_ansi256(color.to_ansi256())
+                } else {
+                    color
+                }
+            });
+            pixels.push(color.map(|color| {
+                *registers.entry(color).or_insert_with(|| {
+                    colors.push(color);
+                    colors.len() - 1
+                })
+            }));
+        }
+    }
+
+    // P2 = 1：未绘制的像素保持终端原有的背景
+    write!(writer, "\x1bP0;1;0q\"1;1;{};{}", width, height)?;
+    for (i, color) in colors.iter().enumerate() {
+        let percent = |c: u8| (c as u32 * 100 + 127) / 255;
+        write!(
+            writer,
+            "#{};2;{};{};{}",
+            i,
+            percent(color.r),
+            percent(color.g),
+            percent(color.b)
+        )?;
+    }
+
+    // 每 6 行像素组成一个条带，每种颜色在条带中绘制一遍
+    for top in (0..height).step_by(6) {
+        let rows = (height - top).min(6);
+        let mut first = true;
+        for register in 0..colors.len() {
+            let sixels: Vec<u8> = (0..width)
+                .map(|x| {
+                    (0..rows).fold(0, |bits, dy| {
+                        let index = ((top + dy) * width + x) as usize;
+                        if pixels[index] == Some(register) {
+                            bits | 1 << dy
+                        } else {
+                            bits
+                        }
+                    })
+                })
+                .collect();
+            if sixels.iter().all(|&bits| bits == 0) {
+                continue;
+            }
+            if !first {
+                // 回到条带的开头，叠加下一种颜色
+                write!(writer, "$")?;
+            }
+            first = false;
+            write!(writer, "#{}", register)?;
+            write_run_length(&sixels, &mut writer)?;
+        }
+        write!(writer, "-")?;
+    }
+    writeln!(writer, "\x1b\\")
+}
+
+fn count_distinct<F>(width: u32, height: u32, pixel_color: &F) -> usize
+where
+    F: Fn(u32, u32) -> Option<Color>,
+{
+    let mut seen = std::collections::HashSet::new();
+    for y in 0..height {
+        for x in 0..width {
+            if let Some(color) = pixel_color(x, y) {
+                seen.insert(color);
+                if seen.len() > MAX_COLORS {
+                    return seen.len();
+                }
+            }
+        }
+    }
+    seen.len()
+}
+
+/// 输出一行 sixel 字符，连续 4 个以上相同的字符用 `!次数字符` 压缩，末尾的空白省略
+fn write_run_length<W: Write>(sixels: &[u8], writer: &mut W) -> Result<()> {
+    let end = sixels
+        .iter()
+        .rposition(|&bits| bits != 0)
+        .map_or(0, |i| i + 1);
+    let sixels = &sixels[..end];
+    let mut i = 0;
+    while i < sixels.len() {
+        let bits = sixels[i];
+        let run = sixels[i..].iter().take_while(|&&b| b == bits).count();
+        let c = (63 + bits) as char;
+        if run >= 4 {
+            write!(writer, "!{}{}", run, c)?;
+        } else {
+            for _ in 0..run {
+                write!(writer, "{}", c)?;
+            }
+        }
+        i += run;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PixelSaysError;
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn test_sixel_encoding() {
+        let mut img = RgbaImage::new(2, 1);
+        img.put_pixel(0, 0, Rgba([255, 0, 0, 255]));
+        let options = RenderOptions::default();
+        let mut output = Vec::new();
+        write_sixel(&DynamicImage::ImageRgba8(img), &options, &mut output).unwrap();
+
+        // 2x1 像素放大为 40x20，右半边透明；20 行分为 6、6、6、2 四个条带
+        let expected = "\x1bP0;1;0q\"1;1;40;20#0;2;100;0;0\
+                        #0!20~-#0!20~-#0!20~-#0!20B-\x1b\\\n";
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_sixel_size_limits() {
+        // 不限制字符格数时，4096 像素高的图片放大后远远超出解码限制
+        let img = DynamicImage::ImageRgba8(RgbaImage::new(1, 4096));
+        let options = RenderOptions {
+            max_cells: None,
+            ..RenderOptions::default()
+        };
+        let err = write_sixel(&img, &options, &mut Vec::new()).unwrap_err();
+        let Some(PixelSaysError::ImageTooLarge(too_large)) = PixelSaysError::from_io(&err) else {
+            panic!("unexpected error: {}", err);
+        };
+        assert_eq!(too_large.dimensions, Some((20, 81920)));
+    }
+}
@@ -0,0 +1,124 @@
+use crate::{load_image, load_image_from_bytes, DecodeLimits, PixelSaysError};
+use image::{DynamicImage, GenericImageView};
+use std::io::Result;
+use std::path::Path;
+
+/// 精灵图的切分方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SheetLayout {
+    /// 按列数和行数等分整张图片
+    Grid { columns: u32, rows: u32 },
+    /// 按固定的格子大小（像素）切分，图片右侧和下方不足一格的部分被忽略
+    CellSize { width: u32, height: u32 },
+}
+
+/// 按网格排列多个帧的精灵图
+///
+/// 帧按从左到右、从上到下的顺序编号，从 0 开始。
+#[derive(Debug, Clone)]
+pub struct SpriteSheet {
+    image: DynamicImage,
+    cell_width: u32,
+    cell_height: u32,
+    columns: u32,
+    rows: u32,
+}
+
+impl SpriteSheet {
+    /// 按给定的方式切分图片，格子为空或比图片还大时返回错误
+    pub fn new(image: DynamicImage, layout: SheetLayout) -> Result<Self> {
+        let (width, height) = image.dimensions();
+        let (cell_width, cell_height) = match layout {
+            SheetLayout::Grid { columns, rows } if columns > 0 && rows > 0 => {
+                (width / columns, height / rows)
+            }
+            SheetLayout::Grid { .. } => (0, 0),
+            SheetLayout::CellSize { width, height } => (width, height),
+        };
+        if cell_width == 0 || cell_height == 0 || cell_width > width || cell_height > height {
+            return Err(PixelSaysError::InvalidSpriteSheet {
+                layout,
+                width,
+                height,
+            }
+            .into());
+        }
+        Ok(SpriteSheet {
+            image,
+            cell_width,
+            cell_height,
+            columns: width / cell_width,
+            rows: height / cell_height,
+        })
+    }
+
+    /// 在解码限制下读取精灵图文件
+    pub fn open<P>(path: P, layout: SheetLayout, limits: DecodeLimits) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        SpriteSheet::new(load_image(path, limits)?, layout)
+    }
+
+    /// 在解码限制下解码内存中的精灵图
+    pub fn from_bytes(bytes: &[u8], layout: SheetLayout, limits: DecodeLimits) -> Result<Self> {
+        SpriteSheet::new(load_image_from_bytes(bytes, limits)?, layout)
+    }
+
+    /// 帧的数量
+    pub fn len(&self) -> usize {
+        (self.columns * self.rows) as usize
+    }
+
+    /// 是否没有任何帧（切分成功的精灵图至少有一帧）
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 取出第 `index` 帧，超出范围时返回 `None`
+    pub fn frame(&self, index: usize) -> Option<DynamicImage> {
+        if index >= self.len() {
+            return None;
+        }
+        let (column, row) = (index as u32 % self.columns, index as u32 / self.columns);
+        Some(self.image.crop_imm(
+            column * self.cell_width,
+            row * self.cell_height,
+            self.cell_width,
+            self.cell_height,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn test_sprite_sheet_frames() {
+        // 3x2 个 2x2 的格子，每一格的红色分量等于帧的编号
+        let img = RgbaImage::from_fn(6, 4, |x, y| Rgba([(y / 2 * 3 + x / 2) as u8, 0, 0, 255]));
+        let img = DynamicImage::ImageRgba8(img);
+
+        let grid = |columns, rows| SheetLayout::Grid { columns, rows };
+        let sheet = SpriteSheet::new(img.clone(), grid(3, 2)).unwrap();
+        assert_eq!(sheet.len(), 6);
+        let frame = sheet.frame(4).unwrap();
+        assert_eq!(frame.dimensions(), (2, 2));
+        assert_eq!(frame.get_pixel(1, 1), Rgba([4, 0, 0, 255]));
+        assert!(sheet.frame(6).is_none());
+
+        let cell = SheetLayout::CellSize {
+            width: 2,
+            height: 2,
+        };
+        let sheet = SpriteSheet::new(img.clone(), cell).unwrap();
+        assert_eq!(
+            sheet.frame(5).unwrap().get_pixel(0, 0),
+            Rgba([5, 0, 0, 255])
+        );
+
+        assert!(SpriteSheet::new(img, grid(0, 1)).is_err());
+    }
+}
@@ -0,0 +1,142 @@
+#[cfg(feature = "svg")]
+use crate::{DecodeLimits, ImageTooLarge};
+use crate::{PixelSaysError, RenderOptions};
+use image::DynamicImage;
+use std::io::Result;
+use std::path::Path;
+
+/// 无法获取终端大小时假定的列数
+#[cfg(feature = "svg")]
+const FALLBACK_COLUMNS: usize = 80;
+
+/// 路径的扩展名是否为 `.svg` 或 `.svgz`
+pub(crate) fn is_svg_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("svg") || ext.eq_ignore_ascii_case("svgz"))
+}
+
+/// 数据是否像是 SVG 文档（可选的 XML 声明或注释之后是 `<svg` 元素）
+pub(crate) fn is_svg_data(bytes: &[u8]) -> bool {
+    let head = &bytes[..bytes.len().min(1024)];
+    let head = String::from_utf8_lossy(head);
+    let head = head.trim_start_matches('\u{feff}').trim_start();
+    (head.starts_with("<?xml") || head.starts_with("<!--") || head.starts_with("<svg"))
+        && head.contains("<svg")
+}
+
+/// 把 SVG 光栅化为终端宽度能容纳的像素图片
+///
+/// 宽度取终端列数能显示的像素数，且不超过 `max_cells` 限制的尺寸，
+/// 这样缩放时不会再损失细节。SVG 中的文字不会被绘制。计算出的尺寸或所需内存超出
+/// `options.limits` 时返回 [`crate::ImageTooLarge`]。
+#[cfg(feature = "svg")]
+pub(crate) fn rasterize(bytes: &[u8], options: &RenderOptions) -> Result<DynamicImage> {
+    use crate::convert::pixels_in_columns;
+    use crate::terminal::terminal_size;
+
+    let columns = terminal_size().map_or(FALLBACK_COLUMNS, |(columns, _)| columns);
+    let max_width = pixels_in_columns(columns, options) as u32;
+    match options.max_cells {
+        Some((width, height)) => render(bytes, max_width.min(width), height, options.limits),
+        None => render(bytes, max_width, u32::MAX, options.limits),
+    }
+}
+
+/// 以不超过 `max_width`x`max_height` 的尺寸绘制 SVG
+#[cfg(feature = "svg")]
+fn render(
+    bytes: &[u8],
+    max_width: u32,
+    max_height: u32,
+    limits: DecodeLimits,
+) -> Result<DynamicImage> {
+    use resvg::{tiny_skia, usvg};
+
+    let tree = usvg::Tree::from_data(bytes, &usvg::Options::default()).map_err(svg_error)?;
+    let size = tree.size();
+
+    let max_width = max_width.max(1) as f32;
+    let scale = (max_width / size.width()).min(max_height.max(1) as f32 / size.height());
+    let width = (size.width() * scale).round().max(1.0) as u32;
+    let height = (size.height() * scale).round().max(1.0) as u32;
+    // 与解码位图时一样检查尺寸和所需内存，细长的 SVG 可能得到极大的高度
+    let alloc = width as u64 * height as u64 * 4;
+    if !limits.allows(width, height) || limits.max_alloc.is_some_and(|max| alloc > max) {
+        return Err(ImageTooLarge {
+            dimensions: Some((width, height)),
+            limits,
+        }
+        .into());
+    }
+
+    let mut pixmap = tiny_skia::Pixmap::new(width, height)
+        .ok_or_else(|| svg_error(format!("无效的尺寸 {}x{}", width, height)))?;
+    resvg::render(
+        &tree,
+        tiny_skia::Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+
+    // tiny-skia 使用预乘透明度，转换回普通的 RGBA
+    let data = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let color = pixel.demultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        })
+        .collect();
+    let img = image::RgbaImage::from_raw(width, height, data)
+        .ok_or_else(|| svg_error("像素数据的长度不正确"))?;
+    Ok(DynamicImage::ImageRgba8(img))
+}
+
+/// 没有启用 `svg` 功能时无法读取 SVG
+#[cfg(not(feature = "svg"))]
+pub(crate) fn rasterize(_bytes: &[u8], _options: &RenderOptions) -> Result<DynamicImage> {
+    Err(PixelSaysError::FeatureDisabled("svg").into())
+}
+
+#[cfg(feature = "svg")]
+fn svg_error(e: impl std::fmt::Display) -> std::io::Error {
+    PixelSaysError::Svg(e.to_string()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_svg() {
+        assert!(is_svg_path(Path::new("mascot.SVG")));
+        assert!(!is_svg_path(Path::new("mascot.png")));
+        assert!(is_svg_data(
+            b"<?xml version=\"1.0\"?>\n<svg xmlns=\"http://www.w3.org/2000/svg\"/>"
+        ));
+        assert!(!is_svg_data(b"\x89PNG\r\n\x1a\n"));
+    }
+
+    #[cfg(feature = "svg")]
+    #[test]
+    fn test_rasterize_svg() {
+        let svg = br##"<svg xmlns="http://www.w3.org/2000/svg" width="4" height="2">
+            <rect width="4" height="2" fill="#ff0000"/>
+        </svg>"##;
+        let img = render(svg, 40, 80, DecodeLimits::default())
+            .unwrap()
+            .to_rgba8();
+
+        // 放大到终端能容纳的宽度，保持宽高比
+        assert_eq!(img.dimensions(), (40, 20));
+        assert_eq!(img.get_pixel(0, 0).0, [255, 0, 0, 255]);
+
+        // 细长的 SVG 在没有限制高度时也不能超出解码限制
+        let tall = br##"<svg xmlns="http://www.w3.org/2000/svg" width="1" height="1000"/>"##;
+        let err = render(tall, 40, u32::MAX, DecodeLimits::default()).unwrap_err();
+        let Some(PixelSaysError::ImageTooLarge(too_large)) = PixelSaysError::from_io(&err) else {
+            panic!("unexpected error: {}", err);
+        };
+        assert_eq!(too_large.dimensions, Some((40, 40000)));
+    }
+}
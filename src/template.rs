@@ -0,0 +1,152 @@
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 消息模板中占位符的取值
+///
+/// 消息中的 `{name}` 在折行之前替换为对应的值，`{{` 和 `}}` 表示字面的花括号，
+/// 没有取值的占位符保持原样。一个模板可以在不同的机器上生成各自的登录横幅。
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Template {
+    values: BTreeMap<String, String>,
+}
+
+impl Template {
+    /// 没有任何取值的模板
+    pub fn new() -> Self {
+        Template::default()
+    }
+
+    /// 包含当前系统信息的模板：`{hostname}`、`{user}`、`{date}`（UTC，`YYYY-MM-DD`）
+    /// 和 `{uptime}`（仅 Linux），无法获取的值会被跳过
+    pub fn system() -> Self {
+        let mut template = Template::new();
+        let values = [
+            ("hostname", hostname()),
+            ("user", user()),
+            ("date", today()),
+            ("uptime", uptime()),
+        ];
+        for (key, value) in values {
+            if let Some(value) = value {
+                template.set(key, value);
+            }
+        }
+        template
+    }
+
+    /// 设置占位符的值，已有的值会被覆盖
+    pub fn set(&mut self, key: impl Into<String>, value: impl Into<String>) -> &mut Self {
+        self.values.insert(key.into(), value.into());
+        self
+    }
+
+    /// 占位符的值
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    /// 替换文本中的占位符
+    pub fn expand(&self, text: &str) -> String {
+        let mut result = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(pos) = rest.find(['{', '}']) {
+            result.push_str(&rest[..pos]);
+            rest = &rest[pos..];
+            if rest.starts_with("{{") || rest.starts_with("}}") {
+                result.push_str(&rest[..1]);
+                rest = &rest[2..];
+                continue;
+            }
+            let placeholder = rest
+                .strip_prefix('{')
+                .and_then(|inner| inner.find('}').map(|end| &inner[..end]))
+                .filter(|key| !key.contains('{'));
+            match placeholder.and_then(|key| self.values.get(key.trim()).map(|v| (key, v))) {
+                Some((key, value)) => {
+                    result.push_str(value);
+                    rest = &rest[key.len() + 2..];
+                }
+                None => {
+                    result.push_str(&rest[..1]);
+                    rest = &rest[1..];
+                }
+            }
+        }
+        result.push_str(rest);
+        result
+    }
+}
+
+#[cfg(unix)]
+fn hostname() -> Option<String> {
+    let mut buf = [0u8; 256];
+    // SAFETY: 缓冲区有效，长度与其大小一致
+    if unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len()) } != 0 {
+        return None;
+    }
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    String::from_utf8(buf[..len].to_vec()).ok()
+}
+
+#[cfg(not(unix))]
+fn hostname() -> Option<String> {
+    std::env::var("COMPUTERNAME").ok()
+}
+
+fn user() -> Option<String> {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .ok()
+        .filter(|user| !user.is_empty())
+}
+
+fn today() -> Option<String> {
+    let seconds = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    let (year, month, day) = civil_from_days((seconds / 86400) as i64);
+    Some(format!("{:04}-{:02}-{:02}", year, month, day))
+}
+
+/// 把 1970-01-01 起的天数转换为公历日期
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    // Howard Hinnant 的 civil_from_days 算法
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+fn uptime() -> Option<String> {
+    let content = std::fs::read_to_string("/proc/uptime").ok()?;
+    let seconds = content.split_whitespace().next()?.parse::<f64>().ok()? as u64;
+    let (days, hours, minutes) = (seconds / 86400, seconds / 3600 % 24, seconds / 60 % 60);
+    Some(if days > 0 {
+        format!("{}d {}h {}m", days, hours, minutes)
+    } else {
+        format!("{}h {}m", hours, minutes)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_template() {
+        let mut template = Template::new();
+        template.set("host", "box").set("user", "ann");
+
+        assert_eq!(
+            template.expand("Welcome {user}@{ host }, {{literal}} {missing} {"),
+            "Welcome ann@box, {literal} {missing} {"
+        );
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(19782), (2024, 2, 29));
+        assert_eq!(civil_from_days(20742), (2026, 10, 16));
+    }
+}
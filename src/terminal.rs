@@ -0,0 +1,574 @@
+use crate::{OutputProfile, PixelMode};
+use std::env;
+use std::io::IsTerminal;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// 终端背景的明暗
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TerminalBackground {
+    /// 深色背景
+    Dark,
+    /// 浅色背景
+    Light,
+}
+
+impl TerminalBackground {
+    /// 适合该背景的黑白模式
+    ///
+    /// 黑白模式用前景色绘制亮的像素，在浅色背景上前景色通常是深色，
+    /// 所以浅色背景改用反色模式，让图片的明暗关系保持不变。
+    pub fn monochrome_mode(self) -> PixelMode {
+        match self {
+            TerminalBackground::Dark => PixelMode::Monochrome,
+            TerminalBackground::Light => PixelMode::Invert,
+        }
+    }
+
+    /// 根据背景颜色判断明暗
+    pub fn from_rgb(r: u8, g: u8, b: u8) -> Self {
+        let luminance = 0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32;
+        if luminance < 128.0 {
+            TerminalBackground::Dark
+        } else {
+            TerminalBackground::Light
+        }
+    }
+}
+
+/// 查询终端回复的等待时间
+const QUERY_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// 检测终端背景的明暗
+///
+/// 优先通过 OSC 11 向终端查询背景色，终端不回复（或不是终端）时退回到
+/// `COLORFGBG` 环境变量。两者都无法判断时返回 `None`。
+pub fn detect_background() -> Option<TerminalBackground> {
+    background_from(|name| env::var(name).ok(), query_background_color)
+}
+
+fn background_from<F, Q>(lookup: F, query: Q) -> Option<TerminalBackground>
+where
+    F: Fn(&str) -> Option<String>,
+    Q: FnOnce() -> Option<(u8, u8, u8)>,
+{
+    query()
+        .map(|(r, g, b)| TerminalBackground::from_rgb(r, g, b))
+        .or_else(|| lookup("COLORFGBG").and_then(|v| parse_colorfgbg(&v)))
+}
+
+/// 解析 `COLORFGBG`（形如 `15;0`，最后一项为背景色的 16 色编号）
+fn parse_colorfgbg(value: &str) -> Option<TerminalBackground> {
+    let background: u8 = value.rsplit(';').next()?.trim().parse().ok()?;
+    Some(match background {
+        0..=6 | 8 => TerminalBackground::Dark,
+        _ => TerminalBackground::Light,
+    })
+}
+
+/// 解析 OSC 11 的回复，例如 `\x1b]11;rgb:ffff/ffff/ffff\x1b\\`
+fn parse_osc11_response(response: &str) -> Option<(u8, u8, u8)> {
+    let rgb = &response[response.find("rgb:")? + 4..];
+    let rgb = rgb.split(['\x1b', '\x07']).next()?;
+    let mut channels = rgb.split('/').map(|channel| {
+        // 每个通道有 1 到 4 位十六进制数，只取最高的 8 位
+        let value = u16::from_str_radix(channel, 16).ok()?;
+        let bits = channel.len() as u32 * 4;
+        Some(if bits >= 8 {
+            (value >> (bits - 8)) as u8
+        } else {
+            (value * 255 / ((1 << bits) - 1)) as u8
+        })
+    });
+    Some((channels.next()??, channels.next()??, channels.next()??))
+}
+
+/// 根据终端的能力选择像素转换模式，结果在进程内缓存
+///
+/// 依次检查：标准输出不是终端且没有设置 `CLICOLOR_FORCE` 时使用不含转义序列的黑白模式；
+/// `TERM=dumb` 时使用 ASCII 字符画；设置了 `NO_COLOR` 时使用黑白模式；`COLORTERM` 为
+/// `truecolor` 或 `24bit` 时使用真彩色；`TERM` 含有 `256color` 时使用 256 色。以上都无法判断时
+/// 查询终端：支持 kitty 图形协议的终端使用真彩色，支持 Sixel 的终端使用 256 色，其余的
+/// `TERM` 为 `xterm`、`linux`、`screen` 等彩色终端时使用 16 色，否则使用黑白模式；
+/// 标准输出不是终端时无法查询，使用 256 色。
+///
+/// 终端中使用黑白模式时还会检测背景（见 [`detect_background`]），浅色背景改用反色模式，
+/// 见 [`TerminalBackground::monochrome_mode`]。
+pub fn detect_pixel_mode() -> PixelMode {
+    static MODE: OnceLock<PixelMode> = OnceLock::new();
+    MODE.get_or_init(|| {
+        pixel_mode_from(
+            |name| env::var(name).ok(),
+            std::io::stdout().is_terminal(),
+            query_graphics,
+            query_background_color,
+        )
+    })
+    .clone()
+}
+
+/// 根据环境选择输出配置
+///
+/// 设置了 `NO_COLOR`，或者标准输出不是终端且没有设置 `CLICOLOR_FORCE` 时使用
+/// [`OutputProfile::Plain`]，保证重定向到文件或管道的输出中没有转义序列；否则使用
+/// [`OutputProfile::Unicode`]。
+pub fn detect_output_profile() -> OutputProfile {
+    output_profile_from(|name| env::var(name).ok(), std::io::stdout().is_terminal())
+}
+
+fn output_profile_from<F>(lookup: F, is_tty: bool) -> OutputProfile
+where
+    F: Fn(&str) -> Option<String>,
+{
+    if no_color(&lookup) || !(is_tty || color_forced(&lookup)) {
+        OutputProfile::Plain
+    } else {
+        OutputProfile::Unicode
+    }
+}
+
+/// 设置了非空的 `NO_COLOR`，见 <https://no-color.org>
+fn no_color<F>(lookup: F) -> bool
+where
+    F: Fn(&str) -> Option<String>,
+{
+    lookup("NO_COLOR").is_some_and(|value| !value.is_empty())
+}
+
+/// 设置了非空且不为 `0` 的 `CLICOLOR_FORCE`，即使输出不是终端也使用颜色
+fn color_forced<F>(lookup: F) -> bool
+where
+    F: Fn(&str) -> Option<String>,
+{
+    lookup("CLICOLOR_FORCE").is_some_and(|value| !value.is_empty() && value != "0")
+}
+
+/// 终端回复的图形能力
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+struct Graphics {
+    kitty: bool,
+    sixel: bool,
+}
+
+fn pixel_mode_from<F, Q, B>(lookup: F, is_tty: bool, query: Q, background: B) -> PixelMode
+where
+    F: Fn(&str) -> Option<String>,
+    Q: FnOnce() -> Option<Graphics>,
+    B: FnOnce() -> Option<(u8, u8, u8)>,
+{
+    if !is_tty && !color_forced(&lookup) {
+        return PixelMode::Monochrome;
+    }
+    // 黑白模式只用字符区分明暗，在浅色背景上需要反过来；标准输出不是终端时不查询
+    let monochrome = || {
+        let query = || if is_tty { background() } else { None };
+        background_from(&lookup, query)
+            .map_or(PixelMode::Monochrome, TerminalBackground::monochrome_mode)
+    };
+    let term = lookup("TERM").unwrap_or_default().to_ascii_lowercase();
+    if term == "dumb" {
+        return PixelMode::Ascii;
+    }
+    if no_color(&lookup) {
+        return monochrome();
+    }
+    let colorterm = lookup("COLORTERM").unwrap_or_default().to_ascii_lowercase();
+    if colorterm == "truecolor" || colorterm == "24bit" {
+        return PixelMode::TrueColor;
+    }
+    if term.contains("256color") || !is_tty {
+        return PixelMode::Ansi256;
+    }
+    match query() {
+        Some(Graphics { kitty: true, .. }) => PixelMode::TrueColor,
+        Some(Graphics { sixel: true, .. }) => PixelMode::Ansi256,
+        _ if supports_basic_colors(&term) => PixelMode::Ansi16,
+        _ => monochrome(),
+    }
+}
+
+/// `TERM` 为常见的彩色终端时，至少支持基本的 16 色
+fn supports_basic_colors(term: &str) -> bool {
+    const COLOR_TERMS: [&str; 7] = ["xterm", "linux", "screen", "tmux", "rxvt", "ansi", "cygwin"];
+    term.contains("color") || COLOR_TERMS.iter().any(|prefix| term.starts_with(prefix))
+}
+
+/// 解析 kitty 图形协议查询和 DA1（主设备属性）的回复，DA1 的参数中有 4 表示支持 Sixel
+fn parse_graphics_response(response: &str) -> Graphics {
+    let kitty = response.contains("\x1b_Gi=31;OK");
+    let sixel = response
+        .find("\x1b[?")
+        .map(|start| &response[start + 3..])
+        .and_then(|rest| rest.split_once('c'))
+        .is_some_and(|(params, _)| params.split(';').any(|param| param == "4"));
+    Graphics { kitty, sixel }
+}
+
+#[cfg(unix)]
+fn query_graphics() -> Option<Graphics> {
+    // 所有终端都会回复 DA1，因此放在最后，作为回复结束的标志
+    let request = "\x1b_Gi=31,s=1,v=1,a=q,t=d,f=24;AAAA\x1b\\\x1b[c";
+    let response = tty::query_until(request, QUERY_TIMEOUT, |response| {
+        let response = String::from_utf8_lossy(response);
+        response
+            .find("\x1b[?")
+            .is_some_and(|start| response[start..].ends_with('c'))
+    })
+    .ok()?;
+    Some(parse_graphics_response(&response))
+}
+
+#[cfg(not(unix))]
+fn query_graphics() -> Option<Graphics> {
+    None
+}
+
+/// 终端的大小 `(列数, 行数)`
+///
+/// 标准输出是终端时读取窗口大小，否则退回到 `COLUMNS` 与 `LINES` 环境变量。
+pub fn terminal_size() -> Option<(usize, usize)> {
+    window_size().or_else(|| {
+        let columns = env::var("COLUMNS").ok()?.trim().parse().ok()?;
+        let lines = env::var("LINES")
+            .ok()
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(0);
+        Some((columns, lines))
+    })
+}
+
+#[cfg(unix)]
+fn window_size() -> Option<(usize, usize)> {
+    // SAFETY: winsize 由 ioctl 填充，失败时不会读取
+    let size = unsafe {
+        let mut size = std::mem::zeroed::<libc::winsize>();
+        if libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) != 0 {
+            return None;
+        }
+        size
+    };
+    (size.ws_col > 0).then_some((size.ws_col as usize, size.ws_row as usize))
+}
+
+#[cfg(not(unix))]
+fn window_size() -> Option<(usize, usize)> {
+    None
+}
+
+/// 监视终端大小的变化，供动画等持续刷新的输出在绘制每一帧之前检查，
+/// 按新的大小重新渲染，避免旧尺寸的输出被终端折行
+///
+/// 每次检查时重新读取终端大小，不会安装信号处理函数；程序自己处理 `SIGWINCH` 时，
+/// 可以改用 [`crate::say_from_frames_resizing`] 在收到信号后才读取。
+#[derive(Debug)]
+pub struct ResizeWatcher {
+    size: Option<(usize, usize)>,
+}
+
+impl ResizeWatcher {
+    /// 记录当前的终端大小并开始监视
+    pub fn new() -> Self {
+        ResizeWatcher {
+            size: terminal_size(),
+        }
+    }
+
+    /// 最近一次记录的终端大小
+    pub fn size(&self) -> Option<(usize, usize)> {
+        self.size
+    }
+
+    /// 终端大小自上次检查以来发生变化时返回新的大小
+    pub fn poll(&mut self) -> Option<(usize, usize)> {
+        let size = terminal_size();
+        if size == self.size {
+            return None;
+        }
+        self.size = size;
+        size
+    }
+}
+
+impl Default for ResizeWatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(unix)]
+fn query_background_color() -> Option<(u8, u8, u8)> {
+    let response = tty::query("\x1b]11;?\x1b\\", QUERY_TIMEOUT).ok()?;
+    parse_osc11_response(&response)
+}
+
+#[cfg(not(unix))]
+fn query_background_color() -> Option<(u8, u8, u8)> {
+    None
+}
+
+#[cfg(unix)]
+pub(crate) mod tty {
+    use crate::PixelSaysError;
+    use std::fs::OpenOptions;
+    use std::io::{Read, Write};
+    use std::os::unix::io::AsRawFd;
+    use std::time::{Duration, Instant};
+
+    /// 在原始模式下向 `/dev/tty` 发送查询并读取以 BEL 或 ST 结束的回复
+    ///
+    /// 无法打开或设置终端时返回 [`PixelSaysError::Io`]，终端没有按时完整回复时返回
+    /// [`PixelSaysError::TerminalQuery`]。
+    pub(crate) fn query(request: &str, timeout: Duration) -> Result<String, PixelSaysError> {
+        query_until(request, timeout, |response| {
+            response.ends_with(b"\x07") || response.ends_with(b"\x1b\\")
+        })
+    }
+
+    /// 与 [`query`] 相同，读取到 `done` 返回 `true` 为止
+    pub(crate) fn query_until<F>(
+        request: &str,
+        timeout: Duration,
+        done: F,
+    ) -> Result<String, PixelSaysError>
+    where
+        F: Fn(&[u8]) -> bool,
+    {
+        let mut tty = OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+        let fd = tty.as_raw_fd();
+
+        // SAFETY: fd 在 tty 的生命周期内有效，termios 由 tcgetattr 完整初始化
+        let original = unsafe {
+            let mut termios = std::mem::zeroed::<libc::termios>();
+            if libc::tcgetattr(fd, &mut termios) != 0 {
+                return Err(std::io::Error::last_os_error().into());
+            }
+            let original = termios;
+            libc::cfmakeraw(&mut termios);
+            if libc::tcsetattr(fd, libc::TCSANOW, &termios) != 0 {
+                return Err(std::io::Error::last_os_error().into());
+            }
+            original
+        };
+
+        let response = tty
+            .write_all(request.as_bytes())
+            .and_then(|_| tty.flush())
+            .map_err(PixelSaysError::from)
+            .and_then(|_| read_response(&mut tty, timeout, done));
+
+        // SAFETY: 恢复之前保存的终端设置
+        unsafe {
+            libc::tcsetattr(fd, libc::TCSANOW, &original);
+        }
+        response
+    }
+
+    fn read_response<F>(
+        tty: &mut std::fs::File,
+        timeout: Duration,
+        done: F,
+    ) -> Result<String, PixelSaysError>
+    where
+        F: Fn(&[u8]) -> bool,
+    {
+        let deadline = Instant::now() + timeout;
+        let mut response = Vec::new();
+        let mut buffer = [0u8; 64];
+        loop {
+            let remaining = deadline
+                .checked_duration_since(Instant::now())
+                .ok_or(PixelSaysError::TerminalQuery("终端没有及时回复"))?;
+            let mut pollfd = libc::pollfd {
+                fd: tty.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            // SAFETY: pollfd 指向一个有效的结构体，数量为 1
+            let ready = unsafe { libc::poll(&mut pollfd, 1, remaining.as_millis() as libc::c_int) };
+            if ready < 0 {
+                return Err(std::io::Error::last_os_error().into());
+            }
+            if ready == 0 {
+                return Err(PixelSaysError::TerminalQuery("终端没有及时回复"));
+            }
+            let n = tty.read(&mut buffer)?;
+            if n == 0 {
+                return Err(PixelSaysError::TerminalQuery("终端在回复完整之前关闭"));
+            }
+            response.extend_from_slice(&buffer[..n]);
+            if done(&response) {
+                return String::from_utf8(response)
+                    .map_err(|_| PixelSaysError::TerminalQuery("终端的回复不是 UTF-8"));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 只含有 `vars` 的环境变量
+    fn env(vars: &'static [(&'static str, &'static str)]) -> impl Fn(&str) -> Option<String> {
+        move |name| {
+            vars.iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+        }
+    }
+
+    #[test]
+    fn test_background_detection_parsing() {
+        assert_eq!(parse_colorfgbg("15;0"), Some(TerminalBackground::Dark));
+        assert_eq!(
+            parse_colorfgbg("0;default;15"),
+            Some(TerminalBackground::Light)
+        );
+        assert_eq!(parse_colorfgbg("garbage"), None);
+
+        assert_eq!(
+            parse_osc11_response("\x1b]11;rgb:ffff/fefe/f0f0\x1b\\"),
+            Some((255, 254, 240))
+        );
+        assert_eq!(
+            parse_osc11_response("\x1b]11;rgb:1e/1e/2e\x07"),
+            Some((30, 30, 46))
+        );
+        assert_eq!(
+            TerminalBackground::from_rgb(255, 254, 240).monochrome_mode(),
+            PixelMode::Invert
+        );
+    }
+
+    #[test]
+    fn test_pixel_mode_detection() {
+        fn no_query<T>() -> Option<T> {
+            panic!("不应查询终端")
+        }
+
+        assert_eq!(
+            pixel_mode_from(
+                env(&[("COLORTERM", "truecolor")]),
+                false,
+                no_query,
+                no_query
+            ),
+            PixelMode::Monochrome
+        );
+        assert_eq!(
+            pixel_mode_from(env(&[("TERM", "dumb")]), true, no_query, no_query),
+            PixelMode::Ascii
+        );
+        assert_eq!(
+            pixel_mode_from(env(&[("COLORTERM", "24bit")]), true, no_query, no_query),
+            PixelMode::TrueColor
+        );
+        assert_eq!(
+            pixel_mode_from(env(&[("TERM", "xterm-256color")]), true, no_query, no_query),
+            PixelMode::Ansi256
+        );
+
+        let graphics = parse_graphics_response("\x1b_Gi=31;OK\x1b\\\x1b[?62;4;22c");
+        assert_eq!(
+            graphics,
+            Graphics {
+                kitty: true,
+                sixel: true
+            }
+        );
+        let sixel = parse_graphics_response("\x1b[?63;1;4c");
+        assert_eq!(
+            pixel_mode_from(env(&[("TERM", "xterm")]), true, || Some(sixel), || None),
+            PixelMode::Ansi256
+        );
+        assert_eq!(
+            pixel_mode_from(env(&[("TERM", "xterm")]), true, || None, || None),
+            PixelMode::Ansi16
+        );
+        assert_eq!(
+            pixel_mode_from(env(&[("TERM", "linux")]), true, || None, || None),
+            PixelMode::Ansi16
+        );
+        assert_eq!(
+            pixel_mode_from(env(&[("TERM", "vt100")]), true, || None, || None),
+            PixelMode::Monochrome
+        );
+        assert_eq!(
+            pixel_mode_from(env(&[("CLICOLOR_FORCE", "1")]), false, no_query, no_query),
+            PixelMode::Ansi256
+        );
+        assert_eq!(
+            pixel_mode_from(
+                env(&[("CLICOLOR_FORCE", "1"), ("NO_COLOR", "1")]),
+                false,
+                no_query,
+                no_query
+            ),
+            PixelMode::Monochrome
+        );
+    }
+
+    #[test]
+    fn test_auto_mode_follows_background() {
+        let light = env(&[("TERM", "vt100"), ("COLORFGBG", "0;15")]);
+        assert_eq!(
+            pixel_mode_from(light, true, || None, || None),
+            PixelMode::Invert
+        );
+        let dark = env(&[("TERM", "vt100"), ("COLORFGBG", "15;0")]);
+        assert_eq!(
+            pixel_mode_from(dark, true, || None, || None),
+            PixelMode::Monochrome
+        );
+        let no_color = env(&[("NO_COLOR", "1"), ("COLORFGBG", "0;15")]);
+        assert_eq!(
+            pixel_mode_from(no_color, true, || None, || None),
+            PixelMode::Invert
+        );
+        // 终端回复的背景色优先于 `COLORFGBG`
+        let light = env(&[("TERM", "vt100"), ("COLORFGBG", "0;15")]);
+        assert_eq!(
+            pixel_mode_from(light, true, || None, || Some((0, 0, 0))),
+            PixelMode::Monochrome
+        );
+        // 有颜色时不受背景影响
+        let xterm = env(&[("TERM", "xterm"), ("COLORFGBG", "0;15")]);
+        assert_eq!(
+            pixel_mode_from(xterm, true, || None, || None),
+            PixelMode::Ansi16
+        );
+    }
+
+    #[test]
+    fn test_output_profile_detection() {
+        assert_eq!(output_profile_from(env(&[]), true), OutputProfile::Unicode);
+        assert_eq!(output_profile_from(env(&[]), false), OutputProfile::Plain);
+        assert_eq!(
+            output_profile_from(env(&[("NO_COLOR", "")]), true),
+            OutputProfile::Unicode
+        );
+        assert_eq!(
+            output_profile_from(env(&[("NO_COLOR", "1")]), true),
+            OutputProfile::Plain
+        );
+        assert_eq!(
+            output_profile_from(env(&[("CLICOLOR_FORCE", "1")]), false),
+            OutputProfile::Unicode
+        );
+        assert_eq!(
+            output_profile_from(env(&[("CLICOLOR_FORCE", "0")]), false),
+            OutputProfile::Plain
+        );
+        assert_eq!(
+            output_profile_from(env(&[("CLICOLOR_FORCE", "1"), ("NO_COLOR", "1")]), false),
+            OutputProfile::Plain
+        );
+    }
+
+    #[test]
+    fn test_resize_watcher() {
+        let mut watcher = ResizeWatcher::new();
+        assert_eq!(watcher.poll(), None);
+        assert_eq!(watcher.size(), terminal_size());
+    }
+}
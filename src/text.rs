@@ -0,0 +1,566 @@
+use crate::figlet::banner_text;
+use crate::highlight::highlight_line;
+use crate::{AmbiguousWidth, AnsiPolicy, OutputProfile, PixelSaysError, RenderOptions, WrapMode};
+use regex::Regex;
+use std::borrow::Cow;
+use std::io::Result;
+use std::sync::OnceLock;
+use textwrap::core::Fragment;
+use textwrap::wrap_algorithms::wrap_first_fit;
+use textwrap::WordSeparator;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthChar;
+
+/// 匹配终端转义序列：CSI（`ESC [`）、OSC（`ESC ]`，以 BEL 或 ST 结束）以及其他两字节序列
+pub(crate) fn ansi_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"\x1b(?:\[[0-?]*[ -/]*[@-~]|\][^\x07\x1b]*(?:\x07|\x1b\\)?|[@-Z\\-_])").unwrap()
+    })
+}
+
+/// 去除文本中的终端转义序列
+pub(crate) fn strip_ansi(input: &str) -> String {
+    ansi_regex().replace_all(input, "").to_string()
+}
+
+/// 除换行与制表符以外的控制字符
+fn is_stray_control(c: char) -> bool {
+    c.is_control() && c != '\n' && c != '\t'
+}
+
+/// 文本的显示宽度，忽略其中的转义序列
+pub(crate) fn display_width(line: &str) -> usize {
+    text_width(line, AmbiguousWidth::Narrow)
+}
+
+/// 按照歧义宽度字符的设置计算文本的显示宽度，忽略其中的转义序列
+///
+/// 宽度按字位簇累加，与终端绘制 emoji 序列和组合符号的方式一致。
+pub(crate) fn text_width(line: &str, ambiguous: AmbiguousWidth) -> usize {
+    let width = |line: &str| {
+        line.graphemes(true)
+            .map(|grapheme| grapheme_width(grapheme, ambiguous))
+            .sum()
+    };
+    if line.contains('\x1b') {
+        width(strip_ansi(line).as_str())
+    } else {
+        width(line)
+    }
+}
+
+/// 一个字位簇的显示宽度
+///
+/// 按第一个字符计算，组合符号和 ZWJ 连接的后续字符不再占宽度；带有 emoji
+/// 变体选择符（U+FE0F）的序列和由两个区域指示符组成的国旗占两列。
+pub(crate) fn grapheme_width(grapheme: &str, ambiguous: AmbiguousWidth) -> usize {
+    let mut chars = grapheme.chars();
+    let Some(first) = chars.next() else {
+        return 0;
+    };
+    let rest = chars.as_str();
+    let regional_indicator = ('\u{1f1e6}'..='\u{1f1ff}').contains(&first);
+    if rest.contains('\u{fe0f}') || (regional_indicator && !rest.is_empty()) {
+        return 2;
+    }
+    match ambiguous {
+        AmbiguousWidth::Narrow => first.width(),
+        AmbiguousWidth::Wide => first.width_cjk(),
+    }
+    .unwrap_or(0)
+}
+
+/// 预处理并折行消息文本
+///
+/// 用 ```` ``` ```` 围起来的代码块原样保留：不合并空白，也不重新折行。
+pub(crate) fn wrap_message(message: &str, options: &RenderOptions) -> Result<String> {
+    // 不允许转义序列时，消息中原有的样式也要去除
+    let policy = match options.ansi {
+        AnsiPolicy::PassThrough if !options.profile.allows_escapes() => AnsiPolicy::Strip,
+        policy => policy,
+    };
+    let mut input = sanitize_message(&expand_template(message, options), policy)?;
+    if options.profile == OutputProfile::Ascii {
+        input = to_ascii(&input);
+    }
+    if let Some(font) = &options.banner {
+        return Ok(banner_text(&strip_ansi(&input), font, options.max_width));
+    }
+
+    let parts: Vec<String> = split_code_blocks(&input)
+        .into_iter()
+        .map(|(verbatim, segment)| {
+            if verbatim {
+                // 制表符的显示宽度取决于终端，展开为空格以保证对齐
+                segment.replace('\t', "    ")
+            } else {
+                wrap_text(&segment, options)
+            }
+        })
+        .collect();
+    let mut wrapped = parts.join("\n");
+    if options.ansi == AnsiPolicy::PassThrough && wrapped.contains('\x1b') {
+        wrapped = carry_styles(&wrapped);
+    }
+    if options.highlights.is_empty() || !options.profile.allows_escapes() {
+        return Ok(wrapped);
+    }
+    let lines: Vec<String> = wrapped
+        .split('\n')
+        .map(|line| highlight_line(line, &options.highlights))
+        .collect();
+    Ok(lines.join("\n"))
+}
+
+/// 在每一行开头重新设置上一行结束时仍然有效的样式
+///
+/// 消息框在每行末尾重置样式，折行后被拆开的彩色文字需要在下一行重新着色。
+fn carry_styles(wrapped: &str) -> String {
+    let mut active: Vec<&str> = Vec::new();
+    let mut lines = Vec::new();
+    for line in wrapped.split('\n') {
+        let mut carried = active.concat();
+        carried.push_str(line);
+        lines.push(carried);
+        for m in ansi_regex().find_iter(line) {
+            let sequence = m.as_str();
+            if !sequence.starts_with("\x1b[") || !sequence.ends_with('m') {
+                continue;
+            }
+            if sequence == "\x1b[m" || sequence == "\x1b[0m" {
+                active.clear();
+            } else {
+                active.push(sequence);
+            }
+        }
+    }
+    lines.join("\n")
+}
+
+/// 替换消息中的模板占位符
+pub(crate) fn expand_template<'a>(message: &'a str, options: &RenderOptions) -> Cow<'a, str> {
+    match &options.template {
+        Some(template) => Cow::Owned(template.expand(message)),
+        None => Cow::Borrowed(message),
+    }
+}
+
+/// 把消息切分为普通文本和代码块，返回 `(是否为代码块, 内容)`
+///
+/// 代码块包含开头和结尾的 ```` ``` ```` 行；没有闭合的代码块延续到消息末尾。
+fn split_code_blocks(input: &str) -> Vec<(bool, String)> {
+    let mut segments: Vec<(bool, String)> = Vec::new();
+    let mut in_block = false;
+    for line in input.split('\n') {
+        let is_fence = line.trim_start().starts_with("```");
+        let verbatim = in_block || is_fence;
+        match segments.last_mut() {
+            Some((last, text)) if *last == verbatim => {
+                if !text.is_empty() || verbatim {
+                    text.push('\n');
+                }
+                text.push_str(line);
+            }
+            _ => segments.push((verbatim, line.to_string())),
+        }
+        if is_fence {
+            in_block = !in_block;
+            if !in_block {
+                // 结束的代码块与后面的代码块不能合并在一起
+                segments.push((false, String::new()));
+            }
+        }
+    }
+    segments.retain(|(verbatim, text)| *verbatim || !text.is_empty());
+    segments
+}
+
+/// 按照折行方式折行普通文本
+fn wrap_text(input: &str, options: &RenderOptions) -> String {
+    match options.wrap {
+        WrapMode::Lines => fill_text(&merge_white_spaces(input), options.max_width, options),
+        WrapMode::Paragraphs => {
+            // 空行（可以只含空白）分隔段落，段落内的单个换行被合并后重新折行
+            static SEPARATOR: OnceLock<Regex> = OnceLock::new();
+            let separator = SEPARATOR.get_or_init(|| Regex::new(r"\n\s*\n").unwrap());
+            separator
+                .split(&merge_white_spaces(input))
+                .map(|paragraph| {
+                    paragraph
+                        .split('\n')
+                        .map(str::trim)
+                        .filter(|line| !line.is_empty())
+                        .fold(String::new(), |mut joined, line| {
+                            if needs_space(&joined, line) {
+                                joined.push(' ');
+                            }
+                            joined.push_str(line);
+                            joined
+                        })
+                })
+                .filter(|paragraph| !paragraph.is_empty())
+                .map(|paragraph| fill_text(&paragraph, options.max_width, options))
+                .collect::<Vec<_>>()
+                .join("\n\n")
+        }
+        WrapMode::PreserveNewlines => input
+            .split('\n')
+            .map(|line| fill_indented(line, options))
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
+
+/// 折行一行文本，后续行沿用行首的缩进（按宽度换成空格）；缩进至少给内容留出一列
+fn fill_indented(line: &str, options: &RenderOptions) -> String {
+    let line = line.replace('\t', "    ");
+    let content = line.trim_start();
+    if content.is_empty() {
+        return String::new();
+    }
+    let indent = text_width(&line[..line.len() - content.len()], options.ambiguous_width)
+        .min(options.max_width.saturating_sub(1));
+    let width = options.max_width.saturating_sub(indent).max(1);
+    let indent = " ".repeat(indent);
+    fill_text(content.trim_end(), width, options)
+        .split('\n')
+        .map(|row| format!("{}{}", indent, row))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 按 `width` 折行一段文本
+///
+/// 按照 Unicode 换行规则（UAX #14）寻找断点：中日文在表意文字之间断开，
+/// 不会在句号、右括号等结尾标点之前断开。宽度按字位簇计算，超出宽度的单词
+/// 按字位簇拆开，emoji 序列和组合符号不会被拆散。
+fn fill_text(input: &str, width: usize, options: &RenderOptions) -> String {
+    let ambiguous = options.ambiguous_width;
+    let width = width.max(1);
+    let mut lines = Vec::new();
+    for line in input.split('\n') {
+        let mut words = Vec::new();
+        for word in WordSeparator::UnicodeBreakProperties.find_words(line) {
+            // 按字位簇拆开过长的单词，只有最后一段保留空白和连字符
+            let escapes: Vec<_> = if word.word.contains('\x1b') {
+                ansi_regex()
+                    .find_iter(word.word)
+                    .map(|m| m.range())
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            let mut start = 0;
+            let mut used = 0;
+            for (i, grapheme) in word.word.grapheme_indices(true) {
+                // 转义序列不占宽度，也不能从中间拆开
+                if escapes.iter().any(|escape| escape.contains(&i)) {
+                    continue;
+                }
+                let w = grapheme_width(grapheme, ambiguous);
+                if used + w > width && i > start {
+                    words.push(MeasuredWord::new(&word.word[start..i], "", "", ambiguous));
+                    start = i;
+                    used = 0;
+                }
+                used += w;
+            }
+            words.push(MeasuredWord::new(
+                &word.word[start..],
+                word.whitespace,
+                word.penalty,
+                ambiguous,
+            ));
+        }
+
+        if words.is_empty() {
+            lines.push(String::new());
+            continue;
+        }
+        for row in wrap_first_fit(&words, &[width as f64]) {
+            let mut text = String::new();
+            for (i, word) in row.iter().enumerate() {
+                text.push_str(word.word);
+                text.push_str(if i + 1 < row.len() {
+                    word.whitespace
+                } else {
+                    word.penalty
+                });
+            }
+            lines.push(text);
+        }
+    }
+    lines.join("\n")
+}
+
+/// 按 [`text_width`] 计算宽度的单词，`textwrap` 按字符累加宽度，
+/// 会把 emoji 序列算宽，也只会按一列计算歧义宽度字符
+#[derive(Debug)]
+struct MeasuredWord<'a> {
+    word: &'a str,
+    whitespace: &'a str,
+    penalty: &'a str,
+    widths: [usize; 3],
+}
+
+impl<'a> MeasuredWord<'a> {
+    fn new(
+        word: &'a str,
+        whitespace: &'a str,
+        penalty: &'a str,
+        ambiguous: AmbiguousWidth,
+    ) -> Self {
+        MeasuredWord {
+            word,
+            whitespace,
+            penalty,
+            widths: [word, whitespace, penalty].map(|text| text_width(text, ambiguous)),
+        }
+    }
+}
+
+impl Fragment for MeasuredWord<'_> {
+    fn width(&self) -> f64 {
+        self.widths[0] as f64
+    }
+
+    fn whitespace_width(&self) -> f64 {
+        self.widths[1] as f64
+    }
+
+    fn penalty_width(&self) -> f64 {
+        self.widths[2] as f64
+    }
+}
+
+/// 合并两行时是否需要插入空格，中日文等全角文字之间不加空格
+fn needs_space(before: &str, after: &str) -> bool {
+    let is_wide = |c: Option<char>| c.is_some_and(|c| c.width() == Some(2));
+    if before.is_empty() {
+        return false;
+    }
+    !(is_wide(before.chars().last()) && is_wide(after.chars().next()))
+}
+
+/// 统一换行符为 `\n`，避免残留的 `\r` 影响宽度计算和输出，同时去掉开头的 BOM
+pub(crate) fn normalize_line_endings(input: &str) -> String {
+    let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+    input.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+/// 是否为只设置颜色和样式的 SGR 序列（`ESC [ ... m`）
+fn is_sgr(sequence: &str) -> bool {
+    sequence
+        .strip_prefix("\x1b[")
+        .and_then(|rest| rest.strip_suffix('m'))
+        .is_some_and(|params| {
+            params
+                .bytes()
+                .all(|b| b.is_ascii_digit() || b == b';' || b == b':')
+        })
+}
+
+/// 统一换行符，并按照 `policy` 处理消息中的转义序列
+pub(crate) fn sanitize_message(message: &str, policy: AnsiPolicy) -> Result<String> {
+    let message = normalize_line_endings(message);
+    let message = match policy {
+        AnsiPolicy::Strip => strip_ansi(&message).replace(is_stray_control, ""),
+        AnsiPolicy::PassThrough => {
+            // 只保留设置颜色和样式的 SGR 序列，OSC 等其他序列可以修改窗口标题、
+            // 写入剪贴板，和其余的控制字符一起去掉
+            let mut result = String::with_capacity(message.len());
+            let mut last = 0;
+            for m in ansi_regex().find_iter(&message) {
+                result.push_str(&message[last..m.start()].replace(is_stray_control, ""));
+                if is_sgr(m.as_str()) {
+                    result.push_str(m.as_str());
+                }
+                last = m.end();
+            }
+            result.push_str(&message[last..].replace(is_stray_control, ""));
+            result
+        }
+        AnsiPolicy::Reject => {
+            if message.chars().any(is_stray_control) {
+                return Err(PixelSaysError::ControlCharacters.into());
+            }
+            message
+        }
+    };
+
+    Ok(message)
+}
+
+/// 把非 ASCII 字符替换为 `?`，转义序列（如果被保留）交给输出检查处理
+pub(crate) fn to_ascii(input: &str) -> String {
+    input
+        .chars()
+        .map(|c| if c.is_ascii() { c } else { '?' })
+        .collect()
+}
+
+pub(crate) fn longest_line(lines: &[&str], ambiguous: AmbiguousWidth) -> usize {
+    lines
+        .iter()
+        .map(|line| text_width(line, ambiguous))
+        .max()
+        .unwrap_or(0)
+}
+
+pub(crate) fn merge_white_spaces(input: &str) -> String {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| Regex::new(r"([^\S\r\n])+").unwrap());
+    re.replace_all(input, " ").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ansi_policies() {
+        let message = "\x1b[31mred\x1b[0m \x1b]0;title\x07text\x07\x1b]52;c;aGk=\x07\x1b[2J";
+
+        assert_eq!(
+            sanitize_message(message, AnsiPolicy::Strip).unwrap(),
+            "red text"
+        );
+        assert_eq!(
+            sanitize_message(message, AnsiPolicy::PassThrough).unwrap(),
+            "\x1b[31mred\x1b[0m text"
+        );
+        assert!(sanitize_message(message, AnsiPolicy::Reject).is_err());
+        assert!(sanitize_message("plain", AnsiPolicy::Reject).is_ok());
+        assert_eq!(display_width("\x1b[1mbold\x1b[0m"), 4);
+    }
+
+    #[test]
+    fn test_wrap_passes_styles_through() {
+        let options = RenderOptions {
+            max_width: 9,
+            ansi: AnsiPolicy::PassThrough,
+            ..RenderOptions::default()
+        };
+        let message = "\x1b[31mred words here\x1b[0m done \x1b[1mabcdefghijkl";
+
+        assert_eq!(
+            wrap_message(message, &options).unwrap(),
+            "\x1b[31mred words\n\x1b[31mhere\x1b[0m done\n\x1b[1mabcdefghi\n\x1b[1mjkl"
+        );
+        let lines = wrap_message(message, &options).unwrap();
+        let lines: Vec<&str> = lines.lines().collect();
+        assert_eq!(longest_line(&lines, AmbiguousWidth::Narrow), 9);
+    }
+
+    #[test]
+    fn test_code_blocks_are_verbatim() {
+        let message = "Run   this:\n```rust\nfn main() {\n\tprintln!(\"hi\");\n}\n```\nand   enjoy";
+        let options = RenderOptions {
+            max_width: 10,
+            ..RenderOptions::default()
+        };
+
+        assert_eq!(
+            wrap_message(message, &options).unwrap(),
+            "Run this:\n```rust\nfn main() {\n    println!(\"hi\");\n}\n```\nand enjoy"
+        );
+    }
+
+    #[test]
+    fn test_crlf_and_paragraphs() {
+        let message = "first line\r\nstill first\r\n\r\n\r\nsecond paragraph\r\n";
+
+        let lines = wrap_message(message, &RenderOptions::default()).unwrap();
+        assert!(!lines.contains('\r'));
+        assert_eq!(lines, "first line\nstill first\n\n\nsecond paragraph\n");
+        let bom = wrap_message("\u{feff}hi", &RenderOptions::default()).unwrap();
+        assert_eq!(bom, "hi");
+
+        let options = RenderOptions {
+            wrap: WrapMode::Paragraphs,
+            ..RenderOptions::default()
+        };
+        let paragraphs = wrap_message(message, &options).unwrap();
+        assert_eq!(paragraphs, "first line still first\n\nsecond paragraph");
+    }
+
+    #[test]
+    fn test_preserve_newlines() {
+        let poem = "Roses  are red,\n\n\n    violets are blue,\n\tsugar is sweet";
+        let options = RenderOptions {
+            max_width: 14,
+            wrap: WrapMode::PreserveNewlines,
+            ..RenderOptions::default()
+        };
+
+        assert_eq!(
+            wrap_message(poem, &options).unwrap(),
+            "Roses  are\nred,\n\n\n    violets\n    are blue,\n    sugar is\n    sweet"
+        );
+    }
+
+    #[test]
+    fn test_grapheme_clusters() {
+        let family = "\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}";
+        for emoji in [family, "👍🏽", "❤\u{fe0f}", "🇯🇵", "1\u{fe0f}\u{20e3}"] {
+            assert_eq!(text_width(emoji, AmbiguousWidth::Narrow), 2, "{}", emoji);
+        }
+        assert_eq!(text_width("cafe\u{301}", AmbiguousWidth::Narrow), 4);
+
+        // 过长的单词按字位簇拆开，不会拆散 ZWJ 序列
+        let options = RenderOptions {
+            max_width: 4,
+            ..RenderOptions::default()
+        };
+        let message = family.repeat(3);
+        let wrapped = wrap_message(&message, &options).unwrap();
+        assert_eq!(wrapped, format!("{}\n{}", family.repeat(2), family));
+
+        // 消息框右边框对齐
+        let mut output = Vec::new();
+        crate::say("hi 👋🏽 ❤\u{fe0f} 🇯🇵 ok", 40, &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let bubble: Vec<&str> = output.lines().take(2).collect();
+        assert_eq!(text_width(bubble[0], AmbiguousWidth::Narrow), 17);
+        assert_eq!(text_width(bubble[1], AmbiguousWidth::Narrow), 18);
+    }
+
+    #[test]
+    fn test_ambiguous_width() {
+        let message = "①①①① ①①";
+        assert_eq!(text_width(message, AmbiguousWidth::Narrow), 7);
+        assert_eq!(text_width(message, AmbiguousWidth::Wide), 13);
+
+        let options = RenderOptions {
+            max_width: 6,
+            ambiguous_width: AmbiguousWidth::Wide,
+            ..RenderOptions::default()
+        };
+        assert_eq!(wrap_message(message, &options).unwrap(), "①①①\n①\n①①");
+        let narrow = RenderOptions {
+            ambiguous_width: AmbiguousWidth::Narrow,
+            ..options
+        };
+        assert_eq!(wrap_message(message, &narrow).unwrap(), "①①①①\n①①");
+    }
+
+    #[test]
+    fn test_cjk_line_breaking() {
+        let options = RenderOptions {
+            max_width: 10,
+            wrap: WrapMode::Paragraphs,
+            ..RenderOptions::default()
+        };
+        let wrapped =
+            wrap_message("我们今天去公园散步，\n天气非常好。你觉得怎么样？", &options).unwrap();
+
+        assert_eq!(
+            wrapped,
+            "我们今天去\n公园散步，\n天气非常\n好。你觉得\n怎么样？"
+        );
+        for line in wrapped.lines() {
+            assert!(display_width(line) <= 10);
+        }
+    }
+}